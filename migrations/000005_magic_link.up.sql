@@ -0,0 +1,2 @@
+-- extra data bound to a token, ie. the browser a magic link was requested from.
+ALTER TABLE user_tokens ADD COLUMN IF NOT EXISTS payload JSONB;
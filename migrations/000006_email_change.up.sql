@@ -0,0 +1,2 @@
+-- access tokens issued before this point are rejected, used to sign the user out everywhere.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tokens_valid_after TIMESTAMPTZ;
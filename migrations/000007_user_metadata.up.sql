@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS user_metadata JSONB NOT NULL DEFAULT '{}'::jsonb;
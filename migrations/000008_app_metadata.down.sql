@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS users_app_metadata;
+DROP INDEX IF EXISTS users_user_metadata;
+
+ALTER TABLE users DROP COLUMN IF EXISTS app_metadata;
@@ -0,0 +1,6 @@
+-- only writable by administrators, unlike user_metadata.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS app_metadata JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+-- containment (@>) filters of the admin search.
+CREATE INDEX IF NOT EXISTS users_user_metadata ON users USING GIN (user_metadata jsonb_path_ops);
+CREATE INDEX IF NOT EXISTS users_app_metadata ON users USING GIN (app_metadata jsonb_path_ops);
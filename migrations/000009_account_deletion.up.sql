@@ -0,0 +1,4 @@
+-- soft deleted accounts are hard deleted once this point is reached, until then they can be restored.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS purge_after TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS users_purge_after ON users (purge_after) WHERE purge_after IS NOT NULL;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS users_created_at;
+
+ALTER TABLE users DROP COLUMN IF EXISTS password_reset_required;
+ALTER TABLE users DROP COLUMN IF EXISTS disabled_at;
@@ -0,0 +1,6 @@
+-- disabled accounts can't login until an administrator enables them again.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMPTZ;
+-- set by an administrator, the password stops working until it's reset through the emailed link.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS users_created_at ON users (created_at);
@@ -0,0 +1,48 @@
+CREATE TABLE IF NOT EXISTS permissions (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS roles (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id BIGINT NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    permission_id BIGINT NOT NULL REFERENCES permissions (id) ON DELETE CASCADE,
+    PRIMARY KEY (role_id, permission_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role_id BIGINT NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, role_id)
+);
+
+CREATE INDEX IF NOT EXISTS user_roles_role_id ON user_roles (role_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users:read', 'search and read user accounts'),
+    ('users:write', 'manage user accounts'),
+    ('roles:read', 'read roles and role assignments'),
+    ('roles:write', 'manage roles and role assignments')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO roles (name, description, created_at)
+VALUES ('admin', 'every permission', current_timestamp)
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions WHERE roles.name = 'admin'
+ON CONFLICT DO NOTHING;
+
+-- administrators used to be flagged with "roles": ["admin"] in app_metadata.
+INSERT INTO user_roles (user_id, role_id, created_at)
+SELECT users.id, roles.id, current_timestamp FROM users, roles
+WHERE roles.name = 'admin' AND users.app_metadata -> 'roles' ? 'admin'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,7 @@
+DELETE FROM permissions WHERE name IN ('service_accounts:read', 'service_accounts:write');
+
+DROP TABLE IF EXISTS service_account_assertions;
+DROP TABLE IF EXISTS service_account_keys;
+DROP TABLE IF EXISTS service_account_secrets;
+DROP TABLE IF EXISTS service_account_permissions;
+DROP TABLE IF EXISTS service_accounts;
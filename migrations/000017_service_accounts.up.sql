@@ -0,0 +1,56 @@
+CREATE TABLE IF NOT EXISTS service_accounts (
+    id BIGSERIAL PRIMARY KEY,
+    -- public identifier used to authenticate, along with a secret or a signed assertion.
+    client_id VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    -- owner, service accounts without one are managed by the administrators.
+    organization_id BIGINT REFERENCES organizations (id) ON DELETE CASCADE,
+    created_by BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    disabled_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS service_accounts_organization_id ON service_accounts (organization_id);
+
+CREATE TABLE IF NOT EXISTS service_account_permissions (
+    service_account_id BIGINT NOT NULL REFERENCES service_accounts (id) ON DELETE CASCADE,
+    permission_id BIGINT NOT NULL REFERENCES permissions (id) ON DELETE CASCADE,
+    PRIMARY KEY (service_account_id, permission_id)
+);
+
+CREATE TABLE IF NOT EXISTS service_account_secrets (
+    id BIGSERIAL PRIMARY KEY,
+    service_account_id BIGINT NOT NULL REFERENCES service_accounts (id) ON DELETE CASCADE,
+    prefix VARCHAR(16) NOT NULL,
+    secret_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ
+);
+
+-- public keys checking the signature of private_key_jwt assertions.
+CREATE TABLE IF NOT EXISTS service_account_keys (
+    id BIGSERIAL PRIMARY KEY,
+    service_account_id BIGINT NOT NULL REFERENCES service_accounts (id) ON DELETE CASCADE,
+    kid VARCHAR(64) NOT NULL UNIQUE,
+    algorithm VARCHAR(16) NOT NULL,
+    public_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- `jti` of the assertions already used, kept until they expire.
+CREATE TABLE IF NOT EXISTS service_account_assertions (
+    jti VARCHAR(255) PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+INSERT INTO permissions (name, description) VALUES
+    ('service_accounts:read', 'read service accounts'),
+    ('service_accounts:write', 'manage service accounts and their credentials')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions
+WHERE roles.name = 'admin' AND permissions.name IN ('service_accounts:read', 'service_accounts:write')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,6 @@
+DELETE FROM permissions WHERE name IN ('clients:read', 'clients:write');
+
+DROP TABLE IF EXISTS refresh_tokens;
+DROP TABLE IF EXISTS oauth_authorization_codes;
+DROP TABLE IF EXISTS oauth_consents;
+DROP TABLE IF EXISTS oauth_clients;
@@ -0,0 +1,72 @@
+-- applications using this service as their OAuth 2.0 authorization server.
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    id BIGSERIAL PRIMARY KEY,
+    client_id VARCHAR(64) NOT NULL UNIQUE,
+    -- NULL for public clients, they can't keep a secret and use PKCE instead.
+    client_secret_hash VARCHAR(64),
+    client_type VARCHAR(16) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    redirect_uris TEXT[] NOT NULL DEFAULT '{}',
+    allowed_scopes TEXT[] NOT NULL DEFAULT '{}',
+    grant_types TEXT[] NOT NULL DEFAULT '{}',
+    -- our own applications, the users aren't asked for their consent.
+    first_party BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    modified_at TIMESTAMPTZ,
+    disabled_at TIMESTAMPTZ
+);
+
+-- scopes a user agreed to give a client.
+CREATE TABLE IF NOT EXISTS oauth_consents (
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    client_id BIGINT NOT NULL REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL,
+    modified_at TIMESTAMPTZ,
+    PRIMARY KEY (user_id, client_id)
+);
+
+CREATE TABLE IF NOT EXISTS oauth_authorization_codes (
+    code_hash VARCHAR(64) PRIMARY KEY,
+    client_id BIGINT NOT NULL REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    redirect_uri TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    code_challenge VARCHAR(128),
+    code_challenge_method VARCHAR(16),
+    organization_id BIGINT REFERENCES organizations (id) ON DELETE CASCADE,
+    -- refresh token family of the tokens issued for the code, revoked if the code is replayed.
+    family_id VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+-- rotated on every use, the tokens descending from the same login share a family.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    family_id VARCHAR(64) NOT NULL,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- NULL for the tokens handed out by the login endpoints.
+    client_id BIGINT REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    scope TEXT NOT NULL,
+    organization_id BIGINT REFERENCES organizations (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id ON refresh_tokens (family_id);
+CREATE INDEX IF NOT EXISTS refresh_tokens_user_id ON refresh_tokens (user_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('clients:read', 'read OAuth clients'),
+    ('clients:write', 'register and manage OAuth clients')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions
+WHERE roles.name = 'admin' AND permissions.name IN ('clients:read', 'clients:write')
+ON CONFLICT DO NOTHING;
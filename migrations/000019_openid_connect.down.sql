@@ -0,0 +1,8 @@
+ALTER TABLE refresh_tokens
+    DROP COLUMN IF EXISTS amr,
+    DROP COLUMN IF EXISTS auth_time;
+
+ALTER TABLE oauth_authorization_codes
+    DROP COLUMN IF EXISTS amr,
+    DROP COLUMN IF EXISTS auth_time,
+    DROP COLUMN IF EXISTS nonce;
@@ -0,0 +1,9 @@
+-- how the user logged in, for the `auth_time` and `amr` claims of the ID tokens.
+ALTER TABLE oauth_authorization_codes
+    ADD COLUMN IF NOT EXISTS nonce TEXT,
+    ADD COLUMN IF NOT EXISTS auth_time TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS amr TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN IF NOT EXISTS auth_time TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS amr TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+DELETE FROM permissions WHERE name IN ('signing_keys:read', 'signing_keys:write');
+
+DROP TABLE IF EXISTS signing_keys;
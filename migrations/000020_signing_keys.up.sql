@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS signing_keys (
+    id BIGSERIAL PRIMARY KEY,
+    -- RFC 7638 thumbprint of the public key, the `kid` header of the tokens it signs.
+    kid VARCHAR(64) NOT NULL UNIQUE,
+    algorithm VARCHAR(16) NOT NULL,
+    -- DER encoded, PKCS#1 for RSA keys and PKCS#8 for the others.
+    private_key BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    -- published ahead of it, so verifiers caching the key set know the key once it signs.
+    activates_at TIMESTAMPTZ NOT NULL,
+    -- neither published nor accepted anymore.
+    retired_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS signing_keys_activates_at ON signing_keys (activates_at);
+
+INSERT INTO permissions (name, description) VALUES
+    ('signing_keys:read', 'read the token signing keys'),
+    ('signing_keys:write', 'rotate and retire the token signing keys')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT roles.id, permissions.id FROM roles, permissions
+WHERE roles.name = 'admin' AND permissions.name IN ('signing_keys:read', 'signing_keys:write')
+ON CONFLICT DO NOTHING;
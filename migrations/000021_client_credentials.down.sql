@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS oauth_client_keys;
+
+ALTER TABLE oauth_clients
+    DROP COLUMN IF EXISTS audiences,
+    DROP COLUMN IF EXISTS client_scopes;
@@ -0,0 +1,15 @@
+-- the client_credentials grant: the scopes granted to the client itself and the APIs
+-- its tokens are meant for, the `aud` claim.
+ALTER TABLE oauth_clients
+    ADD COLUMN IF NOT EXISTS client_scopes TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS audiences TEXT[] NOT NULL DEFAULT '{}';
+
+-- public keys checking the signature of private_key_jwt assertions.
+CREATE TABLE IF NOT EXISTS oauth_client_keys (
+    id BIGSERIAL PRIMARY KEY,
+    oauth_client_id BIGINT NOT NULL REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    kid VARCHAR(64) NOT NULL UNIQUE,
+    algorithm VARCHAR(16) NOT NULL,
+    public_key TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
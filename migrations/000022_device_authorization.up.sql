@@ -0,0 +1,21 @@
+-- RFC 8628 device authorization requests, approved by the user on another device.
+CREATE TABLE IF NOT EXISTS oauth_device_codes (
+    id BIGSERIAL PRIMARY KEY,
+    device_code_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- normalized, without the dash.
+    user_code VARCHAR(16) NOT NULL UNIQUE,
+    client_id BIGINT NOT NULL REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    scope TEXT NOT NULL,
+    -- pending until the user approves or denies it.
+    status VARCHAR(16) NOT NULL,
+    user_id BIGINT REFERENCES users (id) ON DELETE CASCADE,
+    organization_id BIGINT REFERENCES organizations (id) ON DELETE CASCADE,
+    auth_time TIMESTAMPTZ,
+    amr TEXT[] NOT NULL DEFAULT '{}',
+    -- seconds the client waits between polls, raised when it polls too often.
+    poll_interval INT NOT NULL,
+    last_polled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
@@ -0,0 +1,3 @@
+ALTER TABLE users DROP COLUMN IF EXISTS totp_last_step;
+
+ALTER TABLE user_tokens DROP COLUMN IF EXISTS failed_attempts;
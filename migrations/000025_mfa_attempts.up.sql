@@ -0,0 +1,5 @@
+-- wrong codes entered against an MFA challenge, it's burnt after a few.
+ALTER TABLE user_tokens ADD COLUMN IF NOT EXISTS failed_attempts INT NOT NULL DEFAULT 0;
+
+-- last TOTP time step accepted for the user, a code can't be used twice.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
@@ -0,0 +1,2 @@
+-- whether the authorization request named the redirect_uri, it must then be sent again at the token endpoint.
+ALTER TABLE oauth_authorization_codes ADD COLUMN IF NOT EXISTS redirect_uri_provided BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+ALTER TABLE oauth_device_codes DROP COLUMN IF EXISTS session_id;
+
+ALTER TABLE oauth_authorization_codes DROP COLUMN IF EXISTS session_id;
@@ -0,0 +1,4 @@
+-- the login that approved the request, the tokens issued for it belong to that session.
+ALTER TABLE oauth_authorization_codes ADD COLUMN IF NOT EXISTS session_id BIGINT REFERENCES sessions (id) ON DELETE CASCADE;
+
+ALTER TABLE oauth_device_codes ADD COLUMN IF NOT EXISTS session_id BIGINT REFERENCES sessions (id) ON DELETE CASCADE;
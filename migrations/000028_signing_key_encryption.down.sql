@@ -0,0 +1,2 @@
+-- the keys encrypted in the meantime can't be read anymore, retire them before rolling back.
+ALTER TABLE signing_keys DROP COLUMN IF EXISTS encrypted;
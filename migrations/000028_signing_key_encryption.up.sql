@@ -0,0 +1,3 @@
+-- private keys are encrypted with SIGNING_KEY_ENCRYPTION_KEY, the ones stored before
+-- are encrypted by the maintenance task.
+ALTER TABLE signing_keys ADD COLUMN IF NOT EXISTS encrypted BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,69 @@
+// Admin commands, run instead of the server when the binary gets arguments:
+// - export-user <user id or email> <output file>: write the personal data archive of a user
+// - grant-admin <user id or email>: give the admin role, effective from the next login
+use std::fs;
+
+use anyhow::{bail, Context};
+
+use crate::{
+    ctx::Ctx,
+    model::{user::User, ModelManager},
+    repository::{role::RoleRepository, user::UserRepository},
+    service::{constant::ROLE_ADMIN, data_export::DataExportService},
+};
+
+const USAGE: &str = "usage:
+    auth-service export-user <user id or email> <output file>
+    auth-service grant-admin <user id or email>";
+
+pub async fn run(mm: &ModelManager, args: &[String]) -> anyhow::Result<()> {
+    match args {
+        [command, user, output] if command == "export-user" => export_user(mm, user, output).await,
+        [command, user] if command == "grant-admin" => grant_admin(mm, user).await,
+        _ => bail!(USAGE),
+    }
+}
+
+async fn find_user(mm: &ModelManager, user: &str) -> anyhow::Result<User> {
+    let found = match user.parse::<i64>() {
+        Ok(id) => UserRepository::find_by_id(Ctx::root_ctx(), mm, id).await?,
+        Err(_) => UserRepository::get_by_email(Ctx::root_ctx(), mm, user).await?,
+    };
+
+    found.with_context(|| format!("no user found for {user}"))
+}
+
+async fn export_user(mm: &ModelManager, user: &str, output: &str) -> anyhow::Result<()> {
+    let user_id = find_user(mm, user).await?.id;
+
+    let archive = DataExportService::build_archive(mm, user_id)
+        .await
+        .map_err(|err| anyhow::anyhow!("failed to build the archive: {err:?}"))?;
+
+    let archive = serde_json::to_vec_pretty(&archive)?;
+    fs::write(output, archive).with_context(|| format!("failed to write {output}"))?;
+
+    println!("exported user {user_id} to {output}");
+
+    Ok(())
+}
+
+async fn grant_admin(mm: &ModelManager, user: &str) -> anyhow::Result<()> {
+    let user = find_user(mm, user).await?;
+
+    let role = RoleRepository::find_by_name(Ctx::root_ctx(), mm, ROLE_ADMIN)
+        .await?
+        .context("the admin role is missing, run the migrations first")?;
+
+    let roles = RoleRepository::roles_of_user(Ctx::root_ctx(), mm, user.id).await?;
+    if roles.iter().any(|x| x.id == role.id) {
+        println!("user {} is already an administrator", user.id);
+        return Ok(());
+    }
+
+    RoleRepository::assign(Ctx::root_ctx(), mm, user.id, role.id).await?;
+
+    println!("user {} is now an administrator", user.id);
+
+    Ok(())
+}
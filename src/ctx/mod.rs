@@ -5,20 +5,126 @@ pub use self::error::{Error, Result};
 #[derive(Debug, Clone)]
 pub struct Ctx {
     user_id: u64,
+    scopes: Vec<String>,
+    permissions: Vec<String>,
+    // `iat` of the access token, 0 when not authenticated through one.
+    issued_at: u64,
+    // tenant the request acts in, the repositories scope their queries to it when set.
+    organization_id: Option<i64>,
+    // set instead of `user_id` when the request comes from a service account.
+    service_account_id: Option<i64>,
+    // set instead of `user_id` when the request comes from an OAuth client on its own behalf.
+    oauth_client_id: Option<i64>,
+    // when and how the user logged in, the `auth_time` and `amr` claims of the access token.
+    auth_time: u64,
+    amr: Vec<String>,
+    // `jti` and `exp` of the access token, to revoke it on logout.
+    token_id: Option<String>,
+    expires_at: u64,
+    // `sid` of the access token, the session of the login.
+    session_id: Option<i64>,
+    // authenticated by an API key rather than an access token.
+    api_key: bool,
 }
 
 impl Ctx {
     pub fn root_ctx() -> Self {
-        Ctx { user_id: 0 }
+        Ctx {
+            user_id: 0,
+            scopes: Vec::new(),
+            permissions: Vec::new(),
+            issued_at: 0,
+            organization_id: None,
+            service_account_id: None,
+            oauth_client_id: None,
+            auth_time: 0,
+            amr: Vec::new(),
+            token_id: None,
+            expires_at: 0,
+            session_id: None,
+            api_key: false,
+        }
     }
 
     pub fn new(user_id: u64) -> Result<Self> {
         if user_id == 0 {
             Err(Error::CtxCannotNewRootCtx)
         } else {
-            Ok(Self { user_id })
+            Ok(Self {
+                user_id,
+                scopes: Vec::new(),
+                permissions: Vec::new(),
+                issued_at: 0,
+                organization_id: None,
+                service_account_id: None,
+                oauth_client_id: None,
+                auth_time: 0,
+                amr: Vec::new(),
+                token_id: None,
+                expires_at: 0,
+                session_id: None,
+                api_key: false,
+            })
+        }
+    }
+
+    // `user_id` stays 0, there's no user behind the request.
+    pub fn new_service_account(service_account_id: i64) -> Self {
+        Self {
+            service_account_id: Some(service_account_id),
+            ..Self::root_ctx()
+        }
+    }
+
+    // client_credentials grant, no user either.
+    pub fn new_client(oauth_client_id: i64) -> Self {
+        Self {
+            oauth_client_id: Some(oauth_client_id),
+            ..Self::root_ctx()
         }
     }
+
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: Vec<String>) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    pub fn with_issued_at(mut self, issued_at: u64) -> Self {
+        self.issued_at = issued_at;
+        self
+    }
+
+    pub fn with_organization(mut self, organization_id: Option<i64>) -> Self {
+        self.organization_id = organization_id;
+        self
+    }
+
+    pub fn with_token(mut self, token_id: Option<String>, expires_at: u64) -> Self {
+        self.token_id = token_id;
+        self.expires_at = expires_at;
+        self
+    }
+
+    pub fn with_session(mut self, session_id: Option<i64>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: bool) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn with_authentication(mut self, auth_time: u64, amr: Vec<String>) -> Self {
+        self.auth_time = auth_time;
+        self.amr = amr;
+        self
+    }
 }
 
 // Property Accessors.
@@ -26,4 +132,68 @@ impl Ctx {
     pub fn user_id(&self) -> u64 {
         self.user_id
     }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn permissions(&self) -> &[String] {
+        &self.permissions
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|x| x == permission)
+    }
+
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    pub fn organization_id(&self) -> Option<i64> {
+        self.organization_id
+    }
+
+    pub fn service_account_id(&self) -> Option<i64> {
+        self.service_account_id
+    }
+
+    pub fn oauth_client_id(&self) -> Option<i64> {
+        self.oauth_client_id
+    }
+
+    pub fn auth_time(&self) -> u64 {
+        self.auth_time
+    }
+
+    pub fn amr(&self) -> &[String] {
+        &self.amr
+    }
+
+    pub fn token_id(&self) -> Option<&str> {
+        self.token_id.as_deref()
+    }
+
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    pub fn session_id(&self) -> Option<i64> {
+        self.session_id
+    }
+
+    pub fn is_service_account(&self) -> bool {
+        self.service_account_id.is_some()
+    }
+
+    pub fn is_api_key(&self) -> bool {
+        self.api_key
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.oauth_client_id.is_some()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|x| x == scope)
+    }
 }
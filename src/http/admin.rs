@@ -0,0 +1,329 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{
+        self, admin::AdminService, oauth_client::OAuthClientService, role::RoleService,
+        signing_key::SigningKeyService,
+    },
+};
+
+use super::request::{
+    admin::{SuspendUserDTO, UserSearchQuery},
+    client::ClientMeta,
+    oauth::{AddOAuthClientKeyDTO, CreateOAuthClientDTO, UpdateOAuthClientDTO},
+    role::{CreateRoleDTO, UpdateRoleDTO},
+};
+
+pub async fn search_users(
+    State(mm): State<ModelManager>,
+    Query(query): Query<UserSearchQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::search_users(&mm, &query).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn get_user(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::get_user(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn disable_user(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::disable_user(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn enable_user(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::enable_user(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn suspend_user(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+    Json(payload): Json<SuspendUserDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::suspend_user(&mm, &ctx, id, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn unsuspend_user(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::unsuspend_user(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn force_password_reset(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::force_password_reset(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn reset_mfa(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::reset_mfa(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn revoke_sessions(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::revoke_sessions(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn restore_user(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::restore_user(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+// a JSON merge patch applied on the user's app_metadata.
+pub async fn update_app_metadata(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+    Json(payload): Json<serde_json::Value>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AdminService::update_app_metadata(&mm, &ctx, id, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_roles(State(mm): State<ModelManager>) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::list_roles(&mm).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn create_role(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<CreateRoleDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::create_role(&mm, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn get_role(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::get_role(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn update_role(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateRoleDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::update_role(&mm, id, &payload).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn delete_role(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::delete_role(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_permissions(
+    State(mm): State<ModelManager>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::list_permissions(&mm).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn get_user_roles(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::user_roles(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn assign_role(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path((id, role_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::assign_role(&mm, &ctx, id, role_id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn unassign_role(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path((id, role_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = RoleService::unassign_role(&mm, &ctx, id, role_id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_clients(State(mm): State<ModelManager>) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::list(&mm).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn create_client(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<CreateOAuthClientDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::create(&mm, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn get_client(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::get(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn update_client(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateOAuthClientDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::update(&mm, id, &payload).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn rotate_client_secret(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::rotate_secret(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn delete_client(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::delete(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_client_keys(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::list_keys(&mm, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn add_client_key(
+    State(mm): State<ModelManager>,
+    Path(id): Path<i64>,
+    Json(payload): Json<AddOAuthClientKeyDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::add_key(&mm, id, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn delete_client_key(
+    State(mm): State<ModelManager>,
+    Path((id, key_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthClientService::delete_key(&mm, id, key_id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_signing_keys(
+    State(mm): State<ModelManager>,
+) -> service::Result<impl IntoResponse> {
+    let resp = SigningKeyService::list(&mm).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn rotate_signing_key(
+    State(mm): State<ModelManager>,
+) -> service::Result<impl IntoResponse> {
+    let resp = SigningKeyService::rotate(&mm).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn retire_signing_key(
+    State(mm): State<ModelManager>,
+    Path(kid): Path<String>,
+) -> service::Result<impl IntoResponse> {
+    let resp = SigningKeyService::retire(&mm, &kid).await?;
+
+    Ok(Json(resp))
+}
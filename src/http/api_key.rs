@@ -0,0 +1,45 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{self, api_key::ApiKeyService},
+};
+
+use super::request::{api_key::CreateApiKeyDTO, client::ClientMeta};
+
+pub async fn create_api_key(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Json(payload): Json<CreateApiKeyDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ApiKeyService::create(&mm, &ctx, &payload, &client).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn list_api_keys(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ApiKeyService::list(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn delete_api_key(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ApiKeyService::delete(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
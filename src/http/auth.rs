@@ -1,17 +1,29 @@
 use crate::{
     ctx::Ctx,
     model::ModelManager,
-    service::{self, auth, user::UserService},
+    service::{
+        self,
+        auth::{self, cookie_session},
+        email_verification::EmailVerificationService,
+        password_reset::PasswordResetService,
+        session::SessionService,
+        user::UserService,
+    },
 };
 use axum::{
     extract::{Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
 
 use super::request::{
-    google::AuthRequest,
-    user::{CreateUserDTO, LoginDTO},
+    client::ClientMeta,
+    google::{AuthRequest, GoogleLoginQuery},
+    user::{
+        CreateUserDTO, LoginDTO, LogoutDTO, MagicLinkDTO, MagicLinkQuery, ResendVerificationDTO,
+        ResetPasswordDTO, VerifyEmailQuery, VerifyMfaDTO,
+    },
 };
 use axum_extra::extract::cookie::CookieJar;
 
@@ -19,9 +31,10 @@ use axum_extra::extract::cookie::CookieJar;
 // ref: https://github.com/JoeyMckenzie/realworld-rust-axum-sqlx/blob/main/crates/conduit-api/src/extractors/validation_extractor.rs
 pub async fn create_user(
     State(mm): State<ModelManager>,
+    client: ClientMeta,
     Json(payload): Json<CreateUserDTO>,
 ) -> service::Result<impl IntoResponse> {
-    let user = UserService::create_user(
+    let resp = UserService::create_user(
         &mm,
         &CreateUserDTO {
             name: payload.name,
@@ -30,24 +43,135 @@ pub async fn create_user(
             auth_provider: None,
             auth_provider_user_id: None,
             secret: None,
+            organization: payload.organization,
+            invitation: payload.invitation,
         },
+        &client,
     )
     .await?;
 
-    Ok(Json(user))
+    Ok((StatusCode::ACCEPTED, Json(resp)))
 }
 
 pub async fn login(
     State(mm): State<ModelManager>,
+    cookies: CookieJar,
+    client: ClientMeta,
     Json(payload): Json<LoginDTO>,
 ) -> service::Result<impl IntoResponse> {
-    let user = UserService::login(&mm, payload.email, payload.password).await?;
+    let user = UserService::login(
+        &mm,
+        payload.email,
+        payload.password,
+        payload.organization.as_deref(),
+        &client,
+    )
+    .await?;
+    let (cookies, user) = cookie_session::issue(cookies, user);
+
+    Ok((cookies, Json(user)))
+}
+
+// the body is optional, without a refresh token only the access token is revoked.
+pub async fn logout(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    cookies: CookieJar,
+    client: ClientMeta,
+    payload: Option<Json<LogoutDTO>>,
+) -> service::Result<impl IntoResponse> {
+    let payload = payload.map(|Json(x)| x).unwrap_or_default();
+    let resp = SessionService::logout(&mm, &ctx, &payload, &client).await?;
 
-    Ok(Json(user))
+    Ok((cookie_session::clear(cookies), Json(resp)))
 }
 
-pub async fn google_oauth_login() -> service::Result<impl IntoResponse> {
-    let resp = auth::google::login().await?;
+pub async fn logout_all(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    cookies: CookieJar,
+    client: ClientMeta,
+) -> service::Result<impl IntoResponse> {
+    let resp = SessionService::logout_all(&mm, &ctx, &client).await?;
+
+    Ok((cookie_session::clear(cookies), Json(resp)))
+}
+
+// AUTH_COOKIES=true, exchanges the refresh token cookie for new cookies.
+pub async fn refresh_session(
+    State(mm): State<ModelManager>,
+    cookies: CookieJar,
+    client: ClientMeta,
+) -> service::Result<impl IntoResponse> {
+    let (cookies, resp) = cookie_session::refresh(&mm, cookies, &client).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], cookies, Json(resp)))
+}
+
+pub async fn verify_mfa(
+    State(mm): State<ModelManager>,
+    cookies: CookieJar,
+    client: ClientMeta,
+    Json(payload): Json<VerifyMfaDTO>,
+) -> service::Result<impl IntoResponse> {
+    let user = UserService::verify_mfa(&mm, &payload.mfa_token, &payload.code, &client).await?;
+    let (cookies, user) = cookie_session::issue(cookies, user);
+
+    Ok((cookies, Json(user)))
+}
+
+pub async fn request_magic_link(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<MagicLinkDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = auth::magic_link::request(mm, payload.email, payload.organization).await?;
+
+    Ok(resp)
+}
+
+pub async fn verify_magic_link(
+    State(mm): State<ModelManager>,
+    cookies: CookieJar,
+    client: ClientMeta,
+    Query(payload): Query<MagicLinkQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = auth::magic_link::verify(mm, cookies, client, payload).await?;
+
+    Ok(resp)
+}
+
+pub async fn verify_email(
+    State(mm): State<ModelManager>,
+    Query(payload): Query<VerifyEmailQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = EmailVerificationService::verify(&mm, &payload.token).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn resend_verification(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<ResendVerificationDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = EmailVerificationService::resend(&mm, payload.email, payload.organization);
+
+    Ok((StatusCode::ACCEPTED, Json(resp)))
+}
+
+pub async fn reset_password(
+    State(mm): State<ModelManager>,
+    client: ClientMeta,
+    Json(payload): Json<ResetPasswordDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = PasswordResetService::reset(&mm, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn google_oauth_login(
+    Query(payload): Query<GoogleLoginQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = auth::google::login(payload.invitation, payload.organization).await?;
 
     Ok(resp)
 }
@@ -55,9 +179,10 @@ pub async fn google_oauth_login() -> service::Result<impl IntoResponse> {
 pub async fn google_oauth_callback(
     State(mm): State<ModelManager>,
     cookies: CookieJar,
+    client: ClientMeta,
     Query(payload): Query<AuthRequest>,
 ) -> service::Result<impl IntoResponse> {
-    let resp = auth::google::callback(mm, cookies, payload).await?;
+    let resp = auth::google::callback(mm, cookies, client, payload).await?;
 
     Ok(resp)
 }
@@ -65,8 +190,9 @@ pub async fn google_oauth_callback(
 pub async fn allow_mfa(
     State(mm): State<ModelManager>,
     Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
 ) -> service::Result<impl IntoResponse> {
-    let resp = UserService::set_mfa(&mm, &ctx).await?;
+    let resp = UserService::set_mfa(&mm, &ctx, &client).await?;
 
     Ok(Json(resp))
 }
@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{
+        self, account_deletion::AccountDeletionService, data_export::DataExportService,
+        email_change::EmailChangeService, session::SessionService, user::UserService,
+    },
+};
+
+use super::request::{
+    client::ClientMeta,
+    user::{ChangeEmailDTO, DeleteAccountDTO, EmailChangeQuery, RestoreAccountQuery},
+};
+
+pub async fn get_me(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = UserService::get_profile(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
+
+// accepts `application/merge-patch+json` as well as plain json.
+pub async fn update_me(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Json(payload): Json<serde_json::Value>,
+) -> service::Result<impl IntoResponse> {
+    let resp = UserService::update_profile(&mm, &ctx, &payload).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn delete_me(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Json(payload): Json<DeleteAccountDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AccountDeletionService::delete(&mm, &ctx, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn restore_account(
+    State(mm): State<ModelManager>,
+    client: ClientMeta,
+    Query(payload): Query<RestoreAccountQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = AccountDeletionService::restore(&mm, &payload.token, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn change_email(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Json(payload): Json<ChangeEmailDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = EmailChangeService::request(&mm, &ctx, &payload).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(resp)))
+}
+
+pub async fn confirm_email_change(
+    State(mm): State<ModelManager>,
+    client: ClientMeta,
+    Query(payload): Query<EmailChangeQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = EmailChangeService::confirm(&mm, &payload.token, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn cancel_email_change(
+    State(mm): State<ModelManager>,
+    Query(payload): Query<EmailChangeQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = EmailChangeService::cancel(&mm, &payload.token).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn request_export(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+) -> service::Result<impl IntoResponse> {
+    let resp = DataExportService::request(&mm, &ctx, &client).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(resp)))
+}
+
+pub async fn get_export(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = DataExportService::get(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn download_export(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let archive = DataExportService::download(&mm, &ctx, id).await?;
+    let disposition = format!("attachment; filename=\"export-{}.json\"", id);
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(archive)))
+}
+
+pub async fn list_sessions(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = SessionService::list(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn revoke_session(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = SessionService::revoke(&mm, &ctx, id, &client).await?;
+
+    Ok(Json(resp))
+}
@@ -1,60 +1,390 @@
-use std::env;
+use std::{env, net::SocketAddr};
 
 use axum::{
-    extract::Request,
+    extract::{ConnectInfo, Request, State},
     http::{header, StatusCode},
     middleware::Next,
     response::Response,
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use log::info;
 
-use crate::{ctx::Ctx, http::Error, model::user::CustomTokenClaims};
-
-pub async fn jwt_auth(mut request: Request, next: Next) -> Result<Response, Error> {
-    #[allow(non_snake_case)]
-    let JWTKEY: String = env::var("JWT_SECRET").expect("JWT_SECRET was not set in the environment");
+use crate::{
+    ctx::Ctx,
+    http::{middleware::rate_limit::client_ip, Error},
+    model::{user::CustomTokenClaims, ModelManager},
+    repository::{
+        oauth_client::OAuthClientRepository, organization::OrganizationRepository,
+        role::RoleRepository, service_account::ServiceAccountRepository,
+        session::SessionRepository, user::UserRepository,
+    },
+    service::{
+        api_key::ApiKeyService,
+        auth::cookie_session,
+        constant::{
+            API_KEY_PREFIX, GRANT_TYPE_CLIENT_CREDENTIALS, PRINCIPAL_CLIENT,
+            PRINCIPAL_SERVICE_ACCOUNT,
+        },
+        revocation::RevocationService,
+        signing_key::SigningKeyService,
+        suspension_message,
+    },
+};
 
+pub async fn jwt_auth(
+    State(mm): State<ModelManager>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, Error> {
     // get the token first
     let token = request
         .headers()
         .get(header::AUTHORIZATION)
         .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_header| {
-            if auth_header.starts_with("Bearer ") {
-                Some(auth_header[7..].to_owned())
-            } else {
-                None
+        .and_then(|auth_header| auth_header.strip_prefix("Bearer "))
+        .map(|token| token.to_owned());
+
+    // AUTH_COOKIES=true, the browser sends the cookie along with any request to the API,
+    // the ones changing something have to prove they come from the app.
+    let token = match token {
+        Some(token) => Some(token),
+        None => {
+            let token = cookie_session::access_token(request.headers());
+
+            if token.is_some() && !cookie_session::verify_csrf(request.method(), request.headers())
+            {
+                return Err(Error {
+                    status_code: StatusCode::FORBIDDEN,
+                    message: String::from("Invalid CSRF token"),
+                });
             }
-        });
+
+            token
+        }
+    };
 
     let token = token.ok_or_else(|| Error {
         status_code: StatusCode::UNAUTHORIZED,
         message: String::from("Please login first"),
     })?;
 
+    // API keys go through the same checks as the claims of an access token.
+    let is_api_key = token.starts_with(API_KEY_PREFIX);
+
     // authenticate the token
-    let claims = decode::<CustomTokenClaims>(
-        token.as_ref(),
-        &DecodingKey::from_secret(JWTKEY.as_ref()),
-        &Validation::default(),
-    )
-    .map_err(|e| {
-        info!("Error decoding JWT: {}", e);
-        Error {
-            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+    let claims = match is_api_key {
+        true => {
+            let peer = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(x)| *x);
+            let ip = client_ip(request.headers(), peer);
+
+            ApiKeyService::authenticate(&mm, &token, ip.as_deref())
+                .await
+                .map_err(|e| {
+                    info!("Error authenticating API key: {:?}", e);
+                    Error {
+                        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                        message: String::from("failed to authenticate API key"),
+                    }
+                })?
+                .ok_or_else(|| Error {
+                    status_code: StatusCode::UNAUTHORIZED,
+                    message: String::from("Invalid API key"),
+                })?
+        }
+        // signed by one of the published keys, see `SigningKeyService`. The tokens of the
+        // client_credentials grant are only good here when this API is one of their audiences.
+        false => SigningKeyService::verify::<CustomTokenClaims>(
+            &mm,
+            &token,
+            Some(&env::var("BASE_URL").unwrap_or_default()),
+        )
+        .await
+        .map_err(|e| {
+            info!("Error loading signing keys: {:?}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to verify token"),
+            }
+        })?
+        .ok_or_else(|| Error {
+            status_code: StatusCode::UNAUTHORIZED,
             message: String::from("Invalid token"),
+        })?,
+    };
+
+    // logged out or revoked through `/revoke` before it expired.
+    if let Some(jti) = &claims.jti {
+        let revoked = RevocationService::is_revoked(&mm, jti).await.map_err(|e| {
+            info!("Error checking revoked tokens: {:?}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to verify token"),
+            }
+        })?;
+
+        if revoked {
+            return Err(Error {
+                status_code: StatusCode::UNAUTHORIZED,
+                message: String::from("Token has been revoked"),
+            });
+        }
+    }
+
+    let (token_id, expires_at) = (claims.jti.clone(), claims.exp as u64);
+    let ctx = match claims.principal.as_deref() {
+        Some(PRINCIPAL_SERVICE_ACCOUNT) => service_account_ctx(&mm, claims).await?,
+        Some(PRINCIPAL_CLIENT) => client_ctx(&mm, claims).await?,
+        _ => user_ctx(&mm, claims, is_api_key).await?,
+    };
+
+    request
+        .extensions_mut()
+        .insert(ctx.with_token(token_id, expires_at));
+
+    Ok(next.run(request).await)
+}
+
+async fn user_ctx(
+    mm: &ModelManager,
+    claims: CustomTokenClaims,
+    is_api_key: bool,
+) -> Result<Ctx, Error> {
+    // the user could have been deleted or signed out everywhere since the token was issued,
+    // API keys are kept until deleted by the user.
+    let user = UserRepository::find_by_id(Ctx::root_ctx(), mm, claims.sub as i64)
+        .await
+        .map_err(|e| {
+            info!("Error loading token user: {}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to load user"),
+            }
+        })?;
+
+    let is_revoked = user.as_ref().is_none_or(|x| {
+        !is_api_key
+            && x.tokens_valid_after
+                .is_some_and(|after| (claims.iat as i64) < after.unix_timestamp())
+    });
+
+    let user = match user {
+        Some(user) if !is_revoked => user,
+        _ => {
+            return Err(Error {
+                status_code: StatusCode::UNAUTHORIZED,
+                message: String::from("Token has been revoked"),
+            })
+        }
+    };
+
+    if user.disabled_at.is_some() {
+        return Err(Error {
+            status_code: StatusCode::UNAUTHORIZED,
+            message: String::from("account is disabled"),
+        });
+    }
+
+    if user.is_suspended() {
+        return Err(Error {
+            status_code: StatusCode::FORBIDDEN,
+            message: suspension_message(user.suspension_reason, user.suspended_until),
+        });
+    }
+
+    // roles could have been taken away since the token was issued, only the permissions
+    // the user still has are kept. Tokens without permissions skip the lookup.
+    let permissions = match claims.permissions.is_empty() {
+        true => Vec::new(),
+        false => {
+            let current = RoleRepository::permissions_of_user(Ctx::root_ctx(), mm, user.id)
+                .await
+                .map_err(|e| {
+                    info!("Error loading token user permissions: {}", e);
+                    Error {
+                        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                        message: String::from("failed to load user"),
+                    }
+                })?;
+
+            claims
+                .permissions
+                .into_iter()
+                .filter(|x| current.contains(x))
+                .collect()
+        }
+    };
+
+    // signed out of the device the token was issued to, see `SessionService`.
+    if let Some(session_id) = claims.sid {
+        let session = SessionRepository::find_active(Ctx::root_ctx(), mm, session_id)
+            .await
+            .map_err(|e| {
+                info!("Error loading token session: {}", e);
+                Error {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    message: String::from("failed to load session"),
+                }
+            })?;
+
+        if session.is_none_or(|x| x.user_id != user.id) {
+            return Err(Error {
+                status_code: StatusCode::UNAUTHORIZED,
+                message: String::from("Session has been revoked"),
+            });
+        }
+
+        SessionRepository::touch(Ctx::root_ctx(), mm, session_id)
+            .await
+            .map_err(|e| {
+                info!("Error updating token session: {}", e);
+                Error {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    message: String::from("failed to load session"),
+                }
+            })?;
+    }
+
+    // the user could have left the organization the token acts in.
+    if let Some(organization_id) = claims.org {
+        let member = OrganizationRepository::find_member(
+            Ctx::root_ctx().with_organization(Some(organization_id)),
+            mm,
+            user.id,
+        )
+        .await
+        .map_err(|e| {
+            info!("Error loading token user membership: {}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to load user"),
+            }
+        })?;
+
+        if member.is_none() {
+            return Err(Error {
+                status_code: StatusCode::FORBIDDEN,
+                message: String::from("no longer a member of the organization"),
+            });
         }
-    })?
-    .claims;
+    }
+
+    Ok(Ctx::new(claims.sub)
+        .map_err(|_| Error {
+            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "failed to create context".to_string(),
+        })?
+        .with_scopes(claims.scope.split_whitespace().map(String::from).collect())
+        .with_permissions(permissions)
+        .with_issued_at(claims.iat as u64)
+        .with_organization(claims.org)
+        .with_session(claims.sid)
+        .with_api_key(is_api_key)
+        .with_authentication(claims.auth_time.unwrap_or(claims.iat) as u64, claims.amr))
+}
 
-    // save it into the context.
-    let ctx = Ctx::new(claims.sub).map_err(|_| Error {
-        status_code: StatusCode::INTERNAL_SERVER_ERROR,
-        message: "failed to create context".to_string(),
+// Route layer put behind `jwt_auth`, for what only the user logged in can do: managing
+// API keys and credentials, approving OAuth clients, deleting the account and so on.
+// usage: `axum_middleware::from_fn(reject_api_key)`
+pub async fn reject_api_key(request: Request, next: Next) -> Result<Response, Error> {
+    let ctx = request.extensions().get::<Ctx>().ok_or_else(|| Error {
+        status_code: StatusCode::UNAUTHORIZED,
+        message: String::from("Please login first"),
     })?;
 
-    request.extensions_mut().insert(ctx);
+    if ctx.is_api_key() {
+        return Err(Error {
+            status_code: StatusCode::FORBIDDEN,
+            message: String::from("API keys can't be used here, login first"),
+        });
+    }
 
     Ok(next.run(request).await)
 }
+
+// service accounts are checked the same way, they could have been disabled or
+// lost permissions since the token was issued.
+async fn service_account_ctx(mm: &ModelManager, claims: CustomTokenClaims) -> Result<Ctx, Error> {
+    let service_account =
+        ServiceAccountRepository::find_by_id(Ctx::root_ctx(), mm, claims.sub as i64)
+            .await
+            .map_err(|e| {
+                info!("Error loading token service account: {}", e);
+                Error {
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                    message: String::from("failed to load service account"),
+                }
+            })?;
+
+    let service_account = match service_account {
+        Some(x) if x.disabled_at.is_none() => x,
+        _ => {
+            return Err(Error {
+                status_code: StatusCode::UNAUTHORIZED,
+                message: String::from("service account is disabled"),
+            })
+        }
+    };
+
+    let current = ServiceAccountRepository::permissions_of(Ctx::root_ctx(), mm, service_account.id)
+        .await
+        .map_err(|e| {
+            info!("Error loading token service account permissions: {}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to load service account"),
+            }
+        })?;
+
+    let permissions = claims
+        .permissions
+        .into_iter()
+        .filter(|x| current.contains(x))
+        .collect();
+
+    Ok(Ctx::new_service_account(service_account.id)
+        .with_permissions(permissions)
+        .with_issued_at(claims.iat as u64)
+        .with_organization(service_account.organization_id))
+}
+
+// the client could have been disabled, lost the grant or some of its scopes since.
+async fn client_ctx(mm: &ModelManager, claims: CustomTokenClaims) -> Result<Ctx, Error> {
+    let oauth_client = OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, claims.sub as i64)
+        .await
+        .map_err(|e| {
+            info!("Error loading token client: {}", e);
+            Error {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: String::from("failed to load client"),
+            }
+        })?;
+
+    let oauth_client = match oauth_client {
+        Some(x)
+            if x.disabled_at.is_none()
+                && x.allows_grant(GRANT_TYPE_CLIENT_CREDENTIALS)
+                && claims.client_id.as_deref() == Some(x.client_id.as_str()) =>
+        {
+            x
+        }
+        _ => {
+            return Err(Error {
+                status_code: StatusCode::UNAUTHORIZED,
+                message: String::from("client is disabled"),
+            })
+        }
+    };
+
+    let scopes = claims
+        .scope
+        .split_whitespace()
+        .filter(|x| oauth_client.client_scopes.iter().any(|scope| scope == x))
+        .map(String::from)
+        .collect();
+
+    Ok(Ctx::new_client(oauth_client.id)
+        .with_scopes(scopes)
+        .with_issued_at(claims.iat as u64))
+}
@@ -1 +1,4 @@
 pub mod jwt;
+pub mod permission;
+pub mod rate_limit;
+pub mod scope;
@@ -0,0 +1,30 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{ctx::Ctx, http::Error};
+
+// Route layer put behind `jwt_auth`, rejects callers without the permission.
+// usage: `axum_middleware::from_fn_with_state(PERMISSION_USERS_READ, require_permission)`
+pub async fn require_permission(
+    State(permission): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let ctx = request.extensions().get::<Ctx>().ok_or_else(|| Error {
+        status_code: StatusCode::UNAUTHORIZED,
+        message: String::from("Please login first"),
+    })?;
+
+    if !ctx.has_permission(permission) {
+        return Err(Error {
+            status_code: StatusCode::FORBIDDEN,
+            message: format!("missing the {permission} permission"),
+        });
+    }
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,108 @@
+use super::RateLimitPolicy;
+
+// Outcome of taking one token out of a bucket, used to fill the RateLimit-* headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // seconds until the bucket is full again.
+    pub reset_after: f64,
+    // seconds until the next token is available, only meaningful when not allowed.
+    pub retry_after: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    // unix timestamp in seconds of the last refill.
+    pub updated_at: f64,
+}
+
+impl TokenBucket {
+    pub fn full(policy: &RateLimitPolicy, now: f64) -> Self {
+        Self {
+            tokens: policy.capacity as f64,
+            updated_at: now,
+        }
+    }
+
+    // refill the bucket according to the elapsed time, then try to consume a single token.
+    pub fn take(&mut self, policy: &RateLimitPolicy, now: f64) -> Decision {
+        let capacity = policy.capacity as f64;
+        let elapsed = (now - self.updated_at).max(0.0);
+
+        self.tokens = (self.tokens + elapsed * policy.refill_per_second).min(capacity);
+        self.updated_at = now;
+
+        let allowed = self.tokens >= 1.0;
+        if allowed {
+            self.tokens -= 1.0;
+        }
+
+        let retry_after = match allowed {
+            true => 0.0,
+            false => (1.0 - self.tokens) / policy.refill_per_second,
+        };
+
+        Decision {
+            allowed,
+            limit: policy.capacity,
+            remaining: self.tokens.floor() as u32,
+            reset_after: (capacity - self.tokens) / policy.refill_per_second,
+            retry_after,
+        }
+    }
+
+    // a bucket that would be full by now carries no state and can be dropped.
+    pub fn is_idle(&self, policy: &RateLimitPolicy, now: f64) -> bool {
+        let capacity = policy.capacity as f64;
+        self.tokens + (now - self.updated_at) * policy.refill_per_second >= capacity
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::TokenBucket;
+    use crate::http::middleware::rate_limit::{RateLimitKey, RateLimitPolicy};
+
+    fn policy() -> RateLimitPolicy {
+        // 3 requests, refilled over 30 seconds -> 1 token every 10 seconds.
+        RateLimitPolicy::new("test", RateLimitKey::Ip, 3, Duration::from_secs(30))
+    }
+
+    #[test]
+    fn take_until_empty() {
+        let policy = policy();
+        let mut bucket = TokenBucket::full(&policy, 0.0);
+
+        assert_eq!(bucket.take(&policy, 0.0).remaining, 2);
+        assert_eq!(bucket.take(&policy, 0.0).remaining, 1);
+        assert_eq!(bucket.take(&policy, 0.0).remaining, 0);
+
+        let denied = bucket.take(&policy, 0.0);
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, 10.0);
+        assert_eq!(denied.reset_after, 30.0);
+    }
+
+    #[test]
+    fn refill_is_capped() {
+        let policy = policy();
+        let mut bucket = TokenBucket::full(&policy, 0.0);
+        bucket.take(&policy, 0.0);
+        bucket.take(&policy, 0.0);
+
+        let decision = bucket.take(&policy, 5.0);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+
+        assert!(!bucket.is_idle(&policy, 10.0));
+        assert!(bucket.is_idle(&policy, 1000.0));
+
+        let decision = bucket.take(&policy, 1000.0);
+        assert_eq!(decision.remaining, 2);
+    }
+}
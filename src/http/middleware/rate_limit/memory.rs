@@ -0,0 +1,55 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use async_trait::async_trait;
+
+use super::{
+    bucket::{Decision, TokenBucket},
+    unix_now, RateLimitPolicy, RateLimitStore,
+};
+
+// how often (in calls) idle buckets get swept out of the map.
+const SWEEP_EVERY: u64 = 1024;
+
+// Single instance backend, every process keeps its own buckets.
+// buckets are grouped by policy name so idle ones can be swept with the right refill rate.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<&'static str, HashMap<String, TokenBucket>>>,
+    calls: AtomicU64,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision> {
+        let now = unix_now();
+        let mut policies = self.buckets.lock().expect("rate limit store poisoned");
+        let buckets = policies.entry(policy.name).or_default();
+
+        if self
+            .calls
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(SWEEP_EVERY)
+        {
+            buckets.retain(|_, bucket| !bucket.is_idle(policy, now));
+        }
+
+        let decision = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::full(policy, now))
+            .take(policy, now);
+
+        Ok(decision)
+    }
+}
@@ -0,0 +1,343 @@
+// Token bucket rate limiting for the public endpoints.
+// Every policy owns a bucket per key (client ip, email in the json body or the route itself),
+// a request is rejected as soon as one of the policies attached to the route runs dry.
+// The response carries the RateLimit-* headers (draft-ietf-httpapi-ratelimit-headers)
+// of the tightest policy, and a Retry-After header when rejected.
+
+mod bucket;
+mod memory;
+mod postgres;
+
+use std::{
+    convert::Infallible,
+    env,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::{database::DB, http::Error};
+
+pub use self::{bucket::Decision, memory::MemoryStore, postgres::PostgresStore};
+
+// the body is buffered to look up the email, anything bigger isn't a login form.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    // the client address, see `client_ip`.
+    Ip,
+    // the `email` field of the json body, requests without one are not counted.
+    Email,
+    // the matched route, a global budget shared by every caller.
+    Route,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub name: &'static str,
+    pub key: RateLimitKey,
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+impl RateLimitPolicy {
+    // allow a burst of `capacity` requests, the bucket fully refills over `per`.
+    pub fn new(name: &'static str, key: RateLimitKey, capacity: u32, per: Duration) -> Self {
+        Self {
+            name,
+            key,
+            capacity,
+            refill_per_second: capacity as f64 / per.as_secs_f64(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision>;
+}
+
+// RATE_LIMIT_BACKEND=postgres shares the buckets between instances, memory is the default.
+pub fn store_from_env(db: &DB) -> Arc<dyn RateLimitStore> {
+    match env::var("RATE_LIMIT_BACKEND").as_deref() {
+        Ok("postgres") => Arc::new(PostgresStore::new(db.clone())),
+        _ => Arc::new(MemoryStore::new()),
+    }
+}
+
+// TRUSTED_PROXY_HOPS, the number of proxies in front of the service.
+fn trusted_proxy_hops() -> usize {
+    env::var("TRUSTED_PROXY_HOPS")
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(1)
+}
+
+// Each proxy appends the address it got the request from, the entries on the left come
+// from the client and can be anything. The one added by the outermost trusted proxy is
+// `hops` entries from the right, `None` when the request didn't go through all of them.
+fn forwarded_ip(header: &str, hops: usize) -> Option<String> {
+    let entries: Vec<&str> = header.split(',').map(str::trim).collect();
+
+    entries
+        .len()
+        .checked_sub(hops)
+        .and_then(|x| entries.get(x))
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+}
+
+// Address of the caller. X-Forwarded-For is only trusted when TRUST_PROXY_HEADERS=true,
+// otherwise it's the peer address, which requires serving with `into_make_service_with_connect_info`.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    let trust_proxy = env::var("TRUST_PROXY_HEADERS").is_ok_and(|x| x == "true");
+
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| forwarded_ip(x, trusted_proxy_hops()));
+
+    match forwarded {
+        Some(ip) if trust_proxy => Some(ip),
+        _ => peer.map(|x| x.ip().to_string()),
+    }
+}
+
+pub(super) fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    store: Arc<dyn RateLimitStore>,
+    policies: Arc<Vec<RateLimitPolicy>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(store: Arc<dyn RateLimitStore>) -> Self {
+        Self {
+            store,
+            policies: Arc::new(Vec::new()),
+        }
+    }
+
+    pub fn policy(mut self, policy: RateLimitPolicy) -> Self {
+        Arc::make_mut(&mut self.policies).push(policy);
+        self
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            store: self.store.clone(),
+            policies: self.policies.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    store: Arc<dyn RateLimitStore>,
+    policies: Arc<Vec<RateLimitPolicy>>,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // the service that was polled ready is the one that has to handle the request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let store = self.store.clone();
+        let policies = self.policies.clone();
+
+        Box::pin(async move {
+            let (request, keys) = match extract_keys(request, &policies).await {
+                Ok(x) => x,
+                Err(response) => return Ok(response),
+            };
+
+            let mut tightest: Option<Decision> = None;
+            let mut denied: Option<Decision> = None;
+
+            for (policy, key) in policies.iter().zip(keys) {
+                let Some(key) = key else {
+                    continue;
+                };
+
+                // an unavailable store shouldn't take the login down with it.
+                let decision = match store
+                    .take(&format!("{}:{}", policy.name, key), policy)
+                    .await
+                {
+                    Ok(x) => x,
+                    Err(err) => {
+                        warn!("rate limit store failed for {}: {:?}", policy.name, err);
+                        continue;
+                    }
+                };
+
+                if !decision.allowed
+                    && denied
+                        .as_ref()
+                        .is_none_or(|x| x.retry_after < decision.retry_after)
+                {
+                    denied = Some(decision.clone());
+                }
+
+                if tightest
+                    .as_ref()
+                    .is_none_or(|x| x.remaining > decision.remaining)
+                {
+                    tightest = Some(decision);
+                }
+            }
+
+            if let Some(decision) = denied {
+                let mut response = Error::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    String::from("too many requests, please try again later"),
+                )
+                .into_response();
+
+                set_headers(response.headers_mut(), &decision);
+                response.headers_mut().insert(
+                    header::RETRY_AFTER,
+                    HeaderValue::from(decision.retry_after.ceil() as u64),
+                );
+
+                return Ok(response);
+            }
+
+            let mut response = inner.call(request).await?;
+
+            if let Some(decision) = tightest {
+                set_headers(response.headers_mut(), &decision);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+async fn extract_keys(
+    request: Request,
+    policies: &[RateLimitPolicy],
+) -> Result<(Request, Vec<Option<String>>), Response> {
+    let needs_email = policies.iter().any(|x| x.key == RateLimitKey::Email);
+
+    let (request, email) = match needs_email {
+        true => {
+            let (parts, body) = request.into_parts();
+            let bytes = to_bytes(body, MAX_BODY_SIZE).await.map_err(|_| {
+                Error::new(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    String::from("request body is too large"),
+                )
+                .into_response()
+            })?;
+
+            let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+                .ok()
+                .and_then(|x| x.get("email")?.as_str().map(|x| x.trim().to_lowercase()))
+                .filter(|x| !x.is_empty());
+
+            (Request::from_parts(parts, Body::from(bytes)), email)
+        }
+        false => (request, None),
+    };
+
+    let keys = policies
+        .iter()
+        .map(|policy| match policy.key {
+            RateLimitKey::Ip => client_ip(
+                request.headers(),
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(x)| *x),
+            ),
+            RateLimitKey::Email => email.clone(),
+            RateLimitKey::Route => Some(
+                request
+                    .extensions()
+                    .get::<MatchedPath>()
+                    .map(|x| x.as_str().to_string())
+                    .unwrap_or_else(|| request.uri().path().to_string()),
+            ),
+        })
+        .collect();
+
+    Ok((request, keys))
+}
+
+fn set_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(
+        RATELIMIT_RESET,
+        HeaderValue::from(decision.reset_after.ceil() as u64),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::forwarded_ip;
+
+    #[test]
+    fn forwarded_ip_takes_the_entry_of_the_trusted_proxy() {
+        assert_eq!(
+            forwarded_ip("1.1.1.1, 10.0.0.1", 1),
+            Some(String::from("10.0.0.1"))
+        );
+        assert_eq!(
+            forwarded_ip("1.1.1.1, 2.2.2.2, 10.0.0.1", 2),
+            Some(String::from("2.2.2.2"))
+        );
+        assert_eq!(forwarded_ip("10.0.0.1", 1), Some(String::from("10.0.0.1")));
+    }
+
+    #[test]
+    fn forwarded_ip_ignores_short_or_empty_headers() {
+        assert_eq!(forwarded_ip("10.0.0.1", 2), None);
+        assert_eq!(forwarded_ip("1.1.1.1, ", 1), None);
+        assert_eq!(forwarded_ip("", 1), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+
+use crate::database::DB;
+
+use super::{
+    bucket::{Decision, TokenBucket},
+    RateLimitPolicy, RateLimitStore,
+};
+
+// how often (in calls) stale rows get deleted.
+const SWEEP_EVERY: u64 = 1024;
+
+// Shared backend for multi-instance deployments. The bucket row is locked for the
+// duration of the refill so concurrent instances can't hand out the same token twice,
+// and the database clock is used so instances don't need synchronized clocks.
+#[derive(Debug)]
+pub struct PostgresStore {
+    db: DB,
+    calls: AtomicU64,
+}
+
+impl PostgresStore {
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            calls: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> anyhow::Result<Decision> {
+        if self
+            .calls
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(SWEEP_EVERY)
+        {
+            sqlx::query(
+                "DELETE FROM rate_limit_buckets WHERE updated_at < current_timestamp - interval '1 day'",
+            )
+            .execute(&self.db)
+            .await?;
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"INSERT INTO rate_limit_buckets (key, tokens, updated_at) VALUES ($1, $2, current_timestamp) ON CONFLICT (key) DO NOTHING"#,
+        )
+        .bind(key)
+        .bind(policy.capacity as f64)
+        .execute(&mut *tx)
+        .await?;
+
+        let (tokens, updated_at, now): (f64, f64, f64) = sqlx::query_as(
+            r#"
+            SELECT
+                tokens,
+                EXTRACT(EPOCH FROM updated_at)::float8,
+                EXTRACT(EPOCH FROM current_timestamp)::float8
+            FROM rate_limit_buckets
+            WHERE key = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(key)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut bucket = TokenBucket { tokens, updated_at };
+        let decision = bucket.take(policy, now);
+
+        sqlx::query(
+            r#"UPDATE rate_limit_buckets SET tokens = $2, updated_at = to_timestamp($3) WHERE key = $1"#,
+        )
+        .bind(key)
+        .bind(bucket.tokens)
+        .bind(bucket.updated_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(decision)
+    }
+}
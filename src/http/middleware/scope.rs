@@ -0,0 +1,30 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{ctx::Ctx, http::Error};
+
+// Route layer put behind `jwt_auth`, rejects tokens that don't carry the scope.
+// usage: `axum_middleware::from_fn_with_state(SCOPE_ACCOUNT, require_scope)`
+pub async fn require_scope(
+    State(scope): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let ctx = request.extensions().get::<Ctx>().ok_or_else(|| Error {
+        status_code: StatusCode::UNAUTHORIZED,
+        message: String::from("Please login first"),
+    })?;
+
+    if !ctx.has_scope(scope) {
+        return Err(Error {
+            status_code: StatusCode::FORBIDDEN,
+            message: format!("token is missing the {scope} scope"),
+        });
+    }
+
+    Ok(next.run(request).await)
+}
@@ -1,14 +1,71 @@
+use std::time::Duration;
+
 use axum::{middleware as axum_middleware, routing, Router};
 
-use crate::model::ModelManager;
+use crate::{
+    model::ModelManager,
+    service::{
+        auth::cookie_session,
+        constant::{
+            PERMISSION_CLIENTS_READ, PERMISSION_CLIENTS_WRITE, PERMISSION_ROLES_READ,
+            PERMISSION_ROLES_WRITE, PERMISSION_SIGNING_KEYS_READ, PERMISSION_SIGNING_KEYS_WRITE,
+            PERMISSION_USERS_READ, PERMISSION_USERS_WRITE, SCOPE_ACCOUNT, SCOPE_OPENID,
+        },
+    },
+};
 
 use self::{
-    auth::{allow_mfa, create_user, google_oauth_callback, google_oauth_login, login},
-    middleware::jwt::jwt_auth,
+    admin::{
+        add_client_key, assign_role, create_client, create_role, delete_client, delete_client_key,
+        delete_role, disable_user, enable_user, force_password_reset, get_client, get_role,
+        get_user, get_user_roles, list_client_keys, list_clients, list_permissions, list_roles,
+        list_signing_keys, reset_mfa, restore_user, retire_signing_key, revoke_sessions,
+        rotate_client_secret, rotate_signing_key, search_users, suspend_user, unassign_role,
+        unsuspend_user, update_app_metadata, update_client, update_role,
+    },
+    api_key::{create_api_key, delete_api_key, list_api_keys},
+    auth::{
+        allow_mfa, create_user, google_oauth_callback, google_oauth_login, login, logout,
+        logout_all, refresh_session, request_magic_link, resend_verification, reset_password,
+        verify_email, verify_magic_link, verify_mfa,
+    },
+    me::{
+        cancel_email_change, change_email, confirm_email_change, delete_me, download_export,
+        get_export, get_me, list_sessions, request_export, restore_account, revoke_session,
+        update_me,
+    },
+    middleware::{
+        jwt::{jwt_auth, reject_api_key},
+        permission::require_permission,
+        rate_limit::{self, RateLimitKey, RateLimitLayer, RateLimitPolicy},
+        scope::require_scope,
+    },
+    oauth::{
+        authorize, authorize_redirect, device_authorization, device_request, introspect,
+        list_consents, revoke, revoke_consent, token, verify_device,
+    },
+    oidc::{jwks, openid_configuration, userinfo},
+    organization::{
+        accept_invitation, create_organization, get_organization, invite_member, list_invitations,
+        list_members, list_organizations, remove_member, resend_invitation, revoke_invitation,
+        set_member, switch_organization,
+    },
+    service_account::{
+        add_service_account_key, create_service_account, create_service_account_secret,
+        delete_service_account, delete_service_account_key, delete_service_account_secret,
+        get_service_account, list_service_accounts, service_account_token, update_service_account,
+    },
 };
 
+mod admin;
+mod api_key;
 mod auth;
 mod error;
+mod me;
+mod oauth;
+mod oidc;
+mod organization;
+mod service_account;
 
 pub mod middleware;
 pub mod request;
@@ -17,17 +74,636 @@ pub mod response;
 pub use self::error::{ApiError, Error};
 
 pub fn new_router(mm: ModelManager) -> Router {
+    let authenticated = axum_middleware::from_fn_with_state(mm.clone(), jwt_auth);
+    let account_scope = axum_middleware::from_fn_with_state(SCOPE_ACCOUNT, require_scope);
+    let openid_scope = axum_middleware::from_fn_with_state(SCOPE_OPENID, require_scope);
+    let login_only = axum_middleware::from_fn(reject_api_key);
+    let users_read = axum_middleware::from_fn_with_state(PERMISSION_USERS_READ, require_permission);
+    let users_write =
+        axum_middleware::from_fn_with_state(PERMISSION_USERS_WRITE, require_permission);
+    let roles_read = axum_middleware::from_fn_with_state(PERMISSION_ROLES_READ, require_permission);
+    let roles_write =
+        axum_middleware::from_fn_with_state(PERMISSION_ROLES_WRITE, require_permission);
+    let clients_read =
+        axum_middleware::from_fn_with_state(PERMISSION_CLIENTS_READ, require_permission);
+    let clients_write =
+        axum_middleware::from_fn_with_state(PERMISSION_CLIENTS_WRITE, require_permission);
+    let signing_keys_read =
+        axum_middleware::from_fn_with_state(PERMISSION_SIGNING_KEYS_READ, require_permission);
+    let signing_keys_write =
+        axum_middleware::from_fn_with_state(PERMISSION_SIGNING_KEYS_WRITE, require_permission);
+
+    let store = rate_limit::store_from_env(&mm.db);
+    let minute = Duration::from_secs(60);
+    let hour = Duration::from_secs(60 * 60);
+
+    let signup_limit = RateLimitLayer::new(store.clone())
+        .policy(RateLimitPolicy::new(
+            "signup-ip",
+            RateLimitKey::Ip,
+            10,
+            hour,
+        ))
+        .policy(RateLimitPolicy::new(
+            "signup-email",
+            RateLimitKey::Email,
+            3,
+            hour,
+        ));
+
+    let login_limit = RateLimitLayer::new(store.clone())
+        .policy(RateLimitPolicy::new(
+            "login-ip",
+            RateLimitKey::Ip,
+            20,
+            minute,
+        ))
+        .policy(RateLimitPolicy::new(
+            "login-email",
+            RateLimitKey::Email,
+            5,
+            minute,
+        ));
+
+    let mfa_limit = RateLimitLayer::new(store.clone()).policy(RateLimitPolicy::new(
+        "mfa-ip",
+        RateLimitKey::Ip,
+        10,
+        minute,
+    ));
+
+    let magic_link_limit = RateLimitLayer::new(store.clone())
+        .policy(RateLimitPolicy::new(
+            "magic-link-ip",
+            RateLimitKey::Ip,
+            10,
+            hour,
+        ))
+        .policy(RateLimitPolicy::new(
+            "magic-link-email",
+            RateLimitKey::Email,
+            5,
+            hour,
+        ));
+
+    let verification_limit = RateLimitLayer::new(store.clone())
+        .policy(RateLimitPolicy::new(
+            "verify-ip",
+            RateLimitKey::Ip,
+            10,
+            hour,
+        ))
+        .policy(RateLimitPolicy::new(
+            "verify-email",
+            RateLimitKey::Email,
+            3,
+            hour,
+        ));
+
+    let password_reset_limit = RateLimitLayer::new(store.clone()).policy(RateLimitPolicy::new(
+        "password-reset-ip",
+        RateLimitKey::Ip,
+        10,
+        hour,
+    ));
+
+    let oauth_limit = RateLimitLayer::new(store.clone())
+        .policy(RateLimitPolicy::new(
+            "oauth-ip",
+            RateLimitKey::Ip,
+            20,
+            minute,
+        ))
+        .policy(RateLimitPolicy::new(
+            "oauth-route",
+            RateLimitKey::Route,
+            600,
+            minute,
+        ));
+
+    let token_limit = RateLimitLayer::new(store.clone()).policy(RateLimitPolicy::new(
+        "token-ip",
+        RateLimitKey::Ip,
+        60,
+        minute,
+    ));
+
+    let device_code_limit = RateLimitLayer::new(store.clone()).policy(RateLimitPolicy::new(
+        "device-code-ip",
+        RateLimitKey::Ip,
+        20,
+        minute,
+    ));
+
+    // the user codes are short, guessing them has to stay slow.
+    let device_verify_limit = RateLimitLayer::new(store.clone()).policy(RateLimitPolicy::new(
+        "device-verify-ip",
+        RateLimitKey::Ip,
+        10,
+        minute,
+    ));
+
+    let service_account_token_limit = RateLimitLayer::new(store).policy(RateLimitPolicy::new(
+        "service-account-token-ip",
+        RateLimitKey::Ip,
+        60,
+        minute,
+    ));
+
+    let admin = Router::new()
+        .route(
+            "/users",
+            routing::get(search_users).route_layer(users_read.clone()),
+        )
+        .route(
+            "/users/:id",
+            routing::get(get_user).route_layer(users_read.clone()),
+        )
+        .route(
+            "/users/:id/roles",
+            routing::get(get_user_roles).route_layer(roles_read.clone()),
+        )
+        .route(
+            "/users/:id/roles/:role_id",
+            routing::put(assign_role)
+                .delete(unassign_role)
+                .route_layer(roles_write.clone()),
+        )
+        .route(
+            "/users/:id/disable",
+            routing::post(disable_user).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/enable",
+            routing::post(enable_user).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/suspend",
+            routing::post(suspend_user).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/unsuspend",
+            routing::post(unsuspend_user).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/password-reset",
+            routing::post(force_password_reset).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/mfa/reset",
+            routing::post(reset_mfa).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/sessions/revoke",
+            routing::post(revoke_sessions).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/restore",
+            routing::post(restore_user).route_layer(users_write.clone()),
+        )
+        .route(
+            "/users/:id/app-metadata",
+            routing::patch(update_app_metadata).route_layer(users_write),
+        )
+        .route(
+            "/roles",
+            routing::get(list_roles)
+                .route_layer(roles_read.clone())
+                .merge(routing::post(create_role).route_layer(roles_write.clone())),
+        )
+        .route(
+            "/roles/:id",
+            routing::get(get_role)
+                .route_layer(roles_read.clone())
+                .merge(
+                    routing::patch(update_role)
+                        .delete(delete_role)
+                        .route_layer(roles_write),
+                ),
+        )
+        .route(
+            "/permissions",
+            routing::get(list_permissions).route_layer(roles_read),
+        )
+        .route(
+            "/clients",
+            routing::get(list_clients)
+                .route_layer(clients_read.clone())
+                .merge(routing::post(create_client).route_layer(clients_write.clone())),
+        )
+        .route(
+            "/clients/:id",
+            routing::get(get_client)
+                .route_layer(clients_read.clone())
+                .merge(
+                    routing::patch(update_client)
+                        .delete(delete_client)
+                        .route_layer(clients_write.clone()),
+                ),
+        )
+        .route(
+            "/clients/:id/secret",
+            routing::post(rotate_client_secret).route_layer(clients_write.clone()),
+        )
+        .route(
+            "/clients/:id/keys",
+            routing::get(list_client_keys)
+                .route_layer(clients_read)
+                .merge(routing::post(add_client_key).route_layer(clients_write.clone())),
+        )
+        .route(
+            "/clients/:id/keys/:key_id",
+            routing::delete(delete_client_key).route_layer(clients_write),
+        )
+        .route(
+            "/signing-keys",
+            routing::get(list_signing_keys).route_layer(signing_keys_read),
+        )
+        .route(
+            "/signing-keys/rotate",
+            routing::post(rotate_signing_key).route_layer(signing_keys_write.clone()),
+        )
+        .route(
+            "/signing-keys/:kid/retire",
+            routing::post(retire_signing_key).route_layer(signing_keys_write),
+        )
+        .route_layer(authenticated.clone());
+
     Router::new()
-        .route("/signup", routing::post(create_user))
-        .route("/login", routing::post(login))
-        .route("/google/oauth/login", routing::get(google_oauth_login))
+        .route(
+            "/signup",
+            routing::post(create_user).route_layer(signup_limit),
+        )
+        .route("/login", routing::post(login).route_layer(login_limit))
+        .route(
+            "/login/mfa",
+            routing::post(verify_mfa).route_layer(mfa_limit),
+        )
+        .route(
+            "/login/magic-link",
+            routing::post(request_magic_link).route_layer(magic_link_limit),
+        )
+        .route("/login/magic-link/verify", routing::get(verify_magic_link))
+        .route("/verify-email", routing::get(verify_email))
+        .route(
+            "/verify-email/resend",
+            routing::post(resend_verification).route_layer(verification_limit),
+        )
+        .route(
+            "/password/reset",
+            routing::post(reset_password).route_layer(password_reset_limit),
+        )
+        .route(
+            "/google/oauth/login",
+            routing::get(google_oauth_login).route_layer(oauth_limit.clone()),
+        )
         .route(
             "/google/oauth/callback",
-            routing::get(google_oauth_callback),
+            routing::get(google_oauth_callback).route_layer(oauth_limit),
+        )
+        .route(
+            "/logout",
+            routing::post(logout)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/logout/all",
+            routing::post(logout_all)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            cookie_session::REFRESH_PATH,
+            routing::post(refresh_session).route_layer(token_limit.clone()),
         )
         .route(
             "/auth/allow/mfa",
-            routing::patch(allow_mfa).route_layer(axum_middleware::from_fn(jwt_auth)),
+            routing::patch(allow_mfa)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me",
+            routing::get(get_me)
+                .route_layer(authenticated.clone())
+                .merge(
+                    routing::patch(update_me)
+                        .route_layer(account_scope.clone())
+                        .route_layer(authenticated.clone()),
+                )
+                .merge(
+                    routing::delete(delete_me)
+                        .route_layer(login_only.clone())
+                        .route_layer(account_scope.clone())
+                        .route_layer(authenticated.clone()),
+                ),
+        )
+        .route("/me/restore", routing::get(restore_account))
+        .route(
+            "/me/email",
+            routing::post(change_email)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/export",
+            routing::post(request_export)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/export/:id",
+            routing::get(get_export)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/export/:id/download",
+            routing::get(download_export)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/api-keys",
+            routing::get(list_api_keys)
+                .post(create_api_key)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/api-keys/:id",
+            routing::delete(delete_api_key)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/sessions",
+            routing::get(list_sessions)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/sessions/:id",
+            routing::delete(revoke_session)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs",
+            routing::get(list_organizations)
+                .post(create_organization)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id",
+            routing::get(get_organization)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/members",
+            routing::get(list_members)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/members/:user_id",
+            routing::put(set_member)
+                .delete(remove_member)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/invitations",
+            routing::get(list_invitations)
+                .post(invite_member)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/invitations/:invitation_id",
+            routing::delete(revoke_invitation)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/invitations/:invitation_id/resend",
+            routing::post(resend_invitation)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/invitations/accept",
+            routing::post(accept_invitation)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/authorize",
+            routing::get(authorize_redirect).merge(
+                routing::post(authorize)
+                    .route_layer(login_only.clone())
+                    .route_layer(account_scope.clone())
+                    .route_layer(authenticated.clone()),
+            ),
         )
+        .route("/token", routing::post(token).route_layer(token_limit))
+        .route("/introspect", routing::post(introspect))
+        .route("/revoke", routing::post(revoke))
+        .route(
+            "/device/code",
+            routing::post(device_authorization).route_layer(device_code_limit),
+        )
+        .route(
+            "/device/verify",
+            routing::get(device_request)
+                .post(verify_device)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone())
+                .route_layer(device_verify_limit),
+        )
+        .route(
+            "/me/consents",
+            routing::get(list_consents)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/me/consents/:client_id",
+            routing::delete(revoke_consent)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/.well-known/openid-configuration",
+            routing::get(openid_configuration),
+        )
+        .route("/jwks.json", routing::get(jwks))
+        .route(
+            "/userinfo",
+            routing::get(userinfo)
+                .post(userinfo)
+                .route_layer(openid_scope)
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/orgs/:id/token",
+            routing::post(switch_organization)
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/token",
+            routing::post(service_account_token).route_layer(service_account_token_limit),
+        )
+        .route(
+            "/service-accounts",
+            routing::get(list_service_accounts)
+                .post(create_service_account)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/:id",
+            routing::get(get_service_account)
+                .patch(update_service_account)
+                .delete(delete_service_account)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/:id/secrets",
+            routing::post(create_service_account_secret)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/:id/secrets/:secret_id",
+            routing::delete(delete_service_account_secret)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/:id/keys",
+            routing::post(add_service_account_key)
+                .route_layer(login_only.clone())
+                .route_layer(account_scope.clone())
+                .route_layer(authenticated.clone()),
+        )
+        .route(
+            "/service-accounts/:id/keys/:key_id",
+            routing::delete(delete_service_account_key)
+                .route_layer(login_only)
+                .route_layer(account_scope)
+                .route_layer(authenticated),
+        )
+        .route("/me/email/confirm", routing::get(confirm_email_change))
+        .route("/me/email/cancel", routing::get(cancel_email_change))
+        .nest("/admin", admin)
         .with_state(mm)
 }
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use super::new_router;
+    use crate::{
+        ctx::Ctx,
+        http::request::api_key::CreateApiKeyDTO,
+        model::ModelManager,
+        pkg::util::digest::sha256_hex,
+        repository::api_key::ApiKeyRepository,
+        service::constant::{API_KEY_PREFIX, SCOPE_ACCOUNT},
+    };
+
+    // an API key with the account scope of a new user.
+    async fn api_key(mm: &ModelManager) -> String {
+        // `users.id` has no default.
+        sqlx::query("INSERT INTO users (id,name,email,password,created_at) VALUES (1, 'user', 'user@example.com', '', current_timestamp)")
+            .execute(&mm.db)
+            .await
+            .unwrap();
+
+        let key = format!("{}test-api-key", API_KEY_PREFIX);
+        ApiKeyRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            1,
+            &key[..API_KEY_PREFIX.len() + 4],
+            &sha256_hex(&key),
+            &CreateApiKeyDTO {
+                name: String::from("script"),
+                scopes: vec![SCOPE_ACCOUNT.to_string()],
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        key
+    }
+
+    async fn status(mm: &ModelManager, method: Method, uri: &str, key: &str) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", key))
+            .body(Body::empty())
+            .unwrap();
+
+        new_router(mm.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[sqlx::test]
+    async fn api_keys_are_rejected_on_login_only_routes(db: PgPool) {
+        let mm = ModelManager { db };
+        let key = api_key(&mm).await;
+
+        assert_eq!(status(&mm, Method::GET, "/me", &key).await, StatusCode::OK);
+        assert_eq!(
+            status(&mm, Method::POST, "/me/export", &key).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(&mm, Method::DELETE, "/me/sessions/1", &key).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(&mm, Method::GET, "/me/consents", &key).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, Query, RawQuery, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect},
+    Extension, Form, Json,
+};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{
+        self, introspection::IntrospectionService, oauth::OAuthService,
+        revocation::RevocationService,
+    },
+};
+
+use super::request::{
+    client::ClientMeta,
+    oauth::{
+        AuthorizeDTO, AuthorizeQuery, BasicCredentials, DeviceAuthorizationRequest,
+        DeviceVerificationQuery, IntrospectionRequest, RevocationRequest, TokenRequest,
+        VerifyDeviceDTO,
+    },
+};
+
+pub async fn authorize_redirect(
+    State(mm): State<ModelManager>,
+    Query(query): Query<AuthorizeQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> service::Result<impl IntoResponse> {
+    let location =
+        OAuthService::authorize_redirect(&mm, &query, &raw_query.unwrap_or_default()).await?;
+
+    Ok(Redirect::to(&location))
+}
+
+pub async fn authorize(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Json(payload): Json<AuthorizeDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::authorize(&mm, &ctx, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+// RFC 6749 section 5.1 asks for the tokens not to be cached.
+pub async fn token(
+    State(mm): State<ModelManager>,
+    credentials: BasicCredentials,
+    client: ClientMeta,
+    Form(payload): Form<TokenRequest>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::token(&mm, &payload, &credentials, &client).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(resp)))
+}
+
+// the answer depends on the state of the token at the time, not to be cached either.
+pub async fn introspect(
+    State(mm): State<ModelManager>,
+    credentials: BasicCredentials,
+    Form(payload): Form<IntrospectionRequest>,
+) -> service::Result<impl IntoResponse> {
+    let resp = IntrospectionService::introspect(&mm, &payload, &credentials).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(resp)))
+}
+
+// RFC 7009 section 2.2, 200 with an empty body whether the token was revoked or not.
+pub async fn revoke(
+    State(mm): State<ModelManager>,
+    credentials: BasicCredentials,
+    Form(payload): Form<RevocationRequest>,
+) -> service::Result<impl IntoResponse> {
+    RevocationService::revoke(&mm, &payload, &credentials).await?;
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn device_authorization(
+    State(mm): State<ModelManager>,
+    credentials: BasicCredentials,
+    Form(payload): Form<DeviceAuthorizationRequest>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::device_authorization(&mm, &payload, &credentials).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(resp)))
+}
+
+pub async fn device_request(
+    State(mm): State<ModelManager>,
+    Query(query): Query<DeviceVerificationQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::device_request(&mm, &query.user_code).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn verify_device(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Json(payload): Json<VerifyDeviceDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::verify_device(&mm, &ctx, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_consents(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::list_consents(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn revoke_consent(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path(client_id): Path<String>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OAuthService::revoke_consent(&mm, &ctx, &client_id, &client).await?;
+
+    Ok(Json(resp))
+}
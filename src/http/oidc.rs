@@ -0,0 +1,35 @@
+use axum::{extract::State, http::header, response::IntoResponse, Extension, Json};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{self, oidc::OidcService, signing_key::JWKS_MAX_AGE_SECONDS},
+};
+
+pub async fn openid_configuration() -> service::Result<impl IntoResponse> {
+    let resp = OidcService::discovery()?;
+
+    Ok(Json(resp))
+}
+
+// verifiers are expected to cache the keys, new ones are published well ahead of use.
+pub async fn jwks(State(mm): State<ModelManager>) -> service::Result<impl IntoResponse> {
+    let resp = OidcService::jwks(&mm).await?;
+
+    Ok((
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", JWKS_MAX_AGE_SECONDS),
+        )],
+        Json(resp),
+    ))
+}
+
+pub async fn userinfo(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OidcService::userinfo(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{
+        self, auth::cookie_session, invitation::InvitationService,
+        organization::OrganizationService,
+    },
+};
+
+use super::request::{
+    client::ClientMeta,
+    organization::{AcceptInvitationDTO, CreateOrganizationDTO, InviteMemberDTO, SetMemberDTO},
+};
+
+pub async fn create_organization(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Json(payload): Json<CreateOrganizationDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::create(&mm, &ctx, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn list_organizations(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::list(&mm, &ctx).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn get_organization(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::get(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn list_members(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::members(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn set_member(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path((id, user_id)): Path<(i64, i64)>,
+    Json(payload): Json<SetMemberDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::set_member(&mm, &ctx, id, user_id, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn remove_member(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Path((id, user_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::remove_member(&mm, &ctx, id, user_id, &client).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn switch_organization(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    cookies: CookieJar,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = OrganizationService::switch(&mm, &ctx, id).await?;
+    let (cookies, resp) = cookie_session::issue(cookies, resp);
+
+    Ok((cookies, Json(resp)))
+}
+
+pub async fn invite_member(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+    Json(payload): Json<InviteMemberDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = InvitationService::invite(&mm, &ctx, id, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn list_invitations(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = InvitationService::list(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn revoke_invitation(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path((id, invitation_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = InvitationService::revoke(&mm, &ctx, id, invitation_id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn resend_invitation(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path((id, invitation_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = InvitationService::resend(&mm, &ctx, id, invitation_id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn accept_invitation(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    client: ClientMeta,
+    Json(payload): Json<AcceptInvitationDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = InvitationService::accept(&mm, &ctx, &payload, &client).await?;
+
+    Ok(Json(resp))
+}
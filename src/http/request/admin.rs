@@ -0,0 +1,31 @@
+use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchQuery {
+    pub email: Option<String>,
+    pub provider: Option<String>,
+    pub mfa_enabled: Option<bool>,
+    // rfc3339, ie. 2024-01-31T00:00:00Z
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_after: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_before: Option<OffsetDateTime>,
+    pub disabled: Option<bool>,
+    pub suspended: Option<bool>,
+    // search the soft deleted accounts that can still be restored.
+    #[serde(default)]
+    pub deleted: bool,
+    // members of this organization only.
+    pub organization: Option<i64>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendUserDTO {
+    pub reason: String,
+    // rfc3339, the suspension lasts until lifted when left out.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub until: Option<OffsetDateTime>,
+}
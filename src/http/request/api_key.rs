@@ -0,0 +1,13 @@
+use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyDTO {
+    pub name: String,
+    // token scopes and permissions of the user, see `service::constant`.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // rfc3339, the key doesn't expire when left out.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
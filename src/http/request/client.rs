@@ -0,0 +1,42 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts},
+};
+
+use crate::http::middleware::rate_limit::client_ip;
+
+// Where a request comes from, recorded with the audit events.
+#[derive(Debug, Clone, Default)]
+pub struct ClientMeta {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientMeta
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(x)| *x);
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|x| x.to_str().ok())
+            .map(String::from);
+
+        Ok(Self {
+            ip: client_ip(&parts.headers, peer),
+            user_agent,
+        })
+    }
+}
@@ -1,7 +1,13 @@
-use serde::{Deserialize, Serialize};
-
 #[derive(Debug, serde::Deserialize)]
 pub struct AuthRequest {
     pub code: String,
     pub state: String,
 }
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GoogleLoginQuery {
+    // invitation to accept once signed in, see `service::invitation`.
+    pub invitation: Option<String>,
+    // slug of the organization to login to, see TENANT_SCOPED_EMAILS.
+    pub organization: Option<String>,
+}
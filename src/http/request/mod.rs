@@ -1,2 +1,9 @@
+pub mod admin;
+pub mod api_key;
+pub mod client;
 pub mod google;
+pub mod oauth;
+pub mod organization;
+pub mod role;
+pub mod service_account;
 pub mod user;
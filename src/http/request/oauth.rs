@@ -0,0 +1,169 @@
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOAuthClientDTO {
+    pub name: String,
+    // confidential or public.
+    pub client_type: String,
+    // required by the authorization_code grant.
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub allowed_scopes: Vec<String>,
+    // authorization_code and refresh_token when left out.
+    #[serde(default)]
+    pub grant_types: Option<Vec<String>>,
+    #[serde(default)]
+    pub first_party: bool,
+    // client_credentials grant: the scopes granted to the client itself, defined by the
+    // APIs it calls, and these APIs, at least one.
+    #[serde(default)]
+    pub client_scopes: Vec<String>,
+    #[serde(default)]
+    pub audiences: Vec<String>,
+}
+
+// `None` leaves the field as it is, the lists are replaced as a whole.
+#[derive(Debug, Deserialize)]
+pub struct UpdateOAuthClientDTO {
+    pub name: Option<String>,
+    pub redirect_uris: Option<Vec<String>>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub grant_types: Option<Vec<String>>,
+    pub first_party: Option<bool>,
+    pub disabled: Option<bool>,
+    pub client_scopes: Option<Vec<String>>,
+    pub audiences: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddOAuthClientKeyDTO {
+    // RS256, RS384, RS512, ES256 or ES384.
+    pub algorithm: String,
+    // PEM encoded.
+    pub public_key: String,
+}
+
+// RFC 6749 section 4.1.1, with the PKCE parameters of RFC 7636.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorizeQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    // space separated, every scope the client is allowed when left out.
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    // OpenID Connect, given back in the ID token to tie it to the request.
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeDTO {
+    #[serde(flatten)]
+    pub request: AuthorizeQuery,
+    // the user's answer to the consent request, left out until asked.
+    #[serde(default)]
+    pub consent: Option<bool>,
+}
+
+// The form fields identifying the client, it can also authenticate with the basic scheme.
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientAuthentication {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    // private_key_jwt client authentication.
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
+
+// form encoded.
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    // client_credentials grant, space separated like `scope`.
+    pub audience: Option<String>,
+    pub device_code: Option<String>,
+    #[serde(flatten)]
+    pub client: ClientAuthentication,
+}
+
+// RFC 7662 section 2.1. `token_type_hint` isn't needed, access tokens are JWTs and
+// refresh tokens opaque.
+#[derive(Debug, Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    #[serde(flatten)]
+    pub client: ClientAuthentication,
+}
+
+// RFC 7009 section 2.1, `token_type_hint` isn't needed either.
+#[derive(Debug, Deserialize)]
+pub struct RevocationRequest {
+    pub token: String,
+    #[serde(flatten)]
+    pub client: ClientAuthentication,
+}
+
+// RFC 8628 section 3.1, form encoded like the token requests.
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    // space separated, every scope the client is allowed when left out.
+    pub scope: Option<String>,
+    #[serde(flatten)]
+    pub client: ClientAuthentication,
+}
+
+// as typed by the user, the dash and the case don't matter.
+#[derive(Debug, Deserialize)]
+pub struct DeviceVerificationQuery {
+    pub user_code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyDeviceDTO {
+    pub user_code: String,
+    pub approve: bool,
+}
+
+// `client_id:client_secret` of an `Authorization: Basic` header, if any.
+#[derive(Debug, Clone, Default)]
+pub struct BasicCredentials(pub Option<(String, String)>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for BasicCredentials
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let credentials = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.strip_prefix("Basic "))
+            .and_then(|x| STANDARD.decode(x.trim()).ok())
+            .and_then(|x| String::from_utf8(x).ok())
+            .and_then(|x| {
+                x.split_once(':')
+                    .map(|(id, secret)| (id.to_string(), secret.to_string()))
+            });
+
+        Ok(Self(credentials))
+    }
+}
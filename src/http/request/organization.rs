@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationDTO {
+    pub name: String,
+    // lowercase letters, digits and dashes, used to pick the organization at signup and login.
+    pub slug: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMemberDTO {
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberDTO {
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationDTO {
+    pub token: String,
+}
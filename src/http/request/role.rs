@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleDTO {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+// `None` leaves the field as it is, the permissions are replaced as a whole.
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleDTO {
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
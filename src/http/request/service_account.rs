@@ -0,0 +1,51 @@
+use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountDTO {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // left out for service accounts managed by the administrators.
+    pub organization_id: Option<i64>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+// `None` leaves the field as it is, the permissions are replaced as a whole.
+#[derive(Debug, Deserialize)]
+pub struct UpdateServiceAccountDTO {
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+    pub disabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountQuery {
+    pub organization: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountSecretDTO {
+    // rfc3339, the secret doesn't expire when left out.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddServiceAccountKeyDTO {
+    // RS256, RS384, RS512, ES256 or ES384.
+    pub algorithm: String,
+    // PEM encoded.
+    pub public_key: String,
+}
+
+// form encoded, either `client_secret` or a signed `client_assertion` (private_key_jwt).
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountTokenRequest {
+    pub grant_type: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub client_assertion_type: Option<String>,
+    pub client_assertion: Option<String>,
+}
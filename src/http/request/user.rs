@@ -9,10 +9,86 @@ pub struct CreateUserDTO {
     pub auth_provider: Option<String>,
     pub auth_provider_user_id: Option<String>,
     pub secret: Option<String>,
+    // slug of the organization to sign up to, see TENANT_SCOPED_EMAILS.
+    #[serde(default)]
+    pub organization: Option<String>,
+    // token of the organization invitation being accepted by signing up.
+    #[serde(default)]
+    pub invitation: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LoginDTO {
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+// the refresh token handed out with the login, revoked along with the access token.
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutDTO {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationDTO {
+    pub email: String,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkDTO {
+    pub email: String,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMfaDTO {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailDTO {
+    pub new_email: String,
+    // required unless the account only logs in through oauth or magic links.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailChangeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountDTO {
+    // required when the account has a password.
+    pub password: Option<String>,
+    // required when MFA is enabled.
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreAccountQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordDTO {
+    pub token: String,
+    pub password: String,
 }
@@ -0,0 +1,38 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+use super::user::ProfileDTO;
+
+// The profile plus the account state only administrators get to see.
+#[derive(Debug, Serialize)]
+pub struct AdminUserDTO {
+    #[serde(flatten)]
+    pub profile: ProfileDTO,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub disabled_at: Option<OffsetDateTime>,
+    pub password_reset_required: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deleted_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub purge_after: Option<OffsetDateTime>,
+    // only set while the suspension is in effect.
+    pub suspension: Option<SuspensionDTO>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuspensionDTO {
+    pub reason: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub suspended_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub suspended_until: Option<OffsetDateTime>,
+    pub suspended_by: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserPageDTO {
+    pub users: Vec<AdminUserDTO>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
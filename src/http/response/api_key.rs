@@ -0,0 +1,25 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyDTO {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    pub last_used_ip: Option<String>,
+}
+
+// returned once when the key is created, only its hash is stored.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyDTO {
+    #[serde(flatten)]
+    pub api_key: ApiKeyDTO,
+    pub key: String,
+}
@@ -0,0 +1,64 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+use super::{
+    api_key::ApiKeyDTO,
+    oauth::ConsentDTO,
+    organization::OrganizationDTO,
+    user::{ProfileDTO, SessionDTO},
+};
+
+#[derive(Debug, Serialize)]
+pub struct DataExportDTO {
+    pub id: i64,
+    pub status: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    // only set once the archive is ready.
+    pub download_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventDTO {
+    pub event: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: serde_json::Value,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// a refresh token that can still be used, `client_id` is `None` for the ones of a login.
+#[derive(Debug, Serialize)]
+pub struct RefreshTokenDTO {
+    pub id: i64,
+    pub client_id: Option<String>,
+    pub client_name: Option<String>,
+    pub scope: String,
+    pub organization_id: Option<i64>,
+    pub session_id: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+// Everything stored about a user. Secrets (password hash, MFA secret, API key and
+// token hashes) are left out, the profile only tells whether they're set.
+#[derive(Debug, Serialize)]
+pub struct DataExportArchive {
+    #[serde(with = "time::serde::rfc3339")]
+    pub generated_at: OffsetDateTime,
+    pub profile: ProfileDTO,
+    pub login_history: Vec<AuditEventDTO>,
+    pub audit_events: Vec<AuditEventDTO>,
+    pub sessions: Vec<SessionDTO>,
+    pub api_keys: Vec<ApiKeyDTO>,
+    pub organizations: Vec<OrganizationDTO>,
+    pub oauth_consents: Vec<ConsentDTO>,
+    pub refresh_tokens: Vec<RefreshTokenDTO>,
+}
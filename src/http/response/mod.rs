@@ -1,6 +1,14 @@
-use axum::response::IntoResponse;
 use serde::Serialize;
 
+pub mod admin;
+pub mod api_key;
+pub mod data_export;
+pub mod oauth;
+pub mod oidc;
+pub mod organization;
+pub mod role;
+pub mod service_account;
+pub mod signing_key;
 pub mod user;
 
 #[derive(Debug, Serialize)]
@@ -17,3 +25,16 @@ impl<T> BaseResponse<T> {
         }
     }
 }
+
+#[derive(Debug, Serialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+impl MessageResponse {
+    pub fn new(message: &str) -> Self {
+        MessageResponse {
+            message: message.to_string(),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct OAuthClientDTO {
+    pub id: i64,
+    pub client_id: String,
+    pub client_type: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub first_party: bool,
+    pub client_scopes: Vec<String>,
+    pub audiences: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub modified_at: Option<OffsetDateTime>,
+    pub disabled: bool,
+}
+
+// the `kid` goes in the header of the assertions signed with the matching private key.
+#[derive(Debug, Serialize)]
+pub struct OAuthClientKeyDTO {
+    pub id: i64,
+    pub kid: String,
+    pub algorithm: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// returned once when the client is registered or its secret rotated, only its hash is stored.
+#[derive(Debug, Serialize)]
+pub struct CreatedOAuthClientDTO {
+    #[serde(flatten)]
+    pub client: OAuthClientDTO,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsentDTO {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub modified_at: Option<OffsetDateTime>,
+}
+
+// what the user is asked to agree to before the client gets a code.
+#[derive(Debug, Serialize)]
+pub struct ConsentRequestDTO {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+// Either where to send the browser back to the client, with the code or an error,
+// or the consent the user still has to give.
+#[derive(Debug, Serialize)]
+pub struct AuthorizeResponseDTO {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consent: Option<ConsentRequestDTO>,
+}
+
+// RFC 7662 section 2.2, only `active` for the tokens that can't be used.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionDTO {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // `Bearer` for the access tokens, `refresh_token` for the refresh tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aud: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    // what `sub` is, a user when left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<i64>,
+}
+
+// RFC 8628 section 3.2.
+#[derive(Debug, Serialize)]
+pub struct DeviceAuthorizationDTO {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+// what the user is about to approve on the verification page.
+#[derive(Debug, Serialize)]
+pub struct DeviceRequestDTO {
+    pub user_code: String,
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+// RFC 6749 section 5.1.
+#[derive(Debug, Serialize)]
+pub struct TokenResponseDTO {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub scope: String,
+    // OpenID Connect, with the `openid` scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
@@ -0,0 +1,35 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationDTO {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    // role of the current user in the organization.
+    pub role: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberDTO {
+    pub user_id: i64,
+    pub name: String,
+    pub email: String,
+    pub role: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub joined_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationDTO {
+    pub id: i64,
+    pub email: String,
+    pub role: String,
+    pub invited_by: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
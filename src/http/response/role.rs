@@ -0,0 +1,18 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct RoleDTO {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PermissionDTO {
+    pub name: String,
+    pub description: String,
+}
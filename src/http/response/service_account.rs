@@ -0,0 +1,54 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountDTO {
+    pub id: i64,
+    pub client_id: String,
+    pub name: String,
+    pub description: String,
+    pub organization_id: Option<i64>,
+    pub created_by: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub disabled: bool,
+    pub permissions: Vec<String>,
+    pub secrets: Vec<ServiceAccountSecretDTO>,
+    pub keys: Vec<ServiceAccountKeyDTO>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountSecretDTO {
+    pub id: i64,
+    pub prefix: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+// returned once when the secret is created, only its hash is stored.
+#[derive(Debug, Serialize)]
+pub struct CreatedServiceAccountSecretDTO {
+    #[serde(flatten)]
+    pub secret: ServiceAccountSecretDTO,
+    pub client_secret: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountKeyDTO {
+    pub id: i64,
+    pub kid: String,
+    pub algorithm: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccountTokenDTO {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+}
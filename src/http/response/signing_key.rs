@@ -0,0 +1,17 @@
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+
+// the private key never leaves the service.
+#[derive(Debug, Serialize)]
+pub struct SigningKeyDTO {
+    pub kid: String,
+    pub algorithm: String,
+    // pending, active, previous or retired.
+    pub status: &'static str,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub activates_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub retired_at: Option<OffsetDateTime>,
+}
@@ -1,4 +1,5 @@
 use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
 
 #[derive(Debug, Serialize)]
 pub struct UserDTO {
@@ -8,12 +9,73 @@ pub struct UserDTO {
     // pub deleted_at: Option<OffsetDateTime>,
     pub name: String,
     pub email: String,
+    pub email_verified: bool,
+    pub user_metadata: serde_json::Value,
+    pub app_metadata: serde_json::Value,
     pub token: Option<String>,
     pub refresh_token: Option<String>,
     pub mfa_type: Option<String>,
+    // exchanged together with the code at /login/mfa.
+    pub mfa_token: Option<String>,
+    // AUTH_COOKIES=true, sent back in the `X-CSRF-Token` header, the tokens are in cookies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MFAResponse {
     pub url: String,
 }
+
+#[derive(Debug, Serialize)]
+pub struct LinkedProviderDTO {
+    pub provider: String,
+    pub provider_user_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MFAStatusDTO {
+    pub enabled: bool,
+    pub mfa_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileDTO {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub email_verified: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub email_verified_at: Option<OffsetDateTime>,
+    pub providers: Vec<LinkedProviderDTO>,
+    pub mfa: MFAStatusDTO,
+    pub user_metadata: serde_json::Value,
+    pub app_metadata: serde_json::Value,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub modified_at: Option<OffsetDateTime>,
+}
+
+// AUTH_COOKIES=true, the refreshed tokens are in the cookies of the response.
+#[derive(Debug, Serialize)]
+pub struct CookieSessionDTO {
+    pub csrf_token: String,
+    pub expires_in: u64,
+}
+
+// a login of the user, `current` for the one the request comes from.
+#[derive(Debug, Serialize)]
+pub struct SessionDTO {
+    pub id: i64,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub amr: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_seen_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub current: bool,
+}
@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Form, Json,
+};
+
+use crate::{
+    ctx::Ctx,
+    model::ModelManager,
+    service::{self, service_account::ServiceAccountService},
+};
+
+use super::request::service_account::{
+    AddServiceAccountKeyDTO, CreateServiceAccountDTO, CreateServiceAccountSecretDTO,
+    ServiceAccountQuery, ServiceAccountTokenRequest, UpdateServiceAccountDTO,
+};
+
+pub async fn create_service_account(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Json(payload): Json<CreateServiceAccountDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::create(&mm, &ctx, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn list_service_accounts(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Query(query): Query<ServiceAccountQuery>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::list(&mm, &ctx, &query).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn get_service_account(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::get(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn update_service_account(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateServiceAccountDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::update(&mm, &ctx, id, &payload).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn delete_service_account(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::delete(&mm, &ctx, id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn create_service_account_secret(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CreateServiceAccountSecretDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::create_secret(&mm, &ctx, id, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn delete_service_account_secret(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path((id, secret_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::delete_secret(&mm, &ctx, id, secret_id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn add_service_account_key(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path(id): Path<i64>,
+    Json(payload): Json<AddServiceAccountKeyDTO>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::add_key(&mm, &ctx, id, &payload).await?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+pub async fn delete_service_account_key(
+    State(mm): State<ModelManager>,
+    Extension(ctx): Extension<Ctx>,
+    Path((id, key_id)): Path<(i64, i64)>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::delete_key(&mm, &ctx, id, key_id).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn service_account_token(
+    State(mm): State<ModelManager>,
+    Form(payload): Form<ServiceAccountTokenRequest>,
+) -> service::Result<impl IntoResponse> {
+    let resp = ServiceAccountService::issue_token(&mm, &payload).await?;
+
+    Ok(Json(resp))
+}
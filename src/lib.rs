@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod ctx;
 pub mod database;
 pub mod http;
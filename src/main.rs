@@ -1,8 +1,11 @@
-use std::env;
+use std::{env, net::SocketAddr};
 
-use auth_service::{http, model::ModelManager};
-use axum::{http::Method, Router};
-use tower_http::cors::{Any, CorsLayer};
+use auth_service::{cli, http, model::ModelManager, service::maintenance};
+use axum::{
+    http::{HeaderValue, Method},
+    Router,
+};
+use tower_http::cors::{AllowHeaders, Any, CorsLayer};
 
 #[tokio::main]
 async fn main() {
@@ -13,17 +16,60 @@ async fn main() {
     println!("cwd: {}", env::current_dir().unwrap().display());
     dotenv::from_filename(".env").unwrap();
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     let mm = ModelManager::new().await.unwrap();
+
+    // admin commands, see `cli`.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&mm, &args).await {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    maintenance::spawn(mm.clone());
     let app = new_router(mm);
 
-    axum::serve(listener, app).await.unwrap();
+    // the peer address is needed by the rate limiter.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 fn new_router(mm: ModelManager) -> Router {
     let cors_layer = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::HEAD])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::HEAD,
+        ])
         .allow_headers(Any)
         .allow_origin(Any);
+
+    // AUTH_COOKIES=true with the app on another origin, the browser only sends the cookies
+    // to the API when it lists the origin and allows credentials.
+    let allowed_origins: Vec<HeaderValue> = env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|x| HeaderValue::from_str(x.trim()).ok())
+        .filter(|x| !x.is_empty())
+        .collect();
+
+    let cors_layer = match allowed_origins.is_empty() {
+        true => cors_layer,
+        false => cors_layer
+            .allow_headers(AllowHeaders::mirror_request())
+            .allow_origin(allowed_origins)
+            .allow_credentials(true),
+    };
+
     Router::new().merge(http::new_router(mm)).layer(cors_layer)
 }
@@ -0,0 +1,31 @@
+use crate::http::response::api_key::ApiKeyDTO;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: Option<OffsetDateTime>,
+    pub last_used_at: Option<OffsetDateTime>,
+    pub last_used_ip: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyDTO {
+    fn from(val: ApiKey) -> Self {
+        ApiKeyDTO {
+            id: val.id,
+            name: val.name,
+            prefix: val.prefix,
+            scopes: val.scopes,
+            created_at: val.created_at,
+            expires_at: val.expires_at,
+            last_used_at: val.last_used_at,
+            last_used_ip: val.last_used_ip,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::http::response::data_export::AuditEventDTO;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(Clone, FromRow)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub user_id: i64,
+    pub event: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: OffsetDateTime,
+}
+
+impl From<AuditEvent> for AuditEventDTO {
+    fn from(val: AuditEvent) -> Self {
+        AuditEventDTO {
+            event: val.event,
+            ip: val.ip,
+            user_agent: val.user_agent,
+            details: val.details,
+            created_at: val.created_at,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    http::response::data_export::DataExportDTO, service::constant::DATA_EXPORT_STATUS_READY,
+};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct DataExport {
+    pub id: i64,
+    pub user_id: i64,
+    // see `service::constant`, DATA_EXPORT_STATUS_*.
+    pub status: String,
+    pub archive: Option<serde_json::Value>,
+    pub created_at: OffsetDateTime,
+    pub completed_at: Option<OffsetDateTime>,
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+impl DataExport {
+    pub fn into_dto(self, base_url: &str) -> DataExportDTO {
+        let download_url = (self.status == DATA_EXPORT_STATUS_READY)
+            .then(|| format!("{}/me/export/{}/download", base_url, self.id));
+
+        DataExportDTO {
+            id: self.id,
+            status: self.status,
+            created_at: self.created_at,
+            completed_at: self.completed_at,
+            expires_at: self.expires_at,
+            download_url,
+        }
+    }
+}
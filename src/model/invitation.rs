@@ -0,0 +1,29 @@
+use crate::http::response::organization::InvitationDTO;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct Invitation {
+    pub id: i64,
+    pub organization_id: i64,
+    pub email: String,
+    pub role: String,
+    pub token_hash: String,
+    pub invited_by: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub accepted_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+impl From<Invitation> for InvitationDTO {
+    fn from(val: Invitation) -> Self {
+        InvitationDTO {
+            id: val.id,
+            email: val.email,
+            role: val.role,
+            invited_by: val.invited_by,
+            created_at: val.created_at,
+            expires_at: val.expires_at,
+        }
+    }
+}
@@ -1,6 +1,18 @@
 use crate::database::{new_db_pool, DB};
+pub mod api_key;
+pub mod audit_event;
+pub mod data_export;
 pub mod error;
+pub mod invitation;
+pub mod oauth;
+pub mod organization;
+pub mod refresh_token;
+pub mod role;
+pub mod service_account;
+pub mod session;
+pub mod signing_key;
 pub mod user;
+pub mod user_token;
 
 pub use self::error::Error;
 
@@ -20,6 +32,7 @@ impl ModelManager {
         Ok(ModelManager { db })
     }
 
+    #[allow(dead_code)]
     pub(in crate::model) fn db(&self) -> &DB {
         &self.db
     }
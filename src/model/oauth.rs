@@ -0,0 +1,148 @@
+use crate::{
+    http::response::oauth::{ConsentDTO, OAuthClientDTO, OAuthClientKeyDTO},
+    service::constant::CLIENT_TYPE_PUBLIC,
+};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct OAuthClient {
+    pub id: i64,
+    pub client_id: String,
+    pub client_secret_hash: Option<String>,
+    pub client_type: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub first_party: bool,
+    pub created_at: OffsetDateTime,
+    pub modified_at: Option<OffsetDateTime>,
+    pub disabled_at: Option<OffsetDateTime>,
+    // client_credentials grant.
+    pub client_scopes: Vec<String>,
+    pub audiences: Vec<String>,
+}
+
+#[derive(FromRow)]
+pub struct OAuthClientKey {
+    pub id: i64,
+    pub oauth_client_id: i64,
+    pub kid: String,
+    pub algorithm: String,
+    pub public_key: String,
+    pub created_at: OffsetDateTime,
+}
+
+impl OAuthClient {
+    pub fn is_public(&self) -> bool {
+        self.client_type == CLIENT_TYPE_PUBLIC
+    }
+
+    pub fn allows_grant(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|x| x == grant_type)
+    }
+}
+
+// with the client it was given to.
+#[derive(FromRow)]
+pub struct OAuthConsent {
+    pub user_id: i64,
+    pub scopes: Vec<String>,
+    pub created_at: OffsetDateTime,
+    pub modified_at: Option<OffsetDateTime>,
+    pub client_id: String,
+    pub client_name: String,
+}
+
+#[derive(FromRow)]
+pub struct AuthorizationCode {
+    pub code_hash: String,
+    pub client_id: i64,
+    pub user_id: i64,
+    pub redirect_uri: String,
+    pub redirect_uri_provided: bool,
+    pub scope: String,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub organization_id: Option<i64>,
+    pub family_id: String,
+    pub nonce: Option<String>,
+    pub auth_time: Option<OffsetDateTime>,
+    pub amr: Vec<String>,
+    pub session_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+}
+
+#[derive(FromRow)]
+pub struct DeviceCode {
+    pub id: i64,
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub client_id: i64,
+    pub scope: String,
+    pub status: String,
+    pub user_id: Option<i64>,
+    pub organization_id: Option<i64>,
+    pub auth_time: Option<OffsetDateTime>,
+    pub amr: Vec<String>,
+    pub session_id: Option<i64>,
+    pub poll_interval: i32,
+    pub last_polled_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+}
+
+// a device code as found by the client polling the token endpoint.
+#[derive(FromRow)]
+pub struct DevicePoll {
+    #[sqlx(flatten)]
+    pub code: DeviceCode,
+    // polled again before `poll_interval` went by, the interval has been raised.
+    pub too_fast: bool,
+}
+
+impl From<OAuthClient> for OAuthClientDTO {
+    fn from(val: OAuthClient) -> Self {
+        OAuthClientDTO {
+            id: val.id,
+            client_id: val.client_id,
+            client_type: val.client_type,
+            name: val.name,
+            redirect_uris: val.redirect_uris,
+            allowed_scopes: val.allowed_scopes,
+            grant_types: val.grant_types,
+            first_party: val.first_party,
+            client_scopes: val.client_scopes,
+            audiences: val.audiences,
+            created_at: val.created_at,
+            modified_at: val.modified_at,
+            disabled: val.disabled_at.is_some(),
+        }
+    }
+}
+
+impl From<OAuthClientKey> for OAuthClientKeyDTO {
+    fn from(val: OAuthClientKey) -> Self {
+        OAuthClientKeyDTO {
+            id: val.id,
+            kid: val.kid,
+            algorithm: val.algorithm,
+            created_at: val.created_at,
+        }
+    }
+}
+
+impl From<OAuthConsent> for ConsentDTO {
+    fn from(val: OAuthConsent) -> Self {
+        ConsentDTO {
+            client_id: val.client_id,
+            client_name: val.client_name,
+            scopes: val.scopes,
+            created_at: val.created_at,
+            modified_at: val.modified_at,
+        }
+    }
+}
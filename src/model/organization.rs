@@ -0,0 +1,51 @@
+use crate::http::response::organization::{MemberDTO, OrganizationDTO};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct Organization {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(FromRow)]
+pub struct Membership {
+    #[sqlx(flatten)]
+    pub organization: Organization,
+    pub role: String,
+}
+
+// a membership along with the user it's for.
+#[derive(FromRow)]
+pub struct Member {
+    pub user_id: i64,
+    pub name: String,
+    pub email: String,
+    pub role: String,
+    pub created_at: OffsetDateTime,
+}
+
+impl Organization {
+    pub fn into_dto(self, role: Option<String>) -> OrganizationDTO {
+        OrganizationDTO {
+            id: self.id,
+            name: self.name,
+            slug: self.slug,
+            role,
+            created_at: self.created_at,
+        }
+    }
+}
+
+impl From<Member> for MemberDTO {
+    fn from(val: Member) -> Self {
+        MemberDTO {
+            user_id: val.user_id,
+            name: val.name,
+            email: val.email,
+            role: val.role,
+            joined_at: val.created_at,
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::http::response::data_export::RefreshTokenDTO;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub token_hash: String,
+    pub family_id: String,
+    pub user_id: i64,
+    pub client_id: Option<i64>,
+    pub scope: String,
+    pub organization_id: Option<i64>,
+    // `None` for the tokens issued before it was recorded.
+    pub auth_time: Option<OffsetDateTime>,
+    pub amr: Vec<String>,
+    // the login the token belongs to, `None` for the ones given to OAuth clients.
+    pub session_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+// an unused refresh token along with the client it was given to.
+#[derive(FromRow)]
+pub struct ActiveRefreshToken {
+    pub id: i64,
+    pub client_id: Option<String>,
+    pub client_name: Option<String>,
+    pub scope: String,
+    pub organization_id: Option<i64>,
+    pub session_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+impl From<ActiveRefreshToken> for RefreshTokenDTO {
+    fn from(val: ActiveRefreshToken) -> Self {
+        RefreshTokenDTO {
+            id: val.id,
+            client_id: val.client_id,
+            client_name: val.client_name,
+            scope: val.scope,
+            organization_id: val.organization_id,
+            session_id: val.session_id,
+            created_at: val.created_at,
+            expires_at: val.expires_at,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::http::response::role::{PermissionDTO, RoleDTO};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct Role {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(FromRow)]
+pub struct Permission {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+}
+
+impl Role {
+    pub fn into_dto(self, permissions: Vec<String>) -> RoleDTO {
+        RoleDTO {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            permissions,
+            created_at: self.created_at,
+        }
+    }
+}
+
+impl From<Permission> for PermissionDTO {
+    fn from(val: Permission) -> Self {
+        PermissionDTO {
+            name: val.name,
+            description: val.description,
+        }
+    }
+}
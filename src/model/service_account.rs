@@ -0,0 +1,83 @@
+use crate::http::response::service_account::{
+    ServiceAccountDTO, ServiceAccountKeyDTO, ServiceAccountSecretDTO,
+};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct ServiceAccount {
+    pub id: i64,
+    pub client_id: String,
+    pub name: String,
+    pub description: String,
+    pub organization_id: Option<i64>,
+    pub created_by: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub disabled_at: Option<OffsetDateTime>,
+}
+
+#[derive(FromRow)]
+pub struct ServiceAccountSecret {
+    pub id: i64,
+    pub service_account_id: i64,
+    pub prefix: String,
+    pub secret_hash: String,
+    pub created_at: OffsetDateTime,
+    pub expires_at: Option<OffsetDateTime>,
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+#[derive(FromRow)]
+pub struct ServiceAccountKey {
+    pub id: i64,
+    pub service_account_id: i64,
+    pub kid: String,
+    pub algorithm: String,
+    pub public_key: String,
+    pub created_at: OffsetDateTime,
+}
+
+impl ServiceAccount {
+    pub fn into_dto(
+        self,
+        permissions: Vec<String>,
+        secrets: Vec<ServiceAccountSecret>,
+        keys: Vec<ServiceAccountKey>,
+    ) -> ServiceAccountDTO {
+        ServiceAccountDTO {
+            id: self.id,
+            client_id: self.client_id,
+            name: self.name,
+            description: self.description,
+            organization_id: self.organization_id,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            disabled: self.disabled_at.is_some(),
+            permissions,
+            secrets: secrets.into_iter().map(Into::into).collect(),
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<ServiceAccountSecret> for ServiceAccountSecretDTO {
+    fn from(val: ServiceAccountSecret) -> Self {
+        ServiceAccountSecretDTO {
+            id: val.id,
+            prefix: val.prefix,
+            created_at: val.created_at,
+            expires_at: val.expires_at,
+            last_used_at: val.last_used_at,
+        }
+    }
+}
+
+impl From<ServiceAccountKey> for ServiceAccountKeyDTO {
+    fn from(val: ServiceAccountKey) -> Self {
+        ServiceAccountKeyDTO {
+            id: val.id,
+            kid: val.kid,
+            algorithm: val.algorithm,
+            created_at: val.created_at,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::http::response::user::SessionDTO;
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct Session {
+    pub id: i64,
+    pub user_id: i64,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub amr: Vec<String>,
+    pub created_at: OffsetDateTime,
+    pub last_seen_at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+impl Session {
+    pub fn into_dto(self, current: bool) -> SessionDTO {
+        SessionDTO {
+            id: self.id,
+            user_agent: self.user_agent,
+            ip: self.ip,
+            amr: self.amr,
+            created_at: self.created_at,
+            last_seen_at: self.last_seen_at,
+            expires_at: self.expires_at,
+            current,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
+
+#[derive(FromRow)]
+pub struct SigningKey {
+    pub id: i64,
+    pub kid: String,
+    // `Algorithm` name of jsonwebtoken: RS256, ES256 or EdDSA.
+    pub algorithm: String,
+    // encrypted with SIGNING_KEY_ENCRYPTION_KEY, see `pkg::aead`, unless stored before it was.
+    pub private_key: Vec<u8>,
+    pub encrypted: bool,
+    pub created_at: OffsetDateTime,
+    pub activates_at: OffsetDateTime,
+    pub retired_at: Option<OffsetDateTime>,
+}
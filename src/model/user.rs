@@ -1,4 +1,11 @@
-use crate::http::response::user::UserDTO;
+use crate::{
+    http::response::{
+        admin::{AdminUserDTO, SuspensionDTO},
+        oidc::UserInfoDTO,
+        user::{LinkedProviderDTO, MFAStatusDTO, ProfileDTO, UserDTO},
+    },
+    service::constant::{MFA_TYPE_TOTP, PASSWORD_AUTH_PROVIDER},
+};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, types::time::OffsetDateTime};
 
@@ -8,16 +15,57 @@ pub struct User {
     pub created_at: OffsetDateTime,
     pub modified_at: Option<OffsetDateTime>,
     pub deleted_at: Option<OffsetDateTime>,
+    // set along with `deleted_at`, the account is purged once it's reached.
+    pub purge_after: Option<OffsetDateTime>,
     pub name: String,
     pub email: String,
     pub auth_provider: Option<String>,
     pub auth_provider_user_id: Option<String>,
     pub secret: Option<String>,
     pub password: String,
+    pub email_verified_at: Option<OffsetDateTime>,
+    pub tokens_valid_after: Option<OffsetDateTime>,
+    pub user_metadata: serde_json::Value,
+    pub app_metadata: serde_json::Value,
+    pub disabled_at: Option<OffsetDateTime>,
+    pub password_reset_required: bool,
+    pub suspended_at: Option<OffsetDateTime>,
+    // `None` while suspended means until lifted by an administrator.
+    pub suspended_until: Option<OffsetDateTime>,
+    pub suspension_reason: Option<String>,
+    pub suspended_by: Option<i64>,
+    // the tenant owning the account, see TENANT_SCOPED_EMAILS.
+    pub organization_id: Option<i64>,
 }
 
+#[derive(Debug, Default)]
 pub struct UserFilter {
-    pub email: String,
+    pub email: Option<String>,
+    // `password` matches the accounts that have one, anything else the linked oauth provider.
+    pub provider: Option<String>,
+    pub mfa_enabled: Option<bool>,
+    pub created_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+    pub disabled: Option<bool>,
+    pub suspended: Option<bool>,
+    // search the soft deleted accounts that can still be restored instead of the active ones.
+    pub deleted: bool,
+    // users whose metadata contains this json document (postgres `@>`).
+    pub user_metadata: Option<serde_json::Value>,
+    pub app_metadata: Option<serde_json::Value>,
+}
+
+// Columns to update, `None` leaves the column as it is.
+// nullable columns take an inner `None` to be cleared.
+#[derive(Debug, Default)]
+pub struct UserPatch {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub auth_provider: Option<Option<String>>,
+    pub auth_provider_user_id: Option<Option<String>>,
+    pub secret: Option<Option<String>>,
+    pub user_metadata: Option<serde_json::Value>,
+    pub app_metadata: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +73,59 @@ pub struct CustomTokenClaims {
     pub sub: u64,
     pub iat: usize,
     pub exp: usize,
+    // the id a revoked token is denied by, `None` for the API keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // space separated list, see `service::constant` for the known scopes.
+    #[serde(default)]
+    pub scope: String,
+    // copied from the user when configured with JWT_METADATA_CLAIMS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_metadata: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_metadata: Option<serde_json::Value>,
+    // granted through the user's roles, see `service::constant`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+    // organization the token acts in, the user has to stay a member of it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<i64>,
+    // `service_account` or `client` when `sub` is a service account or an OAuth client
+    // instead of a user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    // OAuth client the token was issued to, `None` for the login endpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    // when and how the user logged in, see `service::constant` for the `amr` values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    // the APIs a client_credentials token is meant for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aud: Vec<String>,
+    // the session of the login, the token stops working once it's revoked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<i64>,
+}
+
+// OpenID Connect Core 1.0 section 2, signed with the key published at `/jwks.json`.
+#[derive(Debug, Serialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub aud: String,
+    // the client the token was issued to, the only audience.
+    pub azp: String,
+    pub iat: usize,
+    pub exp: usize,
+    pub auth_time: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    #[serde(flatten)]
+    pub user_info: UserInfoDTO,
 }
 
 impl From<User> for UserDTO {
@@ -33,14 +134,82 @@ impl From<User> for UserDTO {
             id: val.id,
             name: val.name,
             email: val.email,
+            email_verified: val.email_verified_at.is_some(),
+            user_metadata: val.user_metadata,
+            app_metadata: val.app_metadata,
             token: None,
             refresh_token: None,
             mfa_type: None,
+            mfa_token: None,
+            csrf_token: None,
+        }
+    }
+}
+
+impl From<User> for ProfileDTO {
+    fn from(val: User) -> Self {
+        let mut providers = Vec::new();
+
+        if !val.password.is_empty() {
+            providers.push(LinkedProviderDTO {
+                provider: PASSWORD_AUTH_PROVIDER.to_string(),
+                provider_user_id: None,
+            });
+        }
+
+        if let Some(provider) = val.auth_provider {
+            providers.push(LinkedProviderDTO {
+                provider,
+                provider_user_id: val.auth_provider_user_id,
+            });
+        }
+
+        ProfileDTO {
+            id: val.id,
+            name: val.name,
+            email: val.email,
+            email_verified: val.email_verified_at.is_some(),
+            email_verified_at: val.email_verified_at,
+            providers,
+            mfa: MFAStatusDTO {
+                enabled: val.secret.is_some(),
+                mfa_type: val.secret.map(|_| MFA_TYPE_TOTP.to_string()),
+            },
+            user_metadata: val.user_metadata,
+            app_metadata: val.app_metadata,
+            created_at: val.created_at,
+            modified_at: val.modified_at,
+        }
+    }
+}
+
+impl From<User> for AdminUserDTO {
+    fn from(val: User) -> Self {
+        AdminUserDTO {
+            disabled_at: val.disabled_at,
+            password_reset_required: val.password_reset_required,
+            deleted_at: val.deleted_at,
+            purge_after: val.purge_after,
+            suspension: val.is_suspended().then(|| SuspensionDTO {
+                reason: val.suspension_reason.clone(),
+                suspended_at: val.suspended_at,
+                suspended_until: val.suspended_until,
+                suspended_by: val.suspended_by,
+            }),
+            profile: val.into(),
         }
     }
 }
 
 impl User {
+    // expired suspensions count as lifted even before the maintenance job clears them.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+            && self
+                .suspended_until
+                .is_none_or(|x| x > OffsetDateTime::now_utc())
+    }
+
     pub fn into_dto(
         self,
         token: Option<String>,
@@ -51,9 +220,72 @@ impl User {
             id: self.id,
             name: self.name,
             email: self.email,
+            email_verified: self.email_verified_at.is_some(),
+            user_metadata: self.user_metadata,
+            app_metadata: self.app_metadata,
             token,
             refresh_token,
             mfa_type,
+            mfa_token: None,
+            csrf_token: None,
+        }
+    }
+
+    // returned instead of the tokens when the login still needs the second factor.
+    pub fn into_mfa_dto(self, mfa_token: String) -> UserDTO {
+        let mut dto = self.into_dto(None, None, Some(MFA_TYPE_TOTP.to_string()));
+        dto.mfa_token = Some(mfa_token);
+        dto
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+    use time::Duration;
+
+    use super::User;
+
+    fn user(suspended_at: Option<OffsetDateTime>, suspended_until: Option<OffsetDateTime>) -> User {
+        User {
+            id: 1,
+            created_at: OffsetDateTime::now_utc(),
+            modified_at: None,
+            deleted_at: None,
+            purge_after: None,
+            name: String::from("user"),
+            email: String::from("user@example.com"),
+            auth_provider: None,
+            auth_provider_user_id: None,
+            secret: None,
+            password: String::new(),
+            email_verified_at: None,
+            tokens_valid_after: None,
+            user_metadata: serde_json::Value::Null,
+            app_metadata: serde_json::Value::Null,
+            disabled_at: None,
+            password_reset_required: false,
+            suspended_at,
+            suspended_until,
+            suspension_reason: None,
+            suspended_by: None,
+            organization_id: None,
         }
     }
+
+    #[test]
+    fn is_suspended() {
+        let now = OffsetDateTime::now_utc();
+
+        assert!(!user(None, None).is_suspended());
+        assert!(user(Some(now), None).is_suspended());
+        assert!(user(Some(now), Some(now + Duration::hours(1))).is_suspended());
+    }
+
+    #[test]
+    fn is_suspended_expired() {
+        let now = OffsetDateTime::now_utc();
+
+        assert!(!user(Some(now - Duration::days(2)), Some(now - Duration::days(1))).is_suspended());
+    }
 }
@@ -0,0 +1,81 @@
+use anyhow::anyhow;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+pub const KEY_LEN: usize = 32;
+
+fn cipher(key: &[u8]) -> anyhow::Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key)
+        .map_err(|_| anyhow!("encryption key must be {} bytes", KEY_LEN))?;
+
+    Ok(LessSafeKey::new(key))
+}
+
+// AES-256-GCM with a random nonce, put in front of the ciphertext and its tag.
+// `aad` isn't stored, the same has to be given to `open`.
+pub fn seal(key: &[u8], aad: &[u8], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("failed to generate a nonce"))?;
+
+    let mut sealed = plaintext.to_vec();
+    cipher(key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("failed to encrypt"))?;
+
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+pub fn open(key: &[u8], aad: &[u8], sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("encrypted value is too short"));
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("invalid nonce"))?;
+
+    let mut plaintext = ciphertext.to_vec();
+    let len = cipher(key)?
+        .open_in_place(nonce, Aad::from(aad), &mut plaintext)
+        .map_err(|_| anyhow!("failed to decrypt, wrong key or tampered value"))?
+        .len();
+    plaintext.truncate(len);
+
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{open, seal, KEY_LEN};
+
+    #[test]
+    fn seal_and_open() {
+        let key = [7u8; KEY_LEN];
+        let sealed = seal(&key, b"kid", b"private key").unwrap();
+
+        assert_ne!(&sealed[sealed.len() - 11..], b"private key");
+        assert_eq!(open(&key, b"kid", &sealed).unwrap(), b"private key");
+    }
+
+    #[test]
+    fn open_rejects_wrong_key_or_aad() {
+        let key = [7u8; KEY_LEN];
+        let sealed = seal(&key, b"kid", b"private key").unwrap();
+
+        assert!(open(&[8u8; KEY_LEN], b"kid", &sealed).is_err());
+        assert!(open(&key, b"other", &sealed).is_err());
+        assert!(open(&key, b"kid", &sealed[..4]).is_err());
+    }
+
+    #[test]
+    fn seal_rejects_short_key() {
+        assert!(seal(&[7u8; 16], b"kid", b"private key").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use core::fmt;
+
 use hmac::Hmac;
 use sha1::Sha1;
 use sha2::{Sha256, Sha512};
@@ -13,12 +15,12 @@ pub enum HMAC {
     HMACSHA512,
 }
 
-impl ToString for HMAC {
-    fn to_string(&self) -> String {
+impl fmt::Display for HMAC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            HMAC::HMACSHA256 => String::from("SHA256"),
-            HMAC::HMACSHA1 => String::from("SHA1"),
-            HMAC::HMACSHA512 => String::from("SHA512"),
+            HMAC::HMACSHA256 => write!(f, "SHA256"),
+            HMAC::HMACSHA1 => write!(f, "SHA1"),
+            HMAC::HMACSHA512 => write!(f, "SHA512"),
         }
     }
 }
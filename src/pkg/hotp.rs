@@ -86,6 +86,25 @@ impl Hotp {
         Ok(otp_digit)
     }
 
+    // check a TOTP code (RFC 6238, 30 seconds time step),
+    // codes from `skew` steps before or after are accepted to allow for clock drift.
+    pub fn verify_totp(&self, code: u64, unix_time: u64, skew: u64) -> anyhow::Result<bool> {
+        Ok(self.totp_step(code, unix_time, skew)?.is_some())
+    }
+
+    // the time step the code belongs to, to reject it once it was used.
+    pub fn totp_step(&self, code: u64, unix_time: u64, skew: u64) -> anyhow::Result<Option<u64>> {
+        let counter = unix_time / 30;
+
+        for step in counter.saturating_sub(skew)..=counter + skew {
+            if self.hotp(step)? == code {
+                return Ok(Some(step));
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn get_url(&self) -> String {
         // format: otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30
         // secret were encoded in base32 without padding
@@ -97,7 +116,7 @@ impl Hotp {
             "otpauth://totp/{issuer}:{email}?secret={secret}&issuer={issuer}&algorithm={algorithm}&digits=6&period=30",
             issuer = self.issuer,
             email = self.target_email,
-            algorithm = self.hash_function.to_string(),
+            algorithm = self.hash_function,
             secret = secret
         )
     }
@@ -112,7 +131,7 @@ fn dynamic_truncation(val: &[u8], digit: Option<u8>) -> u64 {
         | (val[offset + 2] as u32 & 0xff) << 8
         | (val[offset + 3] as u32 & 0xff);
 
-    value as u64 % pow(10 as u64, digit.unwrap_or(6) as u64)
+    value as u64 % pow(10_u64, digit.unwrap_or(6) as u64)
 }
 
 fn pow(base: u64, exp: u64) -> u64 {
@@ -173,4 +192,35 @@ mod test {
 
         assert_eq!(otp, 46119246);
     }
+
+    #[test]
+    fn verify_totp_with_skew() {
+        let val = Hotp::new(
+            None,
+            "authservice",
+            "test@mail.com",
+            "12345678901234567890123456789012",
+            8,
+        );
+
+        // 46119246 is the code of the 30-59 seconds step, 89 is one step later.
+        assert!(val.verify_totp(46119246, 59, 0).unwrap());
+        assert!(!val.verify_totp(46119246, 89, 0).unwrap());
+        assert!(val.verify_totp(46119246, 89, 1).unwrap());
+    }
+
+    #[test]
+    fn totp_step_of_the_code() {
+        let val = Hotp::new(
+            None,
+            "authservice",
+            "test@mail.com",
+            "12345678901234567890123456789012",
+            8,
+        );
+
+        assert_eq!(val.totp_step(46119246, 59, 0).unwrap(), Some(1));
+        assert_eq!(val.totp_step(46119246, 89, 1).unwrap(), Some(1));
+        assert_eq!(val.totp_step(46119246, 119, 1).unwrap(), None);
+    }
 }
@@ -0,0 +1,57 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+// base64url encoding without padding, as used by the JWK members.
+pub fn base64url(val: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(val)
+}
+
+// RFC 7638 thumbprint of the canonical JSON of a key,
+// its required members in lexicographic order and without whitespace.
+fn thumbprint(canonical: &str) -> String {
+    base64url(&Sha256::digest(canonical.as_bytes()))
+}
+
+// Thumbprints are used as the `kid`: they change with the key and need no bookkeeping.
+// The members are base64url encoded, as published in the JWK.
+pub fn rsa_thumbprint(n: &str, e: &str) -> String {
+    thumbprint(&format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n))
+}
+
+pub fn ec_thumbprint(crv: &str, x: &str, y: &str) -> String {
+    thumbprint(&format!(
+        r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+        crv, x, y
+    ))
+}
+
+pub fn okp_thumbprint(crv: &str, x: &str) -> String {
+    thumbprint(&format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, crv, x))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{okp_thumbprint, rsa_thumbprint};
+
+    #[test]
+    fn test_ok_rsa_thumbprint() {
+        // RFC 7638 section 3.1.
+        let n = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+
+        assert_eq!(
+            rsa_thumbprint(n, "AQAB"),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn test_ok_okp_thumbprint() {
+        // RFC 8037 appendix A.3.
+        let x = "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo";
+
+        assert_eq!(
+            okp_thumbprint("Ed25519", x),
+            "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k"
+        );
+    }
+}
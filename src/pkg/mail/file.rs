@@ -0,0 +1,83 @@
+use std::{env, path::PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{sender, Mail, Transport};
+
+// Writes every mail as a plain .eml file, tests can read them back to follow the links.
+#[derive(Debug)]
+pub struct FileTransport {
+    pub dir: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn from_env() -> Self {
+        let dir = env::var("MAIL_FILE_DIR").unwrap_or_else(|_| String::from("mails"));
+
+        Self::new(PathBuf::from(dir))
+    }
+}
+
+#[async_trait]
+impl Transport for FileTransport {
+    async fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .context("error creating mail directory")?;
+
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}",
+            sender(),
+            mail.to,
+            mail.subject,
+            mail.body
+        );
+
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        tokio::fs::write(&path, content)
+            .await
+            .context("error writing mail")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::FileTransport;
+    use crate::pkg::mail::{Mail, Transport};
+
+    #[tokio::test]
+    async fn send_writes_eml() {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let transport = FileTransport::new(dir.clone());
+
+        transport
+            .send(&Mail::new(
+                "test@mail.com",
+                "hello",
+                String::from("https://example.com/link"),
+            ))
+            .await
+            .unwrap();
+
+        let mut entries = std::fs::read_dir(&dir).unwrap();
+        let content = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+
+        assert!(content.contains("To: test@mail.com\r\n"));
+        assert!(content.contains("Subject: hello\r\n"));
+        assert!(content.ends_with("https://example.com/link"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Mail, Transport};
+
+// development only, the body (and any link in it) ends up in the logs.
+#[derive(Debug)]
+pub struct LogTransport {}
+
+#[async_trait]
+impl Transport for LogTransport {
+    async fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        info!(
+            "mail to {} with subject {:?}:\n{}",
+            mail.to, mail.subject, mail.body
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::env;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{sender, Mail, Transport};
+
+pub struct SmtpTransport {
+    inner: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let url = env::var("SMTP_URL").context("Missing SMTP_URL env var")?;
+        let inner = AsyncSmtpTransport::<Tokio1Executor>::from_url(&url)
+            .context("invalid SMTP_URL")?
+            .build();
+
+        Ok(Self { inner })
+    }
+}
+
+#[async_trait]
+impl Transport for SmtpTransport {
+    async fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(sender().parse().context("invalid MAIL_FROM")?)
+            .to(mail.to.parse().context("invalid recipient")?)
+            .subject(&mail.subject)
+            .body(mail.body.clone())?;
+
+        self.inner
+            .send(message)
+            .await
+            .context("error sending mail")?;
+
+        Ok(())
+    }
+}
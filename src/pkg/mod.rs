@@ -1,3 +1,6 @@
+pub mod aead;
 pub mod hmac;
 pub mod hotp;
+pub mod jwk;
+pub mod mail;
 pub mod util;
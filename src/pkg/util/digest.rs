@@ -0,0 +1,80 @@
+use std::fmt::Write;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::Mac;
+use sha2::{Digest, Sha256};
+
+use crate::pkg::hmac::HmacSha256;
+
+pub fn to_hex(val: &[u8]) -> String {
+    val.iter()
+        .fold(String::with_capacity(val.len() * 2), |mut acc, byte| {
+            let _ = write!(acc, "{byte:02x}");
+            acc
+        })
+}
+
+// hex encoded sha256, used to store one-time tokens without keeping the plaintext.
+pub fn sha256_hex(val: &str) -> String {
+    to_hex(&Sha256::digest(val.as_bytes()))
+}
+
+// PKCE `S256` code challenge of the verifier, base64url encoded sha256 without padding.
+pub fn pkce_s256(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+// hex encoded HMAC-SHA256 of the message.
+pub fn hmac_sha256_hex(secret: &[u8], val: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("hmac accepts keys of any size");
+    mac.update(val.as_bytes());
+
+    to_hex(&mac.finalize().into_bytes())
+}
+
+// compare secrets without leaking the position of the first difference through timing.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0_u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{constant_time_eq, hmac_sha256_hex, pkce_s256, sha256_hex};
+
+    #[test]
+    fn sha256_hex_ok() {
+        assert_eq!(
+            sha256_hex("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    // NOTE: Test vector from https://www.rfc-editor.org/rfc/rfc4231#section-4.3
+    #[test]
+    fn hmac_sha256_hex_ok() {
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    // NOTE: Test vector from https://www.rfc-editor.org/rfc/rfc7636#appendix-B
+    #[test]
+    fn pkce_s256_ok() {
+        assert_eq!(
+            pkce_s256("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn constant_time_eq_ok() {
+        assert!(constant_time_eq("abc", "abc"));
+        assert!(!constant_time_eq("abc", "abd"));
+        assert!(!constant_time_eq("abc", "abcd"));
+    }
+}
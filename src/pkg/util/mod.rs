@@ -2,7 +2,10 @@
 // The encoding process represents 40-bit groups of input bits as output strings of 8 encoded characters
 // These 40 bits are then treated as 8 concatenated 5-bit groups, each of which is translated into a single character in the base 32 alphabet.
 
+pub mod digest;
+pub mod json;
 pub mod rand;
+pub mod user_code;
 
 const BASE32_ALPHABET: [char; 32] = [
     'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
@@ -35,7 +38,7 @@ pub fn encode_base32(val: &[u8], with_padding: bool) -> String {
     }
 
     if with_padding {
-        while result.len() % 8 != 0 {
+        while !result.len().is_multiple_of(8) {
             result.push('=')
         }
     }
@@ -43,6 +46,7 @@ pub fn encode_base32(val: &[u8], with_padding: bool) -> String {
     result
 }
 
+#[cfg(test)]
 mod test {
     use super::encode_base32;
 
@@ -0,0 +1,55 @@
+use rand::Rng;
+
+// RFC 8628 section 6.1: no vowels, no look-alike digits, 20^8 codes of 8 characters.
+const CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const LENGTH: usize = 8;
+
+// stored and compared without the dash.
+pub fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+
+    (0..LENGTH)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+// as typed by the user: any case, with or without the dash and spaces.
+pub fn normalize_user_code(val: &str) -> Option<String> {
+    let code: String = val
+        .chars()
+        .filter(|x| *x != '-' && !x.is_whitespace())
+        .map(|x| x.to_ascii_uppercase())
+        .collect();
+
+    (code.len() == LENGTH && code.bytes().all(|x| CHARSET.contains(&x))).then_some(code)
+}
+
+// `WDJBMJHT` is shown as `WDJB-MJHT`.
+pub fn format_user_code(code: &str) -> String {
+    match code.len() == LENGTH && code.is_ascii() {
+        true => format!("{}-{}", &code[..LENGTH / 2], &code[LENGTH / 2..]),
+        false => code.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_user_code, generate_user_code, normalize_user_code};
+
+    #[test]
+    fn test_ok_user_code_round_trip() {
+        let code = generate_user_code();
+
+        assert_eq!(normalize_user_code(&format_user_code(&code)), Some(code));
+    }
+
+    #[test]
+    fn test_ok_normalize_user_code() {
+        assert_eq!(
+            normalize_user_code(" wdjb-mjht "),
+            Some(String::from("WDJBMJHT"))
+        );
+        assert_eq!(normalize_user_code("WDJB-MJH"), None);
+        assert_eq!(normalize_user_code("AEIO-UAEI"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    ctx::Ctx,
+    http::request::api_key::CreateApiKeyDTO,
+    model::{api_key::ApiKey, ModelManager},
+};
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyRepository {}
+
+impl ApiKeyRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        prefix: &str,
+        key_hash: &str,
+        req: &CreateApiKeyDTO,
+    ) -> anyhow::Result<ApiKey> {
+        let api_key: ApiKey = sqlx::query_as(
+            r#"INSERT INTO api_keys (user_id,name,prefix,key_hash,scopes,created_at,expires_at) VALUES ($1, $2, $3, $4, $5, current_timestamp, $6) RETURNING *"#,
+        )
+        .bind(user_id)
+        .bind(&req.name)
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(&req.scopes)
+        .bind(req.expires_at)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(api_key)
+    }
+
+    pub async fn list_by_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<ApiKey>> {
+        let api_keys: Vec<ApiKey> =
+            sqlx::query_as("SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC")
+                .bind(user_id)
+                .fetch_all(&mm.db)
+                .await?;
+
+        Ok(api_keys)
+    }
+
+    pub async fn count_by_user(_ctx: Ctx, mm: &ModelManager, user_id: i64) -> anyhow::Result<i64> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM api_keys WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&mm.db)
+            .await?;
+
+        Ok(count)
+    }
+
+    // a key that hasn't expired.
+    pub async fn find_active(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        key_hash: &str,
+    ) -> anyhow::Result<Option<ApiKey>> {
+        let api_key: Option<ApiKey> = sqlx::query_as(
+            r#"SELECT * FROM api_keys WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > current_timestamp)"#,
+        )
+        .bind(key_hash)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(api_key)
+    }
+
+    // written at most once a minute per key.
+    pub async fn touch(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        ip: Option<&str>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE api_keys SET last_used_at = current_timestamp, last_used_ip = $2 WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < current_timestamp - interval '1 minute' OR last_used_ip IS DISTINCT FROM $2)"#,
+        )
+        .bind(id)
+        .bind(ip)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        id: i64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE user_id = $1 AND id = $2")
+            .bind(user_id)
+            .bind(id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
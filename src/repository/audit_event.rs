@@ -0,0 +1,48 @@
+use crate::{
+    ctx::Ctx,
+    model::{audit_event::AuditEvent, ModelManager},
+};
+
+#[derive(Debug, Clone)]
+pub struct AuditEventRepository {}
+
+impl AuditEventRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        event: &str,
+        ip: Option<&str>,
+        user_agent: Option<&str>,
+        details: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO audit_events (user_id,event,ip,user_agent,details,created_at) VALUES ($1, $2, $3, $4, $5, current_timestamp)"#,
+        )
+        .bind(user_id)
+        .bind(event)
+        .bind(ip)
+        .bind(user_agent)
+        .bind(details)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // newest first.
+    pub async fn list_by_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<AuditEvent>> {
+        let events: Vec<AuditEvent> = sqlx::query_as(
+            "SELECT * FROM audit_events WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,100 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    model::{oauth::AuthorizationCode, ModelManager},
+};
+
+// what an authorization code stands for, until exchanged at the token endpoint.
+pub struct NewAuthorizationCode<'a> {
+    pub code_hash: &'a str,
+    pub client_id: i64,
+    pub user_id: i64,
+    pub redirect_uri: &'a str,
+    pub redirect_uri_provided: bool,
+    pub scope: &'a str,
+    pub code_challenge: Option<&'a str>,
+    pub code_challenge_method: Option<&'a str>,
+    pub family_id: &'a str,
+    pub nonce: Option<&'a str>,
+    pub auth_time: OffsetDateTime,
+    pub amr: &'a [String],
+    pub session_id: Option<i64>,
+    pub ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthorizationCodeRepository {}
+
+impl AuthorizationCodeRepository {
+    // the code acts in the organization of the context, if any.
+    pub async fn create(
+        ctx: Ctx,
+        mm: &ModelManager,
+        code: NewAuthorizationCode<'_>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO oauth_authorization_codes (code_hash,client_id,user_id,redirect_uri,scope,code_challenge,code_challenge_method,organization_id,family_id,nonce,auth_time,amr,redirect_uri_provided,session_id,created_at,expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, current_timestamp, current_timestamp + make_interval(secs => $15))"#,
+        )
+        .bind(code.code_hash)
+        .bind(code.client_id)
+        .bind(code.user_id)
+        .bind(code.redirect_uri)
+        .bind(code.scope)
+        .bind(code.code_challenge)
+        .bind(code.code_challenge_method)
+        .bind(ctx.organization_id())
+        .bind(code.family_id)
+        .bind(code.nonce)
+        .bind(code.auth_time)
+        .bind(code.amr)
+        .bind(code.redirect_uri_provided)
+        .bind(code.session_id)
+        .bind(code.ttl_seconds as f64)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // marks the code as used, `None` when it's unknown, expired or already used.
+    pub async fn consume(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        code_hash: &str,
+    ) -> anyhow::Result<Option<AuthorizationCode>> {
+        let code: Option<AuthorizationCode> = sqlx::query_as(
+            r#"UPDATE oauth_authorization_codes SET used_at = current_timestamp WHERE code_hash = $1 AND used_at IS NULL AND expires_at > current_timestamp RETURNING *"#,
+        )
+        .bind(code_hash)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(code)
+    }
+
+    pub async fn find(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        code_hash: &str,
+    ) -> anyhow::Result<Option<AuthorizationCode>> {
+        let code: Option<AuthorizationCode> =
+            sqlx::query_as("SELECT * FROM oauth_authorization_codes WHERE code_hash = $1")
+                .bind(code_hash)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(code)
+    }
+
+    // used codes are kept as long as unused ones to notice a replay.
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM oauth_authorization_codes WHERE expires_at <= current_timestamp",
+        )
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    ctx::Ctx,
+    model::{data_export::DataExport, ModelManager},
+    service::constant::{
+        DATA_EXPORT_STATUS_FAILED, DATA_EXPORT_STATUS_PENDING, DATA_EXPORT_STATUS_READY,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct DataExportRepository {}
+
+impl DataExportRepository {
+    pub async fn create(_ctx: Ctx, mm: &ModelManager, user_id: i64) -> anyhow::Result<DataExport> {
+        let export: DataExport = sqlx::query_as(
+            r#"INSERT INTO data_exports (user_id,status,created_at) VALUES ($1, $2, current_timestamp) RETURNING *"#,
+        )
+        .bind(user_id)
+        .bind(DATA_EXPORT_STATUS_PENDING)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(export)
+    }
+
+    // expired exports are left out, they're only waiting to be deleted.
+    pub async fn get(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        id: i64,
+    ) -> anyhow::Result<Option<DataExport>> {
+        let export: Option<DataExport> = sqlx::query_as(
+            r#"SELECT * FROM data_exports WHERE id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > current_timestamp)"#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(export)
+    }
+
+    // an export left pending by a process that died doesn't block new requests for long.
+    pub async fn find_pending(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Option<DataExport>> {
+        let export: Option<DataExport> = sqlx::query_as(
+            r#"SELECT * FROM data_exports WHERE user_id = $1 AND status = $2 AND created_at > current_timestamp - interval '1 hour' ORDER BY id DESC LIMIT 1"#,
+        )
+        .bind(user_id)
+        .bind(DATA_EXPORT_STATUS_PENDING)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(export)
+    }
+
+    pub async fn complete(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        archive: &serde_json::Value,
+        ttl_seconds: i64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE data_exports SET status = $2, archive = $3, completed_at = current_timestamp, expires_at = current_timestamp + make_interval(secs => $4) WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(DATA_EXPORT_STATUS_READY)
+        .bind(archive)
+        .bind(ttl_seconds as f64)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fail(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE data_exports SET status = $2, completed_at = current_timestamp, expires_at = current_timestamp + interval '1 day' WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(DATA_EXPORT_STATUS_FAILED)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            r#"DELETE FROM data_exports WHERE expires_at <= current_timestamp OR (status = $1 AND created_at < current_timestamp - interval '1 day')"#,
+        )
+        .bind(DATA_EXPORT_STATUS_PENDING)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,164 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        oauth::{DeviceCode, DevicePoll},
+        ModelManager,
+    },
+    service::constant::{DEVICE_CODE_STATUS_APPROVED, DEVICE_CODE_STATUS_PENDING},
+};
+
+pub struct NewDeviceCode<'a> {
+    pub device_code_hash: &'a str,
+    pub user_code: &'a str,
+    pub client_id: i64,
+    pub scope: &'a str,
+    pub poll_interval: i32,
+    pub ttl_seconds: i64,
+}
+
+// the user's answer to a device request and the login it was given from.
+pub struct DeviceAnswer<'a> {
+    pub status: &'a str,
+    pub user_id: i64,
+    pub auth_time: OffsetDateTime,
+    pub amr: &'a [String],
+    pub session_id: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceCodeRepository {}
+
+impl DeviceCodeRepository {
+    // `None` when the user code is already taken, by a pending or an expired request.
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        code: NewDeviceCode<'_>,
+    ) -> anyhow::Result<Option<DeviceCode>> {
+        let device_code: Option<DeviceCode> = sqlx::query_as(
+            r#"INSERT INTO oauth_device_codes (device_code_hash,user_code,client_id,scope,status,poll_interval,created_at,expires_at) VALUES ($1, $2, $3, $4, $5, $6, current_timestamp, current_timestamp + make_interval(secs => $7)) ON CONFLICT (user_code) DO NOTHING RETURNING *"#,
+        )
+        .bind(code.device_code_hash)
+        .bind(code.user_code)
+        .bind(code.client_id)
+        .bind(code.scope)
+        .bind(DEVICE_CODE_STATUS_PENDING)
+        .bind(code.poll_interval)
+        .bind(code.ttl_seconds as f64)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(device_code)
+    }
+
+    // the request the user is asked about, if still waiting for an answer.
+    pub async fn find_pending(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_code: &str,
+    ) -> anyhow::Result<Option<DeviceCode>> {
+        let device_code: Option<DeviceCode> = sqlx::query_as(
+            "SELECT * FROM oauth_device_codes WHERE user_code = $1 AND status = $2 AND expires_at > current_timestamp",
+        )
+        .bind(user_code)
+        .bind(DEVICE_CODE_STATUS_PENDING)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(device_code)
+    }
+
+    // the user's answer, false when the request was answered or expired in the meantime.
+    // An approved request acts in the organization of the context, if any.
+    pub async fn answer(
+        ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        answer: DeviceAnswer<'_>,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE oauth_device_codes SET
+                status = $2,
+                user_id = $3,
+                organization_id = $4,
+                auth_time = $5,
+                amr = $6,
+                session_id = $7
+            WHERE id = $1 AND status = $8 AND expires_at > current_timestamp
+            "#,
+        )
+        .bind(id)
+        .bind(answer.status)
+        .bind(answer.user_id)
+        .bind(ctx.organization_id())
+        .bind(answer.auth_time)
+        .bind(answer.amr)
+        .bind(answer.session_id)
+        .bind(DEVICE_CODE_STATUS_PENDING)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Records the poll, raising the interval by `slow_down_seconds` when the previous one
+    // was too recent (RFC 8628 section 3.5).
+    pub async fn poll(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        device_code_hash: &str,
+        slow_down_seconds: i32,
+    ) -> anyhow::Result<Option<DevicePoll>> {
+        let poll: Option<DevicePoll> = sqlx::query_as(
+            r#"
+            WITH polled AS (
+                SELECT id, COALESCE(last_polled_at > current_timestamp - make_interval(secs => poll_interval), FALSE) AS too_fast
+                FROM oauth_device_codes
+                WHERE device_code_hash = $1
+                FOR UPDATE
+            )
+            UPDATE oauth_device_codes SET
+                last_polled_at = current_timestamp,
+                poll_interval = poll_interval + CASE WHEN polled.too_fast THEN $2 ELSE 0 END
+            FROM polled
+            WHERE oauth_device_codes.id = polled.id
+            RETURNING oauth_device_codes.*, polled.too_fast
+            "#,
+        )
+        .bind(device_code_hash)
+        .bind(slow_down_seconds)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(poll)
+    }
+
+    // marks an approved request as used, `None` when it already was.
+    pub async fn consume(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<DeviceCode>> {
+        let device_code: Option<DeviceCode> = sqlx::query_as(
+            "UPDATE oauth_device_codes SET used_at = current_timestamp WHERE id = $1 AND status = $2 AND used_at IS NULL RETURNING *",
+        )
+        .bind(id)
+        .bind(DEVICE_CODE_STATUS_APPROVED)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(device_code)
+    }
+
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM oauth_device_codes WHERE expires_at <= current_timestamp")
+                .execute(&mm.db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
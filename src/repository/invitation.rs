@@ -0,0 +1,117 @@
+use anyhow::Context;
+
+use crate::{
+    ctx::Ctx,
+    model::{invitation::Invitation, ModelManager},
+};
+
+// the invitations are managed within the organization of the context.
+fn tenant(ctx: &Ctx) -> anyhow::Result<i64> {
+    ctx.organization_id()
+        .context("missing organization in context")
+}
+
+#[derive(Debug, Clone)]
+pub struct InvitationRepository {}
+
+impl InvitationRepository {
+    pub async fn create(
+        ctx: Ctx,
+        mm: &ModelManager,
+        email: &str,
+        role: &str,
+        token_hash: &str,
+        invited_by: i64,
+        ttl_seconds: i64,
+    ) -> anyhow::Result<Invitation> {
+        let invitation: Invitation = sqlx::query_as(
+            r#"INSERT INTO organization_invitations (organization_id,email,role,token_hash,invited_by,created_at,expires_at) VALUES ($1, $2, $3, $4, $5, current_timestamp, current_timestamp + make_interval(secs => $6)) RETURNING *"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(email)
+        .bind(role)
+        .bind(token_hash)
+        .bind(invited_by)
+        .bind(ttl_seconds as f64)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(invitation)
+    }
+
+    // neither accepted, revoked nor expired.
+    pub async fn list_pending(ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<Invitation>> {
+        let invitations: Vec<Invitation> = sqlx::query_as(
+            r#"SELECT * FROM organization_invitations WHERE organization_id = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > current_timestamp ORDER BY created_at DESC"#,
+        )
+        .bind(tenant(&ctx)?)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(invitations)
+    }
+
+    pub async fn revoke(ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            r#"UPDATE organization_invitations SET revoked_at = current_timestamp WHERE organization_id = $1 AND id = $2 AND accepted_at IS NULL AND revoked_at IS NULL"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn revoke_for_email(ctx: Ctx, mm: &ModelManager, email: &str) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE organization_invitations SET revoked_at = current_timestamp WHERE organization_id = $1 AND lower(email) = lower($2) AND accepted_at IS NULL AND revoked_at IS NULL"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(email)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // a new token and expiry for an invitation that wasn't accepted or revoked,
+    // the previous link stops working.
+    pub async fn renew(
+        ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        token_hash: &str,
+        ttl_seconds: i64,
+    ) -> anyhow::Result<Option<Invitation>> {
+        let invitation: Option<Invitation> = sqlx::query_as(
+            r#"UPDATE organization_invitations SET token_hash = $3, expires_at = current_timestamp + make_interval(secs => $4) WHERE organization_id = $1 AND id = $2 AND accepted_at IS NULL AND revoked_at IS NULL RETURNING *"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(id)
+        .bind(token_hash)
+        .bind(ttl_seconds as f64)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(invitation)
+    }
+
+    // marks the invitation accepted, only once and only for the address it was sent to.
+    pub async fn accept(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        token_hash: &str,
+        email: &str,
+    ) -> anyhow::Result<Option<Invitation>> {
+        let invitation: Option<Invitation> = sqlx::query_as(
+            r#"UPDATE organization_invitations SET accepted_at = current_timestamp WHERE token_hash = $1 AND lower(email) = lower($2) AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > current_timestamp RETURNING *"#,
+        )
+        .bind(token_hash)
+        .bind(email)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(invitation)
+    }
+}
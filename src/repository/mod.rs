@@ -1 +1,16 @@
+pub mod api_key;
+pub mod audit_event;
+pub mod authorization_code;
+pub mod data_export;
+pub mod device_code;
+pub mod invitation;
+pub mod oauth_client;
+pub mod organization;
+pub mod refresh_token;
+pub mod revoked_token;
+pub mod role;
+pub mod service_account;
+pub mod session;
+pub mod signing_key;
 pub mod user;
+pub mod user_token;
@@ -0,0 +1,287 @@
+use crate::{
+    ctx::Ctx,
+    http::request::oauth::{CreateOAuthClientDTO, UpdateOAuthClientDTO},
+    model::{
+        oauth::{OAuthClient, OAuthClientKey, OAuthConsent},
+        ModelManager,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct OAuthClientRepository {}
+
+impl OAuthClientRepository {
+    // `req.grant_types` is expected to be resolved by the caller.
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        client_id: &str,
+        client_secret_hash: Option<&str>,
+        req: &CreateOAuthClientDTO,
+        grant_types: &[String],
+    ) -> anyhow::Result<OAuthClient> {
+        let client: OAuthClient = sqlx::query_as(
+            r#"INSERT INTO oauth_clients (client_id,client_secret_hash,client_type,name,redirect_uris,allowed_scopes,grant_types,first_party,client_scopes,audiences,created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, current_timestamp) RETURNING *"#,
+        )
+        .bind(client_id)
+        .bind(client_secret_hash)
+        .bind(&req.client_type)
+        .bind(req.name.trim())
+        .bind(&req.redirect_uris)
+        .bind(&req.allowed_scopes)
+        .bind(grant_types)
+        .bind(req.first_party)
+        .bind(&req.client_scopes)
+        .bind(&req.audiences)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(client)
+    }
+
+    pub async fn list(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<OAuthClient>> {
+        let clients: Vec<OAuthClient> = sqlx::query_as("SELECT * FROM oauth_clients ORDER BY name")
+            .fetch_all(&mm.db)
+            .await?;
+
+        Ok(clients)
+    }
+
+    pub async fn find_by_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<OAuthClient>> {
+        let client: Option<OAuthClient> =
+            sqlx::query_as("SELECT * FROM oauth_clients WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(client)
+    }
+
+    pub async fn find_by_client_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        client_id: &str,
+    ) -> anyhow::Result<Option<OAuthClient>> {
+        let client: Option<OAuthClient> =
+            sqlx::query_as("SELECT * FROM oauth_clients WHERE client_id = $1")
+                .bind(client_id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(client)
+    }
+
+    pub async fn update(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        req: &UpdateOAuthClientDTO,
+    ) -> anyhow::Result<Option<OAuthClient>> {
+        let client: Option<OAuthClient> = sqlx::query_as(
+            r#"
+            UPDATE oauth_clients SET
+                name = COALESCE($2, name),
+                redirect_uris = COALESCE($3, redirect_uris),
+                allowed_scopes = COALESCE($4, allowed_scopes),
+                grant_types = COALESCE($5, grant_types),
+                first_party = COALESCE($6, first_party),
+                disabled_at = CASE WHEN $7 IS NULL THEN disabled_at WHEN $7 THEN COALESCE(disabled_at, current_timestamp) ELSE NULL END,
+                client_scopes = COALESCE($8, client_scopes),
+                audiences = COALESCE($9, audiences),
+                modified_at = current_timestamp
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(&req.redirect_uris)
+        .bind(&req.allowed_scopes)
+        .bind(&req.grant_types)
+        .bind(req.first_party)
+        .bind(req.disabled)
+        .bind(&req.client_scopes)
+        .bind(&req.audiences)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(client)
+    }
+
+    pub async fn set_secret(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        client_secret_hash: &str,
+    ) -> anyhow::Result<Option<OAuthClient>> {
+        let client: Option<OAuthClient> = sqlx::query_as(
+            "UPDATE oauth_clients SET client_secret_hash = $2, modified_at = current_timestamp WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(client_secret_hash)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(client)
+    }
+
+    pub async fn delete(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM oauth_clients WHERE id = $1")
+            .bind(id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_key(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        oauth_client_id: i64,
+        kid: &str,
+        algorithm: &str,
+        public_key: &str,
+    ) -> anyhow::Result<OAuthClientKey> {
+        let key: OAuthClientKey = sqlx::query_as(
+            "INSERT INTO oauth_client_keys (oauth_client_id,kid,algorithm,public_key,created_at) VALUES ($1, $2, $3, $4, current_timestamp) RETURNING *",
+        )
+        .bind(oauth_client_id)
+        .bind(kid)
+        .bind(algorithm)
+        .bind(public_key)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(key)
+    }
+
+    pub async fn list_keys(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        oauth_client_id: i64,
+    ) -> anyhow::Result<Vec<OAuthClientKey>> {
+        let keys: Vec<OAuthClientKey> = sqlx::query_as(
+            "SELECT * FROM oauth_client_keys WHERE oauth_client_id = $1 ORDER BY created_at",
+        )
+        .bind(oauth_client_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(keys)
+    }
+
+    pub async fn find_key_by_kid(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        kid: &str,
+    ) -> anyhow::Result<Option<OAuthClientKey>> {
+        let key: Option<OAuthClientKey> =
+            sqlx::query_as("SELECT * FROM oauth_client_keys WHERE kid = $1")
+                .bind(kid)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(key)
+    }
+
+    pub async fn delete_key(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        oauth_client_id: i64,
+        key_id: i64,
+    ) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM oauth_client_keys WHERE id = $1 AND oauth_client_id = $2")
+                .bind(key_id)
+                .bind(oauth_client_id)
+                .execute(&mm.db)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // the scopes the user already agreed to give the client, empty when never asked.
+    pub async fn consented_scopes(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        let scopes: Option<(Vec<String>,)> = sqlx::query_as(
+            "SELECT scopes FROM oauth_consents WHERE user_id = $1 AND client_id = $2",
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(scopes.map(|(x,)| x).unwrap_or_default())
+    }
+
+    // adds the scopes to the ones already given.
+    pub async fn grant_consent(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: i64,
+        scopes: &[String],
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scopes, created_at) VALUES ($1, $2, $3, current_timestamp)
+            ON CONFLICT (user_id, client_id) DO UPDATE SET
+                scopes = ARRAY(SELECT DISTINCT unnest(oauth_consents.scopes || EXCLUDED.scopes) ORDER BY 1),
+                modified_at = current_timestamp
+            "#,
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .bind(scopes)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_consents(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<OAuthConsent>> {
+        let consents: Vec<OAuthConsent> = sqlx::query_as(
+            r#"
+            SELECT oauth_consents.user_id, oauth_consents.scopes, oauth_consents.created_at, oauth_consents.modified_at,
+                oauth_clients.client_id, oauth_clients.name AS client_name
+            FROM oauth_consents
+            JOIN oauth_clients ON oauth_clients.id = oauth_consents.client_id
+            WHERE oauth_consents.user_id = $1
+            ORDER BY oauth_clients.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(consents)
+    }
+
+    pub async fn revoke_consent(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: i64,
+    ) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM oauth_consents WHERE user_id = $1 AND client_id = $2")
+                .bind(user_id)
+                .bind(client_id)
+                .execute(&mm.db)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Context;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        organization::{Member, Membership, Organization},
+        ModelManager,
+    },
+};
+
+// the membership queries act on the organization of the context.
+fn tenant(ctx: &Ctx) -> anyhow::Result<i64> {
+    ctx.organization_id()
+        .context("missing organization in context")
+}
+
+#[derive(Debug, Clone)]
+pub struct OrganizationRepository {}
+
+impl OrganizationRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        name: &str,
+        slug: &str,
+    ) -> anyhow::Result<Organization> {
+        let organization: Organization = sqlx::query_as(
+            r#"INSERT INTO organizations (name,slug,created_at) VALUES ($1, $2, current_timestamp) RETURNING *"#,
+        )
+        .bind(name)
+        .bind(slug)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(organization)
+    }
+
+    pub async fn find_by_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<Organization>> {
+        let organization: Option<Organization> =
+            sqlx::query_as("SELECT * FROM organizations WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(organization)
+    }
+
+    pub async fn find_by_slug(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        slug: &str,
+    ) -> anyhow::Result<Option<Organization>> {
+        let organization: Option<Organization> =
+            sqlx::query_as("SELECT * FROM organizations WHERE slug = $1")
+                .bind(slug)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(organization)
+    }
+
+    // every organization the user is a member of, along with their role in it.
+    pub async fn list_for_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<Membership>> {
+        let memberships: Vec<Membership> = sqlx::query_as(
+            r#"SELECT organizations.*, organization_members.role FROM organizations JOIN organization_members ON organization_members.organization_id = organizations.id WHERE organization_members.user_id = $1 ORDER BY organizations.name"#,
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(memberships)
+    }
+
+    pub async fn find_member(
+        ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Option<Member>> {
+        let member: Option<Member> = sqlx::query_as(
+            r#"SELECT users.id AS user_id, users.name, users.email, organization_members.role, organization_members.created_at FROM organization_members JOIN users ON users.id = organization_members.user_id WHERE organization_members.organization_id = $1 AND organization_members.user_id = $2 AND users.deleted_at IS NULL"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(user_id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(member)
+    }
+
+    pub async fn list_members(ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<Member>> {
+        let members: Vec<Member> = sqlx::query_as(
+            r#"SELECT users.id AS user_id, users.name, users.email, organization_members.role, organization_members.created_at FROM organization_members JOIN users ON users.id = organization_members.user_id WHERE organization_members.organization_id = $1 AND users.deleted_at IS NULL ORDER BY organization_members.created_at"#,
+        )
+        .bind(tenant(&ctx)?)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(members)
+    }
+
+    // adds the user to the organization or changes their role in it.
+    pub async fn upsert_member(
+        ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        role: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO organization_members (organization_id,user_id,role,created_at) VALUES ($1, $2, $3, current_timestamp) ON CONFLICT (organization_id, user_id) DO UPDATE SET role = EXCLUDED.role"#,
+        )
+        .bind(tenant(&ctx)?)
+        .bind(user_id)
+        .bind(role)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_member(ctx: Ctx, mm: &ModelManager, user_id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2")
+            .bind(tenant(&ctx)?)
+            .bind(user_id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn count_members_with_role(
+        ctx: Ctx,
+        mm: &ModelManager,
+        role: &str,
+    ) -> anyhow::Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM organization_members WHERE organization_id = $1 AND role = $2",
+        )
+        .bind(tenant(&ctx)?)
+        .bind(role)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(count)
+    }
+}
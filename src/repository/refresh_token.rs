@@ -0,0 +1,165 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        refresh_token::{ActiveRefreshToken, RefreshToken},
+        ModelManager,
+    },
+};
+
+pub struct NewRefreshToken<'a> {
+    pub token_hash: &'a str,
+    pub family_id: &'a str,
+    pub user_id: i64,
+    // `None` for the tokens handed out by the login endpoints.
+    pub client_id: Option<i64>,
+    pub scope: &'a str,
+    // when and how the user logged in, kept for the tokens it's exchanged for.
+    pub auth_time: OffsetDateTime,
+    pub amr: &'a [String],
+    pub session_id: Option<i64>,
+    pub ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRepository {}
+
+impl RefreshTokenRepository {
+    // the token acts in the organization of the context, if any.
+    pub async fn create(
+        ctx: Ctx,
+        mm: &ModelManager,
+        token: NewRefreshToken<'_>,
+    ) -> anyhow::Result<RefreshToken> {
+        let created: RefreshToken = sqlx::query_as(
+            r#"INSERT INTO refresh_tokens (token_hash,family_id,user_id,client_id,scope,organization_id,auth_time,amr,session_id,created_at,expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, current_timestamp, current_timestamp + make_interval(secs => $10)) RETURNING *"#,
+        )
+        .bind(token.token_hash)
+        .bind(token.family_id)
+        .bind(token.user_id)
+        .bind(token.client_id)
+        .bind(token.scope)
+        .bind(ctx.organization_id())
+        .bind(token.auth_time)
+        .bind(token.amr)
+        .bind(token.session_id)
+        .bind(token.ttl_seconds as f64)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(created)
+    }
+
+    // marks the token as used, `None` when it's unknown, expired, revoked or already used.
+    pub async fn consume(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<RefreshToken>> {
+        let token: Option<RefreshToken> = sqlx::query_as(
+            r#"UPDATE refresh_tokens SET used_at = current_timestamp WHERE token_hash = $1 AND used_at IS NULL AND revoked_at IS NULL AND expires_at > current_timestamp RETURNING *"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn find(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<RefreshToken>> {
+        let token: Option<RefreshToken> =
+            sqlx::query_as("SELECT * FROM refresh_tokens WHERE token_hash = $1")
+                .bind(token_hash)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(token)
+    }
+
+    // the tokens of the user that can still be exchanged, logins and OAuth clients alike.
+    pub async fn list_active_by_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<ActiveRefreshToken>> {
+        let tokens: Vec<ActiveRefreshToken> = sqlx::query_as(
+            r#"
+            SELECT refresh_tokens.id, oauth_clients.client_id, oauth_clients.name AS client_name,
+                refresh_tokens.scope, refresh_tokens.organization_id, refresh_tokens.session_id,
+                refresh_tokens.created_at, refresh_tokens.expires_at
+            FROM refresh_tokens
+            LEFT JOIN oauth_clients ON oauth_clients.id = refresh_tokens.client_id
+            WHERE refresh_tokens.user_id = $1 AND refresh_tokens.used_at IS NULL
+                AND refresh_tokens.revoked_at IS NULL AND refresh_tokens.expires_at > current_timestamp
+            ORDER BY refresh_tokens.created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn revoke_family(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        family_id: &str,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = current_timestamp WHERE family_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(family_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn revoke_for_session(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        session_id: i64,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = current_timestamp WHERE session_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(session_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // every token of the user, or only the ones given to the client.
+    pub async fn revoke_for_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: Option<i64>,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = current_timestamp WHERE user_id = $1 AND ($2::BIGINT IS NULL OR client_id = $2) AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM refresh_tokens WHERE expires_at <= current_timestamp")
+                .execute(&mm.db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,49 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{ctx::Ctx, model::ModelManager};
+
+#[derive(Debug, Clone)]
+pub struct RevokedTokenRepository {}
+
+impl RevokedTokenRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        jti: &str,
+        expires_at: OffsetDateTime,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // the tokens that haven't expired yet, with their expiry.
+    pub async fn list_active(
+        _ctx: Ctx,
+        mm: &ModelManager,
+    ) -> anyhow::Result<Vec<(String, OffsetDateTime)>> {
+        let revoked = sqlx::query_as(
+            "SELECT jti, expires_at FROM revoked_tokens WHERE expires_at > current_timestamp",
+        )
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    // the tokens expired by now, `jwt_auth` rejects them anyway.
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= current_timestamp")
+                .execute(&mm.db)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
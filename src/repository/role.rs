@@ -0,0 +1,210 @@
+use crate::{
+    ctx::Ctx,
+    model::{
+        role::{Permission, Role},
+        ModelManager,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct RoleRepository {}
+
+impl RoleRepository {
+    pub async fn list(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<Role>> {
+        let roles: Vec<Role> = sqlx::query_as("SELECT * FROM roles ORDER BY name")
+            .fetch_all(&mm.db)
+            .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn find_by_id(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<Option<Role>> {
+        let role: Option<Role> = sqlx::query_as("SELECT * FROM roles WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mm.db)
+            .await?;
+
+        Ok(role)
+    }
+
+    pub async fn find_by_name(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        name: &str,
+    ) -> anyhow::Result<Option<Role>> {
+        let role: Option<Role> = sqlx::query_as("SELECT * FROM roles WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&mm.db)
+            .await?;
+
+        Ok(role)
+    }
+
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        name: &str,
+        description: &str,
+    ) -> anyhow::Result<Role> {
+        let role: Role = sqlx::query_as(
+            r#"INSERT INTO roles (name,description,created_at) VALUES ($1, $2, current_timestamp) RETURNING *"#,
+        )
+        .bind(name)
+        .bind(description)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(role)
+    }
+
+    pub async fn update_description(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        description: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query("UPDATE roles SET description = $2 WHERE id = $1")
+            .bind(id)
+            .bind(description)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM roles WHERE id = $1")
+            .bind(id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_permissions(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<Permission>> {
+        let permissions: Vec<Permission> =
+            sqlx::query_as("SELECT * FROM permissions ORDER BY name")
+                .fetch_all(&mm.db)
+                .await?;
+
+        Ok(permissions)
+    }
+
+    pub async fn permissions_of_role(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        role_id: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        let permissions: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT permissions.name
+            FROM role_permissions
+            JOIN permissions ON permissions.id = role_permissions.permission_id
+            WHERE role_permissions.role_id = $1
+            ORDER BY permissions.name
+            "#,
+        )
+        .bind(role_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(permissions.into_iter().map(|(x,)| x).collect())
+    }
+
+    // replace the permissions of the role, unknown names are ignored.
+    pub async fn set_permissions(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        role_id: i64,
+        permissions: &[String],
+    ) -> anyhow::Result<()> {
+        let mut tx = mm.db.begin().await?;
+
+        sqlx::query("DELETE FROM role_permissions WHERE role_id = $1")
+            .bind(role_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"INSERT INTO role_permissions (role_id, permission_id) SELECT $1, id FROM permissions WHERE name = ANY($2)"#,
+        )
+        .bind(role_id)
+        .bind(permissions)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn roles_of_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<Role>> {
+        let roles: Vec<Role> = sqlx::query_as(
+            r#"SELECT roles.* FROM user_roles JOIN roles ON roles.id = user_roles.role_id WHERE user_roles.user_id = $1 ORDER BY roles.name"#,
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(roles)
+    }
+
+    // every permission granted to the user through its roles.
+    pub async fn permissions_of_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        let permissions: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT permissions.name
+            FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            JOIN permissions ON permissions.id = role_permissions.permission_id
+            WHERE user_roles.user_id = $1
+            ORDER BY permissions.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(permissions.into_iter().map(|(x,)| x).collect())
+    }
+
+    pub async fn assign(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        role_id: i64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO user_roles (user_id,role_id,created_at) VALUES ($1, $2, current_timestamp) ON CONFLICT DO NOTHING"#,
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unassign(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        role_id: i64,
+    ) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2")
+            .bind(user_id)
+            .bind(role_id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        service_account::{ServiceAccount, ServiceAccountKey, ServiceAccountSecret},
+        ModelManager,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct ServiceAccountRepository {}
+
+impl ServiceAccountRepository {
+    // owned by the organization of the context, if any.
+    pub async fn create(
+        ctx: Ctx,
+        mm: &ModelManager,
+        client_id: &str,
+        name: &str,
+        description: &str,
+        created_by: Option<i64>,
+    ) -> anyhow::Result<ServiceAccount> {
+        let service_account: ServiceAccount = sqlx::query_as(
+            r#"INSERT INTO service_accounts (client_id,name,description,organization_id,created_by,created_at) VALUES ($1, $2, $3, $4, $5, current_timestamp) RETURNING *"#,
+        )
+        .bind(client_id)
+        .bind(name)
+        .bind(description)
+        .bind(ctx.organization_id())
+        .bind(created_by)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(service_account)
+    }
+
+    // the service accounts of the context's organization, or the ones without any.
+    pub async fn list(ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<ServiceAccount>> {
+        let service_accounts: Vec<ServiceAccount> = sqlx::query_as(
+            "SELECT * FROM service_accounts WHERE organization_id IS NOT DISTINCT FROM $1 ORDER BY name",
+        )
+        .bind(ctx.organization_id())
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(service_accounts)
+    }
+
+    pub async fn find_by_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<ServiceAccount>> {
+        let service_account: Option<ServiceAccount> =
+            sqlx::query_as("SELECT * FROM service_accounts WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(service_account)
+    }
+
+    pub async fn find_by_client_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        client_id: &str,
+    ) -> anyhow::Result<Option<ServiceAccount>> {
+        let service_account: Option<ServiceAccount> =
+            sqlx::query_as("SELECT * FROM service_accounts WHERE client_id = $1")
+                .bind(client_id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(service_account)
+    }
+
+    pub async fn update_description(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        description: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query("UPDATE service_accounts SET description = $2 WHERE id = $1")
+            .bind(id)
+            .bind(description)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_disabled(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        disabled: bool,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE service_accounts SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, current_timestamp) ELSE NULL END WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(disabled)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM service_accounts WHERE id = $1")
+            .bind(id)
+            .execute(&mm.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn permissions_of(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        let permissions: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT permissions.name
+            FROM service_account_permissions
+            JOIN permissions ON permissions.id = service_account_permissions.permission_id
+            WHERE service_account_permissions.service_account_id = $1
+            ORDER BY permissions.name
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(permissions.into_iter().map(|(x,)| x).collect())
+    }
+
+    // replaces every permission of the service account.
+    pub async fn set_permissions(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        permissions: &[String],
+    ) -> anyhow::Result<()> {
+        let mut tx = mm.db.begin().await?;
+
+        sqlx::query("DELETE FROM service_account_permissions WHERE service_account_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"INSERT INTO service_account_permissions (service_account_id, permission_id) SELECT $1, id FROM permissions WHERE name = ANY($2)"#,
+        )
+        .bind(id)
+        .bind(permissions)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn create_secret(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        prefix: &str,
+        secret_hash: &str,
+        expires_at: Option<OffsetDateTime>,
+    ) -> anyhow::Result<ServiceAccountSecret> {
+        let secret: ServiceAccountSecret = sqlx::query_as(
+            r#"INSERT INTO service_account_secrets (service_account_id,prefix,secret_hash,created_at,expires_at) VALUES ($1, $2, $3, current_timestamp, $4) RETURNING *"#,
+        )
+        .bind(id)
+        .bind(prefix)
+        .bind(secret_hash)
+        .bind(expires_at)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(secret)
+    }
+
+    pub async fn list_secrets(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Vec<ServiceAccountSecret>> {
+        let secrets: Vec<ServiceAccountSecret> = sqlx::query_as(
+            "SELECT * FROM service_account_secrets WHERE service_account_id = $1 ORDER BY created_at",
+        )
+        .bind(id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(secrets)
+    }
+
+    // a secret that hasn't expired, its last use is recorded.
+    pub async fn use_secret(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        secret_hash: &str,
+    ) -> anyhow::Result<Option<ServiceAccountSecret>> {
+        let secret: Option<ServiceAccountSecret> = sqlx::query_as(
+            r#"UPDATE service_account_secrets SET last_used_at = current_timestamp WHERE service_account_id = $1 AND secret_hash = $2 AND (expires_at IS NULL OR expires_at > current_timestamp) RETURNING *"#,
+        )
+        .bind(id)
+        .bind(secret_hash)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(secret)
+    }
+
+    pub async fn delete_secret(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        secret_id: i64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM service_account_secrets WHERE service_account_id = $1 AND id = $2",
+        )
+        .bind(id)
+        .bind(secret_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_key(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        kid: &str,
+        algorithm: &str,
+        public_key: &str,
+    ) -> anyhow::Result<ServiceAccountKey> {
+        let key: ServiceAccountKey = sqlx::query_as(
+            r#"INSERT INTO service_account_keys (service_account_id,kid,algorithm,public_key,created_at) VALUES ($1, $2, $3, $4, current_timestamp) RETURNING *"#,
+        )
+        .bind(id)
+        .bind(kid)
+        .bind(algorithm)
+        .bind(public_key)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(key)
+    }
+
+    pub async fn list_keys(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Vec<ServiceAccountKey>> {
+        let keys: Vec<ServiceAccountKey> = sqlx::query_as(
+            "SELECT * FROM service_account_keys WHERE service_account_id = $1 ORDER BY created_at",
+        )
+        .bind(id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(keys)
+    }
+
+    pub async fn find_key(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        kid: &str,
+    ) -> anyhow::Result<Option<ServiceAccountKey>> {
+        let key: Option<ServiceAccountKey> =
+            sqlx::query_as("SELECT * FROM service_account_keys WHERE kid = $1")
+                .bind(kid)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(key)
+    }
+
+    pub async fn delete_key(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        key_id: i64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM service_account_keys WHERE service_account_id = $1 AND id = $2",
+        )
+        .bind(id)
+        .bind(key_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // false when the assertion was already used.
+    pub async fn record_assertion(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        jti: &str,
+        expires_at: OffsetDateTime,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO service_account_assertions (jti,expires_at) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_expired_assertions(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM service_account_assertions WHERE expires_at <= current_timestamp",
+        )
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
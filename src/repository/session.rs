@@ -0,0 +1,135 @@
+use crate::{
+    ctx::Ctx,
+    model::{session::Session, ModelManager},
+};
+
+#[derive(Debug, Clone)]
+pub struct SessionRepository {}
+
+impl SessionRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        user_agent: Option<&str>,
+        ip: Option<&str>,
+        amr: &[String],
+        ttl_seconds: i64,
+    ) -> anyhow::Result<Session> {
+        let session: Session = sqlx::query_as(
+            r#"INSERT INTO sessions (user_id,user_agent,ip,amr,created_at,last_seen_at,expires_at) VALUES ($1, $2, $3, $4, current_timestamp, current_timestamp, current_timestamp + make_interval(secs => $5)) RETURNING *"#,
+        )
+        .bind(user_id)
+        .bind(user_agent)
+        .bind(ip)
+        .bind(amr)
+        .bind(ttl_seconds as f64)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(session)
+    }
+
+    // `None` once the session is revoked or expired.
+    pub async fn find_active(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<Session>> {
+        let session: Option<Session> = sqlx::query_as(
+            "SELECT * FROM sessions WHERE id = $1 AND revoked_at IS NULL AND expires_at > current_timestamp",
+        )
+        .bind(id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(session)
+    }
+
+    pub async fn list_active_by_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<Session>> {
+        let sessions: Vec<Session> = sqlx::query_as(
+            "SELECT * FROM sessions WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > current_timestamp ORDER BY last_seen_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    // at most once a minute, it's called on every authenticated request.
+    pub async fn touch(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE sessions SET last_seen_at = current_timestamp WHERE id = $1 AND last_seen_at < current_timestamp - interval '1 minute'"#,
+        )
+        .bind(id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // the session lasts as long as the refresh token it was just exchanged for.
+    pub async fn extend(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        ttl_seconds: i64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE sessions SET last_seen_at = current_timestamp, expires_at = current_timestamp + make_interval(secs => $2) WHERE id = $1 AND revoked_at IS NULL"#,
+        )
+        .bind(id)
+        .bind(ttl_seconds as f64)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // `false` when the user has no such active session.
+    pub async fn revoke(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        id: i64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked_at = current_timestamp WHERE user_id = $1 AND id = $2 AND revoked_at IS NULL AND expires_at > current_timestamp",
+        )
+        .bind(user_id)
+        .bind(id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn revoke_for_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked_at = current_timestamp WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // their refresh tokens expired by then as well, and go with them.
+    pub async fn delete_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= current_timestamp")
+            .execute(&mm.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
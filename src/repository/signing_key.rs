@@ -0,0 +1,140 @@
+use crate::{
+    ctx::Ctx,
+    model::{signing_key::SigningKey, ModelManager},
+};
+
+// held while the first key is created, see `create_first`.
+const FIRST_KEY_LOCK: i64 = 0x0073_6967_6e69_6e67;
+
+#[derive(Debug, Clone)]
+pub struct SigningKeyRepository {}
+
+impl SigningKeyRepository {
+    // the key starts signing `activates_in_seconds` from now, `private_key` is encrypted.
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        kid: &str,
+        algorithm: &str,
+        private_key: &[u8],
+        activates_in_seconds: i64,
+    ) -> anyhow::Result<SigningKey> {
+        let key: SigningKey = sqlx::query_as(
+            r#"INSERT INTO signing_keys (kid,algorithm,private_key,encrypted,created_at,activates_at) VALUES ($1, $2, $3, TRUE, current_timestamp, current_timestamp + make_interval(secs => $4)) RETURNING *"#,
+        )
+        .bind(kid)
+        .bind(algorithm)
+        .bind(private_key)
+        .bind(activates_in_seconds as f64)
+        .fetch_one(&mm.db)
+        .await?;
+
+        Ok(key)
+    }
+
+    // Signs right away unless another key already does, false then. Instances creating the
+    // first key at the same time are serialized by an advisory lock, only one of them stores it.
+    pub async fn create_first(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        kid: &str,
+        algorithm: &str,
+        private_key: &[u8],
+    ) -> anyhow::Result<bool> {
+        let mut tx = mm.db.begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(FIRST_KEY_LOCK)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query(
+            r#"INSERT INTO signing_keys (kid,algorithm,private_key,encrypted,created_at,activates_at) SELECT $1, $2, $3, TRUE, current_timestamp, current_timestamp WHERE NOT EXISTS (SELECT 1 FROM signing_keys WHERE retired_at IS NULL AND activates_at <= current_timestamp)"#,
+        )
+        .bind(kid)
+        .bind(algorithm)
+        .bind(private_key)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // newest first, retired ones included.
+    pub async fn list(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<SigningKey>> {
+        let keys: Vec<SigningKey> =
+            sqlx::query_as("SELECT * FROM signing_keys ORDER BY activates_at DESC, id DESC")
+                .fetch_all(&mm.db)
+                .await?;
+
+        Ok(keys)
+    }
+
+    // the keys not retired yet, pending ones included, newest first.
+    pub async fn list_published(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<SigningKey>> {
+        let keys: Vec<SigningKey> = sqlx::query_as(
+            "SELECT * FROM signing_keys WHERE retired_at IS NULL ORDER BY activates_at DESC, id DESC",
+        )
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(keys)
+    }
+
+    // the keys stored before their encryption, retired ones included.
+    pub async fn list_unencrypted(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<Vec<SigningKey>> {
+        let keys: Vec<SigningKey> =
+            sqlx::query_as("SELECT * FROM signing_keys WHERE encrypted = FALSE")
+                .fetch_all(&mm.db)
+                .await?;
+
+        Ok(keys)
+    }
+
+    pub async fn set_encrypted(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        private_key: &[u8],
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE signing_keys SET private_key = $2, encrypted = TRUE WHERE id = $1 AND encrypted = FALSE",
+        )
+        .bind(id)
+        .bind(private_key)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn retire(_ctx: Ctx, mm: &ModelManager, kid: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE signing_keys SET retired_at = current_timestamp WHERE kid = $1 AND retired_at IS NULL",
+        )
+        .bind(kid)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Retires the keys superseded by a newer one for more than `grace_seconds`,
+    // the tokens they signed have expired by then.
+    pub async fn retire_superseded(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        grace_seconds: i64,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            r#"UPDATE signing_keys k SET retired_at = current_timestamp WHERE k.retired_at IS NULL AND EXISTS (SELECT 1 FROM signing_keys n WHERE n.retired_at IS NULL AND n.activates_at > k.activates_at AND n.activates_at <= current_timestamp - make_interval(secs => $1))"#,
+        )
+        .bind(grace_seconds as f64)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
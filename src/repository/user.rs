@@ -1,16 +1,22 @@
 use crate::{
     ctx::Ctx,
     http::request::user::CreateUserDTO,
-    model::{user::User, ModelManager},
+    model::{
+        user::{User, UserFilter, UserPatch},
+        ModelManager,
+    },
+    service::constant::PASSWORD_AUTH_PROVIDER,
 };
+use sqlx::{types::time::OffsetDateTime, Postgres, QueryBuilder};
 
 #[derive(Debug, Clone)]
 pub struct UserRepository {}
 
 impl UserRepository {
-    pub async fn create(_ctx: Ctx, mm: &ModelManager, req: CreateUserDTO) -> anyhow::Result<User> {
+    // the user belongs to the organization of the context, if any.
+    pub async fn create(ctx: Ctx, mm: &ModelManager, req: CreateUserDTO) -> anyhow::Result<User> {
         let user: User = sqlx::query_as(
-            r#"INSERT INTO users (name,email,password,created_at,auth_provider,auth_provider_user_id,secret) VALUES ($1, $2, $3, current_timestamp, $4,$5,$6) RETURNING *"#,
+            r#"INSERT INTO users (name,email,password,created_at,auth_provider,auth_provider_user_id,secret,organization_id) VALUES ($1, $2, $3, current_timestamp, $4,$5,$6,$7) RETURNING *"#,
         )
             .bind(req.name)
             .bind(req.email)
@@ -18,63 +24,427 @@ impl UserRepository {
             .bind(req.auth_provider)
             .bind(req.auth_provider_user_id)
             .bind(req.secret)
+            .bind(ctx.organization_id())
             .fetch_one(&mm.db)
             .await?;
 
         Ok(user)
     }
 
+    // insert the user unless the email is already taken, in which case nothing is returned.
+    pub async fn create_if_absent(
+        ctx: Ctx,
+        mm: &ModelManager,
+        req: CreateUserDTO,
+    ) -> anyhow::Result<Option<User>> {
+        let user: Option<User> = sqlx::query_as(
+            r#"INSERT INTO users (name,email,password,created_at,auth_provider,auth_provider_user_id,secret,organization_id) VALUES ($1, $2, $3, current_timestamp, $4,$5,$6,$7) ON CONFLICT ((COALESCE(organization_id, 0)), email) WHERE deleted_at IS NULL DO NOTHING RETURNING *"#,
+        )
+            .bind(req.name)
+            .bind(req.email)
+            .bind(req.password)
+            .bind(req.auth_provider)
+            .bind(req.auth_provider_user_id)
+            .bind(req.secret)
+            .bind(ctx.organization_id())
+            .fetch_optional(&mm.db)
+            .await?;
+
+        Ok(user)
+    }
+
+    // emails are unique per organization, the one of the context or none.
     pub async fn get_by_email(
-        _ctx: Ctx,
+        ctx: Ctx,
         mm: &ModelManager,
         email: &str,
     ) -> anyhow::Result<Option<User>> {
-        let user: Option<User> =
-            sqlx::query_as("SELECT * FROM users where email = $1 AND deleted_at IS NULL")
-                .bind(email)
-                .fetch_optional(&mm.db)
-                .await?;
+        let user: Option<User> = sqlx::query_as(
+            "SELECT * FROM users where email = $1 AND organization_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL",
+        )
+        .bind(email)
+        .bind(ctx.organization_id())
+        .fetch_optional(&mm.db)
+        .await?;
 
         Ok(user)
     }
 
     pub async fn get_by_id(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<User> {
         let user: User = sqlx::query_as("SELECT * FROM users where id = $1 AND deleted_at IS NULL")
-            .bind(&id)
+            .bind(id)
             .fetch_one(&mm.db)
             .await?;
 
         Ok(user)
     }
 
-    pub async fn update(
+    pub async fn find_by_id(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<Option<User>> {
+        let user: Option<User> =
+            sqlx::query_as("SELECT * FROM users where id = $1 AND deleted_at IS NULL")
+                .bind(id)
+                .fetch_optional(&mm.db)
+                .await?;
+
+        Ok(user)
+    }
+
+    // the new address was confirmed through the link sent to it, so it's verified as well.
+    pub async fn update_email(
         _ctx: Ctx,
         mm: &ModelManager,
-        id: &i64,
-        model: &User,
+        id: i64,
+        email: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE users SET email = $2, email_verified_at = current_timestamp, modified_at = current_timestamp WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(email)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // truncated to the second as the `iat` claim is, tokens from the same second stay valid.
+    pub async fn revoke_tokens(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE users SET tokens_valid_after = date_trunc('second', current_timestamp) WHERE id = $1"#,
+        )
+        .bind(id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // `false` when a code of this step or a later one was already accepted.
+    pub async fn claim_totp_step(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        step: i64,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            r#"UPDATE users SET totp_last_step = $2 WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)"#,
+        )
+        .bind(id)
+        .bind(step)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_email_verified(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE users SET email_verified_at = COALESCE(email_verified_at, current_timestamp) WHERE id = $1"#,
+        )
+        .bind(id)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // the account disappears from every lookup and its access tokens stop working,
+    // it's kept for `grace_seconds` so it can be restored.
+    pub async fn soft_delete(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        grace_seconds: i64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE users SET deleted_at = current_timestamp, purge_after = current_timestamp + make_interval(secs => $2), tokens_valid_after = date_trunc('second', current_timestamp) WHERE id = $1 AND deleted_at IS NULL"#,
+        )
+        .bind(id)
+        .bind(grace_seconds as f64)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // a soft deleted account that hasn't been purged yet.
+    pub async fn find_deleted_by_id(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> anyhow::Result<Option<User>> {
+        let user: Option<User> = sqlx::query_as(
+            "SELECT * FROM users where id = $1 AND deleted_at IS NOT NULL AND purge_after > current_timestamp",
+        )
+        .bind(id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(user)
+    }
+
+    pub async fn restore(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<Option<User>> {
+        let user: Option<User> = sqlx::query_as(
+            r#"UPDATE users SET deleted_at = NULL, purge_after = NULL, modified_at = current_timestamp WHERE id = $1 AND deleted_at IS NOT NULL AND purge_after > current_timestamp RETURNING *"#,
+        )
+        .bind(id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(user)
+    }
+
+    // hard delete the accounts past their grace period, linked rows go with them (ON DELETE CASCADE).
+    pub async fn purge_expired(_ctx: Ctx, mm: &ModelManager) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            r#"DELETE FROM users WHERE deleted_at IS NOT NULL AND purge_after <= current_timestamp"#,
+        )
+        .execute(&mm.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // restricted to the members of the context's organization when there's one.
+    pub async fn list(
+        ctx: Ctx,
+        mm: &ModelManager,
+        filter: &UserFilter,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<User>> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM users");
+        push_filter(&mut query, &ctx, filter);
+
+        query
+            .push(" ORDER BY id LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let users: Vec<User> = query.build_query_as().fetch_all(&mm.db).await?;
+
+        Ok(users)
+    }
+
+    pub async fn count(ctx: Ctx, mm: &ModelManager, filter: &UserFilter) -> anyhow::Result<i64> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM users");
+        push_filter(&mut query, &ctx, filter);
+
+        let (count,): (i64,) = query.build_query_as().fetch_one(&mm.db).await?;
+
+        Ok(count)
+    }
+
+    // disabling also signs the user out everywhere.
+    pub async fn set_disabled(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        disabled: bool,
+    ) -> anyhow::Result<Option<User>> {
+        let query = match disabled {
+            true => {
+                r#"UPDATE users SET disabled_at = COALESCE(disabled_at, current_timestamp), tokens_valid_after = date_trunc('second', current_timestamp), modified_at = current_timestamp WHERE id = $1 AND deleted_at IS NULL RETURNING *"#
+            }
+            false => {
+                r#"UPDATE users SET disabled_at = NULL, modified_at = current_timestamp WHERE id = $1 AND deleted_at IS NULL RETURNING *"#
+            }
+        };
+
+        let user: Option<User> = sqlx::query_as(query)
+            .bind(id)
+            .fetch_optional(&mm.db)
+            .await?;
+
+        Ok(user)
+    }
+
+    // suspending also signs the user out everywhere.
+    pub async fn suspend(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        reason: &str,
+        until: Option<OffsetDateTime>,
+        suspended_by: Option<i64>,
+    ) -> anyhow::Result<Option<User>> {
+        let user: Option<User> = sqlx::query_as(
+            r#"UPDATE users SET suspended_at = current_timestamp, suspended_until = $2, suspension_reason = $3, suspended_by = $4, tokens_valid_after = date_trunc('second', current_timestamp), modified_at = current_timestamp WHERE id = $1 AND deleted_at IS NULL RETURNING *"#,
+        )
+        .bind(id)
+        .bind(until)
+        .bind(reason)
+        .bind(suspended_by)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(user)
+    }
+
+    pub async fn unsuspend(_ctx: Ctx, mm: &ModelManager, id: i64) -> anyhow::Result<Option<User>> {
+        let user: Option<User> = sqlx::query_as(
+            r#"UPDATE users SET suspended_at = NULL, suspended_until = NULL, suspension_reason = NULL, suspended_by = NULL, modified_at = current_timestamp WHERE id = $1 AND deleted_at IS NULL RETURNING *"#,
+        )
+        .bind(id)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(user)
+    }
+
+    // clear the suspensions past their end, returns the ids of the users they were lifted for.
+    pub async fn lift_expired_suspensions(
+        _ctx: Ctx,
+        mm: &ModelManager,
+    ) -> anyhow::Result<Vec<i64>> {
+        let ids: Vec<(i64,)> = sqlx::query_as(
+            r#"UPDATE users SET suspended_at = NULL, suspended_until = NULL, suspension_reason = NULL, suspended_by = NULL, modified_at = current_timestamp WHERE suspended_until <= current_timestamp RETURNING id"#,
+        )
+        .fetch_all(&mm.db)
+        .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn require_password_reset(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
     ) -> anyhow::Result<()> {
         sqlx::query(
-            r#"
-            UPDATE users
-                SET
-                    modified_at = current_timestamp,
-                    name = $2,
-                    email = $3,
-                    auth_provider = COALESCE(auth_provider, $4),
-                    auth_provider_user_id = COALESCE(auth_provider_user_id, $5),
-                    secret = COALESCE(secret, $6)
-                WHERE id = $1;
-            "#,
+            r#"UPDATE users SET password_reset_required = TRUE, tokens_valid_after = date_trunc('second', current_timestamp), modified_at = current_timestamp WHERE id = $1"#,
         )
         .bind(id)
-        .bind(&model.name)
-        .bind(&model.email)
-        .bind(&model.auth_provider)
-        .bind(&model.auth_provider_user_id)
-        .bind(&model.secret)
         .execute(&mm.db)
         .await?;
 
         Ok(())
     }
+
+    // `password` is the bcrypt hash, every token issued with the old password stops working.
+    pub async fn reset_password(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        password: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE users SET password = $2, password_reset_required = FALSE, tokens_valid_after = date_trunc('second', current_timestamp), modified_at = current_timestamp WHERE id = $1"#,
+        )
+        .bind(id)
+        .bind(password)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // only the columns set in the patch are written.
+    pub async fn update(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: &i64,
+        patch: &UserPatch,
+    ) -> anyhow::Result<User> {
+        let mut query =
+            QueryBuilder::<Postgres>::new("UPDATE users SET modified_at = current_timestamp");
+
+        if let Some(name) = &patch.name {
+            query.push(", name = ").push_bind(name);
+        }
+        if let Some(email) = &patch.email {
+            query.push(", email = ").push_bind(email);
+        }
+        if let Some(auth_provider) = &patch.auth_provider {
+            query.push(", auth_provider = ").push_bind(auth_provider);
+        }
+        if let Some(auth_provider_user_id) = &patch.auth_provider_user_id {
+            query
+                .push(", auth_provider_user_id = ")
+                .push_bind(auth_provider_user_id);
+        }
+        if let Some(secret) = &patch.secret {
+            query.push(", secret = ").push_bind(secret);
+        }
+        if let Some(user_metadata) = &patch.user_metadata {
+            query.push(", user_metadata = ").push_bind(user_metadata);
+        }
+        if let Some(app_metadata) = &patch.app_metadata {
+            query.push(", app_metadata = ").push_bind(app_metadata);
+        }
+
+        query
+            .push(" WHERE id = ")
+            .push_bind(id)
+            .push(" AND deleted_at IS NULL RETURNING *");
+
+        let user: User = query.build_query_as().fetch_one(&mm.db).await?;
+
+        Ok(user)
+    }
+}
+
+fn push_filter(query: &mut QueryBuilder<Postgres>, ctx: &Ctx, filter: &UserFilter) {
+    match filter.deleted {
+        true => query.push(" WHERE deleted_at IS NOT NULL AND purge_after > current_timestamp"),
+        false => query.push(" WHERE deleted_at IS NULL"),
+    };
+
+    if let Some(organization_id) = ctx.organization_id() {
+        query
+            .push(" AND id IN (SELECT user_id FROM organization_members WHERE organization_id = ")
+            .push_bind(organization_id)
+            .push(")");
+    }
+
+    if let Some(email) = &filter.email {
+        query.push(" AND email = ").push_bind(email.clone());
+    }
+    if let Some(provider) = &filter.provider {
+        match provider.as_str() {
+            PASSWORD_AUTH_PROVIDER => query.push(" AND password <> ''"),
+            _ => query
+                .push(" AND auth_provider = ")
+                .push_bind(provider.clone()),
+        };
+    }
+    if let Some(mfa_enabled) = filter.mfa_enabled {
+        match mfa_enabled {
+            true => query.push(" AND secret IS NOT NULL"),
+            false => query.push(" AND secret IS NULL"),
+        };
+    }
+    if let Some(created_after) = filter.created_after {
+        query.push(" AND created_at >= ").push_bind(created_after);
+    }
+    if let Some(created_before) = filter.created_before {
+        query.push(" AND created_at < ").push_bind(created_before);
+    }
+    if let Some(disabled) = filter.disabled {
+        match disabled {
+            true => query.push(" AND disabled_at IS NOT NULL"),
+            false => query.push(" AND disabled_at IS NULL"),
+        };
+    }
+    if let Some(suspended) = filter.suspended {
+        match suspended {
+            true => query.push(
+                " AND suspended_at IS NOT NULL AND (suspended_until IS NULL OR suspended_until > current_timestamp)",
+            ),
+            false => query.push(
+                " AND (suspended_at IS NULL OR suspended_until <= current_timestamp)",
+            ),
+        };
+    }
+    if let Some(user_metadata) = &filter.user_metadata {
+        query
+            .push(" AND user_metadata @> ")
+            .push_bind(user_metadata.clone());
+    }
+    if let Some(app_metadata) = &filter.app_metadata {
+        query
+            .push(" AND app_metadata @> ")
+            .push_bind(app_metadata.clone());
+    }
 }
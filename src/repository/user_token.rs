@@ -0,0 +1,112 @@
+use crate::{
+    ctx::Ctx,
+    model::{user_token::UserToken, ModelManager},
+};
+
+#[derive(Debug, Clone)]
+pub struct UserTokenRepository {}
+
+impl UserTokenRepository {
+    pub async fn create(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        purpose: &str,
+        token_hash: &str,
+        ttl_seconds: i64,
+        payload: Option<serde_json::Value>,
+    ) -> anyhow::Result<UserToken> {
+        let token: UserToken = sqlx::query_as(
+            r#"INSERT INTO user_tokens (user_id,purpose,token_hash,created_at,expires_at,payload) VALUES ($1, $2, $3, current_timestamp, current_timestamp + make_interval(secs => $4), $5) RETURNING *"#,
+        )
+            .bind(user_id)
+            .bind(purpose)
+            .bind(token_hash)
+            .bind(ttl_seconds as f64)
+            .bind(payload)
+            .fetch_one(&mm.db)
+            .await?;
+
+        Ok(token)
+    }
+
+    // a token that can still be consumed.
+    pub async fn get_active(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        purpose: &str,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<UserToken>> {
+        let token: Option<UserToken> = sqlx::query_as(
+            r#"SELECT * FROM user_tokens WHERE token_hash = $1 AND purpose = $2 AND consumed_at IS NULL AND expires_at > current_timestamp"#,
+        )
+        .bind(token_hash)
+        .bind(purpose)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(token)
+    }
+
+    // mark the token as used, returns nothing when it's unknown, expired or already used.
+    pub async fn consume(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        purpose: &str,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<UserToken>> {
+        let token: Option<UserToken> = sqlx::query_as(
+            r#"
+            UPDATE user_tokens
+                SET consumed_at = current_timestamp
+                WHERE token_hash = $1
+                    AND purpose = $2
+                    AND consumed_at IS NULL
+                    AND expires_at > current_timestamp
+                RETURNING *;
+            "#,
+        )
+        .bind(token_hash)
+        .bind(purpose)
+        .fetch_optional(&mm.db)
+        .await?;
+
+        Ok(token)
+    }
+
+    // counts a wrong answer, the token is consumed once `max_attempts` is reached.
+    pub async fn record_failure(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        id: i64,
+        max_attempts: i32,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE user_tokens SET failed_attempts = failed_attempts + 1, consumed_at = CASE WHEN failed_attempts + 1 >= $2 THEN current_timestamp ELSE consumed_at END WHERE id = $1 AND consumed_at IS NULL"#,
+        )
+        .bind(id)
+        .bind(max_attempts)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+
+    // invalidate every pending token of the user for the given purpose.
+    pub async fn revoke_for_user(
+        _ctx: Ctx,
+        mm: &ModelManager,
+        user_id: i64,
+        purpose: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"UPDATE user_tokens SET consumed_at = current_timestamp WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL"#,
+        )
+        .bind(user_id)
+        .bind(purpose)
+        .execute(&mm.db)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use std::env;
+
+use anyhow::Context;
+use bcrypt::verify;
+use chrono::Utc;
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{client::ClientMeta, user::DeleteAccountDTO},
+        response::{BaseResponse, MessageResponse},
+    },
+    model::{user::User, ModelManager},
+    pkg::{
+        mail::{self, Mail},
+        util::{digest::sha256_hex, rand::generate_random_string},
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_ACCOUNT_DELETED, AUDIT_EVENT_ACCOUNT_RESTORED, TOKEN_PURPOSE_ACCOUNT_RESTORE,
+    },
+    error::Result,
+    user::UserService,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 32;
+const DEFAULT_GRACE_DAYS: i64 = 30;
+// accounts without a password or MFA prove who they are with a freshly issued token.
+const DEFAULT_REAUTH_MAX_AGE_SECONDS: i64 = 5 * 60;
+
+fn grace_seconds() -> i64 {
+    env::var("ACCOUNT_DELETION_GRACE_DAYS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_GRACE_DAYS)
+        .max(0)
+        * 24
+        * 60
+        * 60
+}
+
+fn reauth_max_age_seconds() -> i64 {
+    env::var("REAUTH_MAX_AGE_SECONDS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REAUTH_MAX_AGE_SECONDS)
+}
+
+fn invalid_link() -> ServiceError {
+    ServiceError::BadRequest(String::from("link is invalid or has expired"))
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountDeletionService {}
+
+impl AccountDeletionService {
+    // Soft delete the account of the caller, the restore link mailed to the user
+    // works until the account is purged.
+    pub async fn delete(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &DeleteAccountDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        // a stolen access token alone shouldn't be enough to delete the account.
+        if !user.password.is_empty() {
+            let password = req.password.as_deref().unwrap_or_default();
+
+            if !verify(password.as_bytes(), &user.password)? {
+                return Err(ServiceError::InvalidLoginAttmpt);
+            }
+        }
+
+        if user.secret.is_some() {
+            let code = req.code.as_deref().unwrap_or_default();
+            UserService::verify_totp_code(mm, &user, code).await?;
+        }
+
+        if user.password.is_empty()
+            && user.secret.is_none()
+            && Utc::now().timestamp() - ctx.issued_at() as i64 > reauth_max_age_seconds()
+        {
+            return Err(ServiceError::ForbiddenWithMessage(String::from(
+                "login again before deleting the account",
+            )));
+        }
+
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+        let grace_seconds = grace_seconds();
+
+        UserRepository::soft_delete(Ctx::root_ctx(), mm, user.id, grace_seconds).await?;
+
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_ACCOUNT_DELETED,
+            client,
+            json!({ "grace_seconds": grace_seconds }),
+        )
+        .await;
+
+        let message = match grace_seconds {
+            0 => {
+                mail::send_in_background(Mail::new(
+                    &user.email,
+                    "Your account was deleted",
+                    format!(
+                        "Hi {},\n\nyour account was deleted as requested, all of its data will be erased shortly.",
+                        user.name
+                    ),
+                ));
+
+                "account deleted"
+            }
+            _ => {
+                let token = generate_random_string(TOKEN_LENGTH);
+
+                UserTokenRepository::create(
+                    Ctx::root_ctx(),
+                    mm,
+                    user.id,
+                    TOKEN_PURPOSE_ACCOUNT_RESTORE,
+                    &sha256_hex(&token),
+                    grace_seconds,
+                    None,
+                )
+                .await?;
+
+                mail::send_in_background(Mail::new(
+                    &user.email,
+                    "Your account was deleted",
+                    format!(
+                        "Hi {},\n\nyour account was deleted as requested, all of its data will be erased in {} days.\nIf you change your mind, restore it before then with the link below:\n\n{}/me/restore?token={}",
+                        user.name,
+                        grace_seconds / (24 * 60 * 60),
+                        base_url,
+                        token
+                    ),
+                ));
+
+                "account deleted, it can be restored with the link sent by email until it is purged"
+            }
+        };
+
+        Ok(BaseResponse::new(200, MessageResponse::new(message)))
+    }
+
+    // The link is only spent once the account is back, it keeps working after the address
+    // turned out to be taken and was freed again.
+    pub async fn restore(
+        mm: &ModelManager,
+        token: &str,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let token_hash = sha256_hex(token);
+
+        let record = UserTokenRepository::get_active(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_ACCOUNT_RESTORE,
+            &token_hash,
+        )
+        .await?
+        .ok_or_else(invalid_link)?;
+
+        let user = UserRepository::find_deleted_by_id(Ctx::root_ctx(), mm, record.user_id)
+            .await?
+            .ok_or_else(invalid_link)?;
+
+        let user = Self::restore_user(mm, &user).await?;
+
+        UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_ACCOUNT_RESTORE,
+            &token_hash,
+        )
+        .await?;
+
+        AuditService::record(mm, user.id, AUDIT_EVENT_ACCOUNT_RESTORED, client, json!({})).await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("account restored, you can login again"),
+        ))
+    }
+
+    // bring back a soft deleted account that hasn't been purged yet.
+    pub async fn restore_user(mm: &ModelManager, user: &User) -> Result<User> {
+        // the address is free for anyone to register while the account is deleted.
+        if UserRepository::get_by_email(
+            Ctx::root_ctx().with_organization(user.organization_id),
+            mm,
+            &user.email,
+        )
+        .await?
+        .is_some()
+        {
+            return Err(ServiceError::ObjectConflict(String::from(
+                "email address is already in use by another account",
+            )));
+        }
+
+        let user = UserRepository::restore(Ctx::root_ctx(), mm, user.id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(String::from("account can't be restored")))?;
+
+        Ok(user)
+    }
+
+    pub async fn purge_expired(mm: &ModelManager) -> Result<u64> {
+        let purged = UserRepository::purge_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::PgPool;
+
+    use super::AccountDeletionService;
+    use crate::{
+        ctx::Ctx,
+        http::request::client::ClientMeta,
+        model::ModelManager,
+        pkg::util::digest::sha256_hex,
+        repository::{user::UserRepository, user_token::UserTokenRepository},
+        service::{constant::TOKEN_PURPOSE_ACCOUNT_RESTORE, ServiceError},
+    };
+
+    const TOKEN: &str = "restore-token";
+
+    // user 1 deleted a day ago, with a restore link good for `ttl_seconds`.
+    async fn deleted_user(mm: &ModelManager, ttl_seconds: i64) {
+        sqlx::query("INSERT INTO users (id,name,email,password,created_at,deleted_at,purge_after) VALUES (1, 'user', 'user@example.com', '', current_timestamp, current_timestamp - interval '1 day', current_timestamp + interval '29 days')")
+            .execute(&mm.db)
+            .await
+            .unwrap();
+
+        UserTokenRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            1,
+            TOKEN_PURPOSE_ACCOUNT_RESTORE,
+            &sha256_hex(TOKEN),
+            ttl_seconds,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn is_deleted(mm: &ModelManager) -> bool {
+        UserRepository::find_deleted_by_id(Ctx::root_ctx(), mm, 1)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    #[sqlx::test]
+    async fn restore_keeps_the_link_when_the_email_is_taken(db: PgPool) {
+        let mm = ModelManager { db };
+        deleted_user(&mm, 60).await;
+        sqlx::query("INSERT INTO users (id,name,email,password,created_at) VALUES (2, 'other', 'user@example.com', '', current_timestamp)")
+            .execute(&mm.db)
+            .await
+            .unwrap();
+
+        let result = AccountDeletionService::restore(&mm, TOKEN, &ClientMeta::default()).await;
+        assert!(matches!(result, Err(ServiceError::ObjectConflict(_))));
+        assert!(is_deleted(&mm).await);
+
+        // the address was freed, the same link restores the account.
+        sqlx::query("UPDATE users SET deleted_at = current_timestamp WHERE id = 2")
+            .execute(&mm.db)
+            .await
+            .unwrap();
+
+        AccountDeletionService::restore(&mm, TOKEN, &ClientMeta::default())
+            .await
+            .unwrap();
+        assert!(!is_deleted(&mm).await);
+
+        // and is spent now.
+        let result = AccountDeletionService::restore(&mm, TOKEN, &ClientMeta::default()).await;
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+    }
+
+    #[sqlx::test]
+    async fn restore_rejects_expired_links(db: PgPool) {
+        let mm = ModelManager { db };
+        deleted_user(&mm, -60).await;
+
+        let result = AccountDeletionService::restore(&mm, TOKEN, &ClientMeta::default()).await;
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+        assert!(is_deleted(&mm).await);
+    }
+}
@@ -0,0 +1,359 @@
+use serde_json::json;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{
+            admin::{SuspendUserDTO, UserSearchQuery},
+            client::ClientMeta,
+        },
+        response::{
+            admin::{AdminUserDTO, UserPageDTO},
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{
+        user::{User, UserFilter, UserPatch},
+        ModelManager,
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+};
+
+use super::{
+    account_deletion::AccountDeletionService,
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_ACCOUNT_DISABLED, AUDIT_EVENT_ACCOUNT_ENABLED, AUDIT_EVENT_ACCOUNT_RESTORED,
+        AUDIT_EVENT_ACCOUNT_SUSPENDED, AUDIT_EVENT_ACCOUNT_UNSUSPENDED,
+        AUDIT_EVENT_APP_METADATA_UPDATED, AUDIT_EVENT_MFA_RESET,
+        AUDIT_EVENT_PASSWORD_RESET_REQUIRED, AUDIT_EVENT_SESSIONS_REVOKED,
+        TOKEN_PURPOSE_MFA_CHALLENGE,
+    },
+    error::Result,
+    password_reset::PasswordResetService,
+    session::SessionService,
+    user::UserService,
+    ServiceError,
+};
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("user not found"))
+}
+
+// the page, from 1, and the number of users per page of a search.
+fn page_bounds(page: Option<i64>, per_page: Option<i64>) -> (i64, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+
+    (page, per_page)
+}
+
+// Account management for administrators, every change is recorded in the audit
+// events of the target user along with the administrator who made it.
+#[derive(Debug, Clone)]
+pub struct AdminService {}
+
+impl AdminService {
+    pub async fn search_users(mm: &ModelManager, query: &UserSearchQuery) -> Result<UserPageDTO> {
+        let (page, per_page) = page_bounds(query.page, query.per_page);
+
+        let filter = UserFilter {
+            email: query.email.clone(),
+            provider: query.provider.clone(),
+            mfa_enabled: query.mfa_enabled,
+            created_after: query.created_after,
+            created_before: query.created_before,
+            disabled: query.disabled,
+            suspended: query.suspended,
+            deleted: query.deleted,
+            ..Default::default()
+        };
+
+        let tenant = Ctx::root_ctx().with_organization(query.organization);
+
+        let users =
+            UserRepository::list(tenant.clone(), mm, &filter, per_page, (page - 1) * per_page)
+                .await?;
+        let total = UserRepository::count(tenant, mm, &filter).await?;
+
+        Ok(UserPageDTO {
+            users: users.into_iter().map(Into::into).collect(),
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    pub async fn get_user(mm: &ModelManager, id: i64) -> Result<AdminUserDTO> {
+        let user = Self::find_user(mm, id).await?;
+
+        Ok(user.into())
+    }
+
+    pub async fn disable_user(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        if id == ctx.user_id() as i64 {
+            return Err(ServiceError::BadRequest(String::from(
+                "you can't disable your own account",
+            )));
+        }
+
+        let user = UserRepository::set_disabled(Ctx::root_ctx(), mm, id, true)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_ACCOUNT_DISABLED, client).await;
+
+        Ok(user.into())
+    }
+
+    pub async fn enable_user(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        let user = UserRepository::set_disabled(Ctx::root_ctx(), mm, id, false)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_ACCOUNT_ENABLED, client).await;
+
+        Ok(user.into())
+    }
+
+    pub async fn suspend_user(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        req: &SuspendUserDTO,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        if id == ctx.user_id() as i64 {
+            return Err(ServiceError::BadRequest(String::from(
+                "you can't suspend your own account",
+            )));
+        }
+
+        let reason = req.reason.trim();
+        if reason.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "a reason is required",
+            )));
+        }
+
+        if req.until.is_some_and(|x| x <= OffsetDateTime::now_utc()) {
+            return Err(ServiceError::BadRequest(String::from(
+                "until must be in the future",
+            )));
+        }
+
+        let user = UserRepository::suspend(
+            Ctx::root_ctx(),
+            mm,
+            id,
+            reason,
+            req.until,
+            (!ctx.is_service_account()).then_some(ctx.user_id() as i64),
+        )
+        .await?
+        .ok_or_else(not_found)?;
+
+        AuditService::record(
+            mm,
+            id,
+            AUDIT_EVENT_ACCOUNT_SUSPENDED,
+            client,
+            json!({
+                "actor": AuditService::actor(ctx),
+                "reason": reason,
+                "until": user.suspended_until.map(|x| x.unix_timestamp()),
+            }),
+        )
+        .await;
+
+        Ok(user.into())
+    }
+
+    pub async fn unsuspend_user(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        let user = UserRepository::unsuspend(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_ACCOUNT_UNSUSPENDED, client).await;
+
+        Ok(user.into())
+    }
+
+    // the current password stops working and the user is signed out,
+    // a link to choose a new one is sent by email.
+    pub async fn force_password_reset(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user = Self::find_user(mm, id).await?;
+
+        if user.password.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "user doesn't login with a password",
+            )));
+        }
+
+        UserRepository::require_password_reset(Ctx::root_ctx(), mm, id).await?;
+        PasswordResetService::send_reset_link(mm, &user).await?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_PASSWORD_RESET_REQUIRED, client).await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("password reset link sent to the user"),
+        ))
+    }
+
+    // for users who lost their authenticator, they can enroll again after their next login.
+    pub async fn reset_mfa(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        Self::find_user(mm, id).await?;
+
+        let user = UserRepository::update(
+            Ctx::root_ctx(),
+            mm,
+            &id,
+            &UserPatch {
+                secret: Some(None),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        UserTokenRepository::revoke_for_user(Ctx::root_ctx(), mm, id, TOKEN_PURPOSE_MFA_CHALLENGE)
+            .await?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_MFA_RESET, client).await;
+
+        Ok(user.into())
+    }
+
+    pub async fn revoke_sessions(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        Self::find_user(mm, id).await?;
+
+        SessionService::revoke_all(mm, id).await?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_SESSIONS_REVOKED, client).await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("every session of the user has been revoked"),
+        ))
+    }
+
+    // only accounts still in their deletion grace period can be restored.
+    pub async fn restore_user(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        let user = UserRepository::find_deleted_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        let user = AccountDeletionService::restore_user(mm, &user).await?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_ACCOUNT_RESTORED, client).await;
+
+        Ok(user.into())
+    }
+
+    pub async fn update_app_metadata(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        patch: &serde_json::Value,
+        client: &ClientMeta,
+    ) -> Result<AdminUserDTO> {
+        Self::find_user(mm, id).await?;
+
+        UserService::update_app_metadata(mm, id, patch).await?;
+
+        Self::record(mm, ctx, id, AUDIT_EVENT_APP_METADATA_UPDATED, client).await;
+
+        Self::get_user(mm, id).await
+    }
+
+    // run by the maintenance job, `User::is_suspended` already ignores expired suspensions.
+    pub async fn lift_expired_suspensions(mm: &ModelManager) -> Result<usize> {
+        let ids = UserRepository::lift_expired_suspensions(Ctx::root_ctx(), mm).await?;
+
+        for id in &ids {
+            AuditService::record(
+                mm,
+                *id,
+                AUDIT_EVENT_ACCOUNT_UNSUSPENDED,
+                &ClientMeta::default(),
+                json!({ "expired": true }),
+            )
+            .await;
+        }
+
+        Ok(ids.len())
+    }
+
+    async fn find_user(mm: &ModelManager, id: i64) -> Result<User> {
+        UserRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)
+    }
+
+    async fn record(mm: &ModelManager, ctx: &Ctx, id: i64, event: &str, client: &ClientMeta) {
+        AuditService::record(
+            mm,
+            id,
+            event,
+            client,
+            json!({ "actor": AuditService::actor(ctx) }),
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{page_bounds, DEFAULT_PER_PAGE, MAX_PER_PAGE};
+
+    #[test]
+    fn page_bounds_defaults() {
+        assert_eq!(page_bounds(None, None), (1, DEFAULT_PER_PAGE));
+    }
+
+    #[test]
+    fn page_bounds_clamped() {
+        assert_eq!(page_bounds(Some(0), Some(0)), (1, 1));
+        assert_eq!(page_bounds(Some(-3), Some(1000)), (1, MAX_PER_PAGE));
+        assert_eq!(page_bounds(Some(4), Some(50)), (4, 50));
+    }
+}
@@ -0,0 +1,242 @@
+use serde_json::json;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{api_key::CreateApiKeyDTO, client::ClientMeta},
+        response::{
+            api_key::{ApiKeyDTO, CreatedApiKeyDTO},
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{user::CustomTokenClaims, ModelManager},
+    pkg::util::{digest::sha256_hex, rand::generate_random_string},
+    repository::{api_key::ApiKeyRepository, role::RoleRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        API_KEY_PREFIX, AUDIT_EVENT_API_KEY_CREATED, AUDIT_EVENT_API_KEY_DELETED, SCOPE_ACCOUNT,
+    },
+    error::Result,
+    ServiceError,
+};
+
+const KEY_LENGTH: usize = 40;
+// characters of the random part kept along with API_KEY_PREFIX to recognize a key.
+const VISIBLE_LENGTH: usize = 8;
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_KEYS_PER_USER: i64 = 25;
+
+// Sorted and deduplicated, each scope is `account` or one of the user's permissions.
+fn validate_scopes(scopes: &[String], permissions: &[String]) -> Result<Vec<String>> {
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    scopes.dedup();
+
+    if scopes.is_empty() {
+        return Err(ServiceError::BadRequest(String::from(
+            "at least one scope is required",
+        )));
+    }
+
+    if let Some(unknown) = scopes
+        .iter()
+        .find(|x| *x != SCOPE_ACCOUNT && !permissions.contains(x))
+    {
+        return Err(ServiceError::BadRequest(format!("unknown scope {unknown}")));
+    }
+
+    Ok(scopes)
+}
+
+// the token scopes and the permissions among the scopes of a key.
+fn split_scopes(scopes: Vec<String>) -> (Vec<String>, Vec<String>) {
+    scopes.into_iter().partition(|x| x == SCOPE_ACCOUNT)
+}
+
+// API keys let scripts act on behalf of a user without logging in. Their scopes are
+// token scopes or permissions the user holds, checked again on every request.
+#[derive(Debug, Clone)]
+pub struct ApiKeyService {}
+
+impl ApiKeyService {
+    // the key itself is only part of this response.
+    pub async fn create(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &CreateApiKeyDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<CreatedApiKeyDTO>> {
+        let user_id = ctx.user_id() as i64;
+
+        let name = req.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ServiceError::BadRequest(format!(
+                "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+            )));
+        }
+
+        let permissions = RoleRepository::permissions_of_user(Ctx::root_ctx(), mm, user_id).await?;
+        let scopes = validate_scopes(&req.scopes, &permissions)?;
+
+        if req
+            .expires_at
+            .is_some_and(|x| x <= OffsetDateTime::now_utc())
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "expires_at must be in the future",
+            )));
+        }
+
+        if ApiKeyRepository::count_by_user(Ctx::root_ctx(), mm, user_id).await? >= MAX_KEYS_PER_USER
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "no more than {MAX_KEYS_PER_USER} api keys per user"
+            )));
+        }
+
+        let key = format!("{}{}", API_KEY_PREFIX, generate_random_string(KEY_LENGTH));
+        let prefix = &key[..API_KEY_PREFIX.len() + VISIBLE_LENGTH];
+
+        let api_key = ApiKeyRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user_id,
+            prefix,
+            &sha256_hex(&key),
+            &CreateApiKeyDTO {
+                name: name.to_string(),
+                scopes,
+                expires_at: req.expires_at,
+            },
+        )
+        .await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_API_KEY_CREATED,
+            client,
+            json!({ "api_key_id": api_key.id, "name": api_key.name }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            201,
+            CreatedApiKeyDTO {
+                api_key: api_key.into(),
+                key,
+            },
+        ))
+    }
+
+    pub async fn list(mm: &ModelManager, ctx: &Ctx) -> Result<Vec<ApiKeyDTO>> {
+        let api_keys =
+            ApiKeyRepository::list_by_user(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        Ok(api_keys.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn delete(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user_id = ctx.user_id() as i64;
+
+        if !ApiKeyRepository::delete(Ctx::root_ctx(), mm, user_id, id).await? {
+            return Err(ServiceError::NotFound(String::from("api key not found")));
+        }
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_API_KEY_DELETED,
+            client,
+            json!({ "api_key_id": id }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("api key deleted"),
+        ))
+    }
+
+    // The claims an access token for the key would carry, `None` for unknown or expired keys.
+    // Records when and where the key was last used.
+    pub async fn authenticate(
+        mm: &ModelManager,
+        key: &str,
+        ip: Option<&str>,
+    ) -> Result<Option<CustomTokenClaims>> {
+        let Some(api_key) =
+            ApiKeyRepository::find_active(Ctx::root_ctx(), mm, &sha256_hex(key)).await?
+        else {
+            return Ok(None);
+        };
+
+        ApiKeyRepository::touch(Ctx::root_ctx(), mm, api_key.id, ip).await?;
+
+        let (scopes, permissions) = split_scopes(api_key.scopes);
+
+        Ok(Some(CustomTokenClaims {
+            sub: api_key.user_id as u64,
+            iat: api_key.created_at.unix_timestamp() as usize,
+            exp: api_key
+                .expires_at
+                .map(|x| x.unix_timestamp() as usize)
+                .unwrap_or_default(),
+            jti: None,
+            scope: scopes.join(" "),
+            user_metadata: None,
+            app_metadata: None,
+            permissions,
+            org: None,
+            principal: None,
+            client_id: None,
+            auth_time: None,
+            amr: Vec::new(),
+            aud: Vec::new(),
+            sid: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_scopes, validate_scopes};
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn validate_scopes_dedup() {
+        let scopes = validate_scopes(
+            &strings(&["users:read", "account", "users:read"]),
+            &strings(&["users:read"]),
+        )
+        .unwrap();
+
+        assert_eq!(scopes, strings(&["account", "users:read"]));
+    }
+
+    #[test]
+    fn validate_scopes_rejects_empty_or_unknown() {
+        assert!(validate_scopes(&[], &strings(&["users:read"])).is_err());
+        assert!(validate_scopes(&strings(&["users:write"]), &strings(&["users:read"])).is_err());
+    }
+
+    #[test]
+    fn split_scopes_from_permissions() {
+        let (scopes, permissions) = split_scopes(strings(&["account", "roles:read", "users:read"]));
+
+        assert_eq!(scopes, strings(&["account"]));
+        assert_eq!(permissions, strings(&["roles:read", "users:read"]));
+    }
+}
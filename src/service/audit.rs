@@ -0,0 +1,43 @@
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::{
+    ctx::Ctx, http::request::client::ClientMeta, model::ModelManager,
+    repository::audit_event::AuditEventRepository,
+};
+
+#[derive(Debug, Clone)]
+pub struct AuditService {}
+
+impl AuditService {
+    // who made a change on behalf of someone else, recorded in the event details.
+    pub fn actor(ctx: &Ctx) -> Value {
+        match ctx.service_account_id() {
+            Some(id) => json!({ "service_account": id }),
+            None => json!(ctx.user_id()),
+        }
+    }
+
+    // failures are only logged, a missing audit row shouldn't fail the action itself.
+    pub async fn record(
+        mm: &ModelManager,
+        user_id: i64,
+        event: &str,
+        client: &ClientMeta,
+        details: Value,
+    ) {
+        if let Err(err) = AuditEventRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user_id,
+            event,
+            client.ip.as_deref(),
+            client.user_agent.as_deref(),
+            details,
+        )
+        .await
+        {
+            error!("failed to record audit event {}: {:?}", event, err);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::env;
+
+use ::cookie::time::Duration;
+use axum::http::{HeaderMap, Method};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+
+use crate::{
+    http::{
+        request::client::ClientMeta,
+        response::user::{CookieSessionDTO, UserDTO},
+    },
+    model::ModelManager,
+    pkg::util::{digest::constant_time_eq, rand::generate_random_string},
+    service::{
+        self,
+        constant::{
+            COOKIE_ACCESS_TOKEN, COOKIE_CSRF_TOKEN, COOKIE_REFRESH_TOKEN, HEADER_CSRF_TOKEN,
+        },
+        oauth::OAuthService,
+        refresh_token::refresh_token_ttl_seconds,
+        token::access_token_ttl_seconds,
+        ServiceError,
+    },
+};
+
+const CSRF_TOKEN_LENGTH: usize = 32;
+// the refresh token cookie is only sent there.
+pub const REFRESH_PATH: &str = "/session/refresh";
+
+// AUTH_COOKIES=true keeps the tokens of the first-party web app in HttpOnly cookies instead
+// of handing them to its javascript. Bearer tokens keep working for everything else.
+pub fn enabled() -> bool {
+    env::var("AUTH_COOKIES").is_ok_and(|x| x == "true")
+}
+
+// AUTH_COOKIE_SAME_SITE=none when the app is served from another site than the API.
+fn same_site() -> SameSite {
+    match env::var("AUTH_COOKIE_SAME_SITE").as_deref() {
+        Ok("strict") => SameSite::Strict,
+        Ok("none") => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
+
+fn build_cookie(
+    name: &'static str,
+    value: String,
+    path: &'static str,
+    max_age: i64,
+    http_only: bool,
+) -> Cookie<'static> {
+    Cookie::build((name, value))
+        .http_only(http_only)
+        .secure(true)
+        .path(path)
+        .same_site(same_site())
+        .max_age(Duration::seconds(max_age))
+        .into()
+}
+
+// The tokens and the CSRF token, which the app reads from the response body: the cookie
+// isn't readable when the app is on another origin. A new one unless `csrf_token` is kept.
+fn add_tokens(
+    cookies: CookieJar,
+    access_token: String,
+    refresh_token: Option<String>,
+    csrf_token: Option<String>,
+) -> (CookieJar, String) {
+    let csrf_token = csrf_token.unwrap_or_else(|| generate_random_string(CSRF_TOKEN_LENGTH));
+
+    let mut cookies = cookies
+        .add(build_cookie(
+            COOKIE_ACCESS_TOKEN,
+            access_token,
+            "/",
+            access_token_ttl_seconds() as i64,
+            true,
+        ))
+        .add(build_cookie(
+            COOKIE_CSRF_TOKEN,
+            csrf_token.clone(),
+            "/",
+            refresh_token_ttl_seconds(),
+            false,
+        ));
+
+    if let Some(refresh_token) = refresh_token {
+        cookies = cookies.add(build_cookie(
+            COOKIE_REFRESH_TOKEN,
+            refresh_token,
+            REFRESH_PATH,
+            refresh_token_ttl_seconds(),
+            true,
+        ));
+    }
+
+    (cookies, csrf_token)
+}
+
+// Moves the tokens of a login response into cookies, the body only carries the CSRF token.
+// Left as is without AUTH_COOKIES, or when the login still needs the second factor.
+pub fn issue(cookies: CookieJar, mut user: UserDTO) -> (CookieJar, UserDTO) {
+    if !enabled() {
+        return (cookies, user);
+    }
+
+    let Some(access_token) = user.token.take() else {
+        return (cookies, user);
+    };
+
+    let (cookies, csrf_token) = add_tokens(cookies, access_token, user.refresh_token.take(), None);
+    user.csrf_token = Some(csrf_token);
+
+    (cookies, user)
+}
+
+// Exchanges the refresh token cookie, called by the app once the access token expired or
+// to get the CSRF token after a reload. It isn't CSRF protected: it only rotates the tokens,
+// the CSRF token is kept so a refresh forced by a cross-site page doesn't log the app out,
+// and that page can't read the response.
+pub async fn refresh(
+    mm: &ModelManager,
+    cookies: CookieJar,
+    client: &ClientMeta,
+) -> service::Result<(CookieJar, CookieSessionDTO)> {
+    if !enabled() {
+        return Err(ServiceError::NotFound(String::from(
+            "cookie sessions are disabled",
+        )));
+    }
+
+    let refresh_token = cookies
+        .get(COOKIE_REFRESH_TOKEN)
+        .map(|x| x.value().to_string())
+        .ok_or(ServiceError::Unauthorized)?;
+
+    let resp = OAuthService::refresh_login(mm, &refresh_token, client).await?;
+    let csrf_token = cookies
+        .get(COOKIE_CSRF_TOKEN)
+        .map(|x| x.value().to_string())
+        .filter(|x| !x.is_empty());
+    let (cookies, csrf_token) =
+        add_tokens(cookies, resp.access_token, resp.refresh_token, csrf_token);
+
+    Ok((
+        cookies,
+        CookieSessionDTO {
+            csrf_token,
+            expires_in: resp.expires_in,
+        },
+    ))
+}
+
+// on logout, the session itself is revoked by `SessionService`.
+pub fn clear(cookies: CookieJar) -> CookieJar {
+    if !enabled() {
+        return cookies;
+    }
+
+    // added rather than removed from the jar, the refresh token cookie isn't sent here.
+    [
+        (COOKIE_ACCESS_TOKEN, "/"),
+        (COOKIE_REFRESH_TOKEN, REFRESH_PATH),
+        (COOKIE_CSRF_TOKEN, "/"),
+    ]
+    .into_iter()
+    .fold(cookies, |cookies, (name, path)| {
+        let mut cookie = build_cookie(name, String::new(), path, 0, true);
+        cookie.make_removal();
+        cookies.add(cookie)
+    })
+}
+
+// the access token cookie, for the requests without an `Authorization` header.
+pub fn access_token(headers: &HeaderMap) -> Option<String> {
+    if !enabled() {
+        return None;
+    }
+
+    CookieJar::from_headers(headers)
+        .get(COOKIE_ACCESS_TOKEN)
+        .map(|x| x.value().to_string())
+}
+
+// Double submit: the unsafe requests authenticated by the access token cookie have to
+// repeat the CSRF cookie in the `X-CSRF-Token` header, which a cross-site page can't do.
+pub fn verify_csrf(method: &Method, headers: &HeaderMap) -> bool {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return true;
+    }
+
+    let header = headers.get(HEADER_CSRF_TOKEN).and_then(|x| x.to_str().ok());
+
+    match (
+        CookieJar::from_headers(headers).get(COOKIE_CSRF_TOKEN),
+        header,
+    ) {
+        (Some(cookie), Some(header)) => constant_time_eq(cookie.value(), header),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::{header, HeaderMap, HeaderValue, Method};
+    use axum_extra::extract::cookie::CookieJar;
+
+    use super::{add_tokens, verify_csrf};
+    use crate::service::constant::{COOKIE_CSRF_TOKEN, HEADER_CSRF_TOKEN};
+
+    fn headers(cookie: Option<&str>, header: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(cookie) = cookie {
+            let value = format!("{COOKIE_CSRF_TOKEN}={cookie}");
+            headers.insert(header::COOKIE, HeaderValue::from_str(&value).unwrap());
+        }
+        if let Some(header) = header {
+            headers.insert(HEADER_CSRF_TOKEN, HeaderValue::from_str(header).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn verify_csrf_skips_safe_methods() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            assert!(verify_csrf(&method, &headers(None, None)));
+        }
+    }
+
+    #[test]
+    fn verify_csrf_requires_cookie_and_header() {
+        assert!(!verify_csrf(&Method::POST, &headers(None, None)));
+        assert!(!verify_csrf(&Method::POST, &headers(Some("abc"), None)));
+        assert!(!verify_csrf(&Method::DELETE, &headers(None, Some("abc"))));
+    }
+
+    #[test]
+    fn verify_csrf_rejects_mismatch() {
+        assert!(!verify_csrf(
+            &Method::POST,
+            &headers(Some("abc"), Some("abd"))
+        ));
+        assert!(!verify_csrf(
+            &Method::PUT,
+            &headers(Some("abc"), Some("ab"))
+        ));
+    }
+
+    #[test]
+    fn verify_csrf_accepts_match() {
+        assert!(verify_csrf(
+            &Method::POST,
+            &headers(Some("abc"), Some("abc"))
+        ));
+        assert!(verify_csrf(
+            &Method::PATCH,
+            &headers(Some("abc"), Some("abc"))
+        ));
+    }
+
+    #[test]
+    fn add_tokens_keeps_csrf_token() {
+        let (cookies, csrf_token) = add_tokens(
+            CookieJar::new(),
+            String::from("access"),
+            None,
+            Some(String::from("kept")),
+        );
+
+        assert_eq!(csrf_token, "kept");
+        assert_eq!(cookies.get(COOKIE_CSRF_TOKEN).unwrap().value(), "kept");
+
+        let (_, csrf_token) = add_tokens(CookieJar::new(), String::from("access"), None, None);
+        assert_ne!(csrf_token, "kept");
+        assert!(!csrf_token.is_empty());
+    }
+}
@@ -11,12 +11,19 @@ use tracing::info;
 
 use crate::{
     ctx::Ctx,
-    http::request::{google::AuthRequest, user::CreateUserDTO},
-    model::ModelManager,
-    repository::user::UserRepository,
+    http::request::{client::ClientMeta, google::AuthRequest, user::CreateUserDTO},
+    model::{user::UserPatch, ModelManager},
+    repository::{organization::OrganizationRepository, user::UserRepository},
     service::{
         self,
-        constant::{COOKIE_AUTH_CODE_VERIFIER, COOKIE_AUTH_CSRF_STATE, GOOGLE_OAUTH_PROVIDER},
+        auth::cookie_session,
+        constant::{
+            COOKIE_AUTH_CODE_VERIFIER, COOKIE_AUTH_CSRF_STATE, COOKIE_ORG_INVITATION,
+            COOKIE_ORG_TENANT, GOOGLE_OAUTH_PROVIDER, ORG_ROLE_MEMBER,
+        },
+        invitation::InvitationService,
+        organization::OrganizationService,
+        user::UserService,
     },
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
@@ -26,7 +33,7 @@ use oauth2::{
     TokenResponse, TokenUrl,
 };
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use sqlx::types::time::OffsetDateTime;
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct GoogleUser {
@@ -56,7 +63,12 @@ fn get_oauth_client() -> anyhow::Result<BasicClient> {
     Ok(client)
 }
 
-pub async fn login() -> service::Result<impl IntoResponse> {
+// `invitation` and `organization` are kept in cookies until the callback, where the
+// account is looked up in the organization and the invitation accepted for it.
+pub async fn login(
+    invitation: Option<String>,
+    organization: Option<String>,
+) -> service::Result<impl IntoResponse> {
     let client = get_oauth_client()?;
 
     let (pkce_code_challenge, pkce_code_verifier) = PkceCodeChallenge::new_random_sha256();
@@ -89,7 +101,27 @@ pub async fn login() -> service::Result<impl IntoResponse> {
     .max_age(Duration::minutes(5))
     .into();
 
-    let cookies = CookieJar::new().add(csrf_cookie).add(code_veifier);
+    let mut cookies = CookieJar::new().add(csrf_cookie).add(code_veifier);
+
+    if let Some(invitation) = invitation {
+        cookies = cookies.add(
+            Cookie::build((COOKIE_ORG_INVITATION, invitation))
+                .http_only(true)
+                .path("/")
+                .same_site(SameSite::Lax)
+                .max_age(Duration::minutes(5)),
+        );
+    }
+
+    if let Some(organization) = organization {
+        cookies = cookies.add(
+            Cookie::build((COOKIE_ORG_TENANT, organization))
+                .http_only(true)
+                .path("/")
+                .same_site(SameSite::Lax)
+                .max_age(Duration::minutes(5)),
+        );
+    }
 
     let response = (cookies, Redirect::to(authorize_url.as_str()));
 
@@ -99,6 +131,7 @@ pub async fn login() -> service::Result<impl IntoResponse> {
 pub async fn callback(
     mm: ModelManager,
     cookies: CookieJar,
+    client: ClientMeta,
     req: AuthRequest,
 ) -> service::Result<impl IntoResponse> {
     let stored_state = cookies.get(COOKIE_AUTH_CSRF_STATE);
@@ -119,13 +152,13 @@ pub async fn callback(
     }
 
     // this code below were logic for "token" use after user authenticate it.
-    let client = get_oauth_client()?;
+    let oauth_client = get_oauth_client()?;
     let code = AuthorizationCode::new(req.code);
     let pkce_code_verifier = PkceCodeVerifier::new(code_verifier.value().to_string());
 
     // this code below should implement the "code" exchange
     // reference: https://developers.google.com/identity/protocols/oauth2/web-server#httprest_5
-    let token_response = client
+    let token_response = oauth_client
         .exchange_code(code)
         .set_pkce_verifier(pkce_code_verifier)
         .request_async(async_http_client)
@@ -149,16 +182,33 @@ pub async fn callback(
         return Err(service::ServiceError::InternalServerError);
     };
 
-    let existing_user = UserRepository::get_by_email(Ctx::root_ctx(), &mm, &email).await?;
+    let organization = cookies.get(COOKIE_ORG_TENANT).map(|x| x.value());
+    let tenant = OrganizationService::tenant_ctx(&mm, organization).await?;
+    let existing_user = UserRepository::get_by_email(tenant.clone(), &mm, &email).await?;
+    let email_verified = user_info.email_verified.unwrap_or(false);
 
-    let user = match existing_user {
-        Some(mut x) => {
+    let mut user = match existing_user {
+        // anyone can put an address on a google account, only a verified one
+        // leads to the account registered with it.
+        Some(_) if !email_verified => {
+            return Err(service::ServiceError::ForbiddenWithMessage(String::from(
+                "google hasn't verified the email address",
+            )));
+        }
+        Some(x) => {
             // mean user is already sign-in but not authenticated.
             if x.auth_provider.is_none() {
-                x.auth_provider = Some(GOOGLE_OAUTH_PROVIDER.to_string());
-                x.auth_provider_user_id = Some(user_info.sub);
-                UserRepository::update(Ctx::root_ctx(), &mm, &x.id, &x).await?;
-                x
+                UserRepository::update(
+                    Ctx::root_ctx(),
+                    &mm,
+                    &x.id,
+                    &UserPatch {
+                        auth_provider: Some(Some(GOOGLE_OAUTH_PROVIDER.to_string())),
+                        auth_provider_user_id: Some(Some(user_info.sub)),
+                        ..Default::default()
+                    },
+                )
+                .await?
             } else {
                 x
             }
@@ -166,7 +216,7 @@ pub async fn callback(
         None => {
             // TODO: make sure in login api, the oauth user were redirected into google account.
             let user = UserRepository::create(
-                Ctx::root_ctx(),
+                tenant.clone(),
                 &mm,
                 CreateUserDTO {
                     name: user_info.name,
@@ -175,20 +225,45 @@ pub async fn callback(
                     auth_provider: Some(GOOGLE_OAUTH_PROVIDER.to_string()),
                     auth_provider_user_id: Some(user_info.sub),
                     secret: None,
+                    organization: None,
+                    invitation: None,
                 },
             )
             .await?;
 
+            if tenant.organization_id().is_some() {
+                OrganizationRepository::upsert_member(tenant, &mm, user.id, ORG_ROLE_MEMBER)
+                    .await?;
+            }
+
             user
         }
     };
 
-    // enough for authentication proccess here's the authorization process
-    let auth_code = Uuid::new_v4();
+    // google already checked the address, no need to send our own verification.
+    if email_verified && user.email_verified_at.is_none() {
+        UserRepository::set_email_verified(Ctx::root_ctx(), &mm, user.id).await?;
+        user.email_verified_at = Some(OffsetDateTime::now_utc());
+    }
+
+    UserService::ensure_can_login(&user)?;
+
+    // only for addresses google vouches for, the invitation is bound to one.
+    if let Some(invitation) = cookies.get(COOKIE_ORG_INVITATION) {
+        if !email_verified
+            || InvitationService::redeem(&mm, &user, invitation.value(), &client)
+                .await?
+                .is_none()
+        {
+            return Err(service::ServiceError::BadRequest(String::from(
+                "invitation is invalid, has expired or was sent to another address",
+            )));
+        }
+    }
+
+    // the users with MFA enabled get a challenge instead of the tokens.
+    let resp = UserService::complete_login(&mm, user, GOOGLE_OAUTH_PROVIDER, &client).await?;
+    let (cookies, resp) = cookie_session::issue(cookies, resp);
 
-    Ok(Json(user.into_dto(
-        Some(auth_code.to_string()),
-        Some("supposethisisrefreshtoken".to_string()),
-        None,
-    )))
+    Ok((cookies, Json(resp)))
 }
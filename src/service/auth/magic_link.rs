@@ -0,0 +1,207 @@
+use std::env;
+
+use ::cookie::time::Duration;
+use anyhow::Context;
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use serde_json::json;
+use tracing::error;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{client::ClientMeta, user::MagicLinkQuery},
+        response::{BaseResponse, MessageResponse},
+    },
+    model::ModelManager,
+    pkg::{
+        mail::{self, Mail},
+        util::{
+            digest::{constant_time_eq, hmac_sha256_hex, sha256_hex},
+            rand::generate_random_string,
+        },
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+    service::{
+        self,
+        auth::cookie_session,
+        constant::{COOKIE_MAGIC_LINK_BINDING, LOGIN_METHOD_MAGIC_LINK, TOKEN_PURPOSE_MAGIC_LINK},
+        organization::OrganizationService,
+        user::UserService,
+        ServiceError,
+    },
+};
+
+const TOKEN_LENGTH: usize = 32;
+const BINDING_LENGTH: usize = 32;
+const DEFAULT_TTL_MINUTES: i64 = 15;
+const REQUEST_MESSAGE: &str = "if the address belongs to an account, a login link has been sent";
+
+fn ttl_seconds() -> i64 {
+    env::var("MAGIC_LINK_TTL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_MINUTES)
+        * 60
+}
+
+// with MAGIC_LINK_BIND_BROWSER=true the link only works in the browser that asked for it.
+fn bind_browser() -> bool {
+    env::var("MAGIC_LINK_BIND_BROWSER").is_ok_and(|x| x == "true")
+}
+
+fn signing_secret() -> anyhow::Result<String> {
+    env::var("LINK_SIGNING_SECRET").context("Missing LINK_SIGNING_SECRET env var")
+}
+
+// the link carries `<token>.<signature>`, forged links are rejected before touching the database.
+fn sign(token: &str) -> anyhow::Result<String> {
+    let signature = hmac_sha256_hex(signing_secret()?.as_bytes(), token);
+
+    Ok(format!("{token}.{signature}"))
+}
+
+fn verify_signature(signed: &str) -> anyhow::Result<Option<String>> {
+    let Some((token, signature)) = signed.split_once('.') else {
+        return Ok(None);
+    };
+
+    let expected = hmac_sha256_hex(signing_secret()?.as_bytes(), token);
+
+    Ok(constant_time_eq(&expected, signature).then(|| token.to_string()))
+}
+
+pub async fn request(
+    mm: ModelManager,
+    email: String,
+    organization: Option<String>,
+) -> service::Result<impl IntoResponse> {
+    let binding = bind_browser().then(|| generate_random_string(BINDING_LENGTH));
+    let binding_hash = binding.as_deref().map(sha256_hex);
+
+    // the lookup runs in the background so the response doesn't tell whether the account exists.
+    tokio::spawn(async move {
+        if let Err(err) = send_link(&mm, &email, organization.as_deref(), binding_hash).await {
+            error!("failed to send magic link: {:?}", err);
+        }
+    });
+
+    let mut cookies = CookieJar::new();
+
+    if let Some(binding) = binding {
+        let binding_cookie: Cookie = Cookie::build((COOKIE_MAGIC_LINK_BINDING, binding))
+            .http_only(true)
+            .path("/")
+            .same_site(SameSite::Lax)
+            .max_age(Duration::seconds(ttl_seconds()))
+            .into();
+
+        cookies = cookies.add(binding_cookie);
+    }
+
+    let response = (
+        StatusCode::ACCEPTED,
+        cookies,
+        Json(BaseResponse::new(
+            202,
+            MessageResponse::new(REQUEST_MESSAGE),
+        )),
+    );
+
+    Ok(response)
+}
+
+async fn send_link(
+    mm: &ModelManager,
+    email: &str,
+    organization: Option<&str>,
+    binding_hash: Option<String>,
+) -> service::Result<()> {
+    let tenant = OrganizationService::tenant_ctx(mm, organization).await?;
+    let Some(user) = UserRepository::get_by_email(tenant, mm, email).await? else {
+        return Ok(());
+    };
+
+    let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+    let token = generate_random_string(TOKEN_LENGTH);
+
+    UserTokenRepository::create(
+        Ctx::root_ctx(),
+        mm,
+        user.id,
+        TOKEN_PURPOSE_MAGIC_LINK,
+        &sha256_hex(&token),
+        ttl_seconds(),
+        binding_hash.map(|x| json!({ "binding": x })),
+    )
+    .await?;
+
+    mail::send_in_background(Mail::new(
+        &user.email,
+        "Your login link",
+        format!(
+            "Hi {},\n\nuse the link below to login, it can only be used once:\n\n{}/login/magic-link/verify?token={}\n\nThe link expires in {} minutes. If you didn't ask for it, you can ignore this email.",
+            user.name,
+            base_url,
+            sign(&token)?,
+            ttl_seconds() / 60
+        ),
+    ));
+
+    Ok(())
+}
+
+pub async fn verify(
+    mm: ModelManager,
+    cookies: CookieJar,
+    client: ClientMeta,
+    req: MagicLinkQuery,
+) -> service::Result<impl IntoResponse> {
+    let invalid = || ServiceError::BadRequest(String::from("login link is invalid or has expired"));
+
+    let token = verify_signature(&req.token)?.ok_or_else(invalid)?;
+    let token_hash = sha256_hex(&token);
+
+    let record = UserTokenRepository::get_active(
+        Ctx::root_ctx(),
+        &mm,
+        TOKEN_PURPOSE_MAGIC_LINK,
+        &token_hash,
+    )
+    .await?
+    .ok_or_else(invalid)?;
+
+    let expected_binding = record
+        .payload
+        .as_ref()
+        .and_then(|x| x.get("binding"))
+        .and_then(|x| x.as_str());
+
+    // checked before consuming, opening the link in another browser doesn't burn it.
+    if let Some(expected) = expected_binding {
+        let binding = cookies
+            .get(COOKIE_MAGIC_LINK_BINDING)
+            .map(|x| sha256_hex(x.value()));
+
+        if !binding.is_some_and(|x| constant_time_eq(&x, expected)) {
+            return Err(ServiceError::ForbiddenWithMessage(String::from(
+                "open the link in the browser the login was requested from",
+            )));
+        }
+    }
+
+    let record =
+        UserTokenRepository::consume(Ctx::root_ctx(), &mm, TOKEN_PURPOSE_MAGIC_LINK, &token_hash)
+            .await?
+            .ok_or_else(invalid)?;
+
+    // following the link proves the user owns the address.
+    UserRepository::set_email_verified(Ctx::root_ctx(), &mm, record.user_id).await?;
+    let user = UserRepository::get_by_id(Ctx::root_ctx(), &mm, record.user_id).await?;
+
+    let resp = UserService::complete_login(&mm, user, LOGIN_METHOD_MAGIC_LINK, &client).await?;
+    let cookies = cookies.remove(Cookie::build(COOKIE_MAGIC_LINK_BINDING).path("/"));
+    let (cookies, resp) = cookie_session::issue(cookies, resp);
+
+    Ok((cookies, Json(resp)))
+}
@@ -1 +1,3 @@
+pub mod cookie_session;
 pub mod google;
+pub mod magic_link;
@@ -1,6 +1,17 @@
 // Cookie session
 pub const COOKIE_AUTH_CSRF_STATE: &str = "auth-csrf-state";
 pub const COOKIE_AUTH_CODE_VERIFIER: &str = "auth-code-verifier";
+pub const COOKIE_MAGIC_LINK_BINDING: &str = "magic-link-binding";
+pub const COOKIE_ORG_INVITATION: &str = "org-invitation";
+pub const COOKIE_ORG_TENANT: &str = "org-tenant";
+// AUTH_COOKIES=true, the `__Host-` ones can't be set by a sibling domain or over http.
+pub const COOKIE_ACCESS_TOKEN: &str = "__Host-access-token";
+pub const COOKIE_REFRESH_TOKEN: &str = "__Secure-refresh-token";
+pub const COOKIE_CSRF_TOKEN: &str = "__Host-csrf-token";
+pub const HEADER_CSRF_TOKEN: &str = "x-csrf-token";
+
+// Auth Provider
+pub const PASSWORD_AUTH_PROVIDER: &str = "password";
 
 // OAUTH Provider
 pub const GITHUB_OAUTH_PROVIDER: &str = "github";
@@ -9,3 +20,143 @@ pub const FACEBOOK_OAUTH_PROVIDER: &str = "facebook";
 pub const TWITTER_OAUTH_PROVIDER: &str = "twitter";
 pub const LINKEDIN_OAUTH_PROVIDER: &str = "linkedin";
 pub const APPLE_OAUTH_PROVIDER: &str = "apple";
+
+// Access token scopes
+// full access to the account.
+pub const SCOPE_ACCOUNT: &str = "account";
+// given instead of `account` while the email address is waiting for verification.
+pub const SCOPE_UNVERIFIED: &str = "unverified";
+// OpenID Connect, `openid` asks for an ID token, the others for the claims of the user.
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPE_EMAIL: &str = "email";
+
+// API keys start with it, to tell them apart from access tokens.
+pub const API_KEY_PREFIX: &str = "ak_";
+
+// Roles, the admin role is created by the migrations with every permission.
+pub const ROLE_ADMIN: &str = "admin";
+
+// Permissions, granted through roles and carried by the access token.
+pub const PERMISSION_USERS_READ: &str = "users:read";
+pub const PERMISSION_USERS_WRITE: &str = "users:write";
+pub const PERMISSION_ROLES_READ: &str = "roles:read";
+pub const PERMISSION_ROLES_WRITE: &str = "roles:write";
+pub const PERMISSION_SERVICE_ACCOUNTS_READ: &str = "service_accounts:read";
+pub const PERMISSION_SERVICE_ACCOUNTS_WRITE: &str = "service_accounts:write";
+pub const PERMISSION_CLIENTS_READ: &str = "clients:read";
+pub const PERMISSION_CLIENTS_WRITE: &str = "clients:write";
+pub const PERMISSION_SIGNING_KEYS_READ: &str = "signing_keys:read";
+pub const PERMISSION_SIGNING_KEYS_WRITE: &str = "signing_keys:write";
+
+// Service accounts
+// `principal` claim of the access tokens issued to service accounts.
+pub const PRINCIPAL_SERVICE_ACCOUNT: &str = "service_account";
+// `principal` claim of the access tokens of the client_credentials grant of the OAuth clients.
+pub const PRINCIPAL_CLIENT: &str = "client";
+pub const SERVICE_ACCOUNT_CLIENT_ID_PREFIX: &str = "sa_";
+pub const SERVICE_ACCOUNT_SECRET_PREFIX: &str = "sas_";
+pub const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+// OAuth 2.0 authorization server
+pub const CLIENT_TYPE_CONFIDENTIAL: &str = "confidential";
+pub const CLIENT_TYPE_PUBLIC: &str = "public";
+pub const CLIENT_SECRET_PREFIX: &str = "cs_";
+pub const RESPONSE_TYPE_CODE: &str = "code";
+pub const CODE_CHALLENGE_METHOD_S256: &str = "S256";
+pub const GRANT_TYPE_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_TYPE_REFRESH_TOKEN: &str = "refresh_token";
+pub const GRANT_TYPE_CLIENT_CREDENTIALS: &str = "client_credentials";
+pub const GRANT_TYPE_DEVICE_CODE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+// Device authorization requests, see RFC 8628.
+pub const DEVICE_CODE_STATUS_PENDING: &str = "pending";
+pub const DEVICE_CODE_STATUS_APPROVED: &str = "approved";
+pub const DEVICE_CODE_STATUS_DENIED: &str = "denied";
+
+// Authentication method references of the `amr` claim, see RFC 8176.
+pub const AMR_PASSWORD: &str = "pwd";
+pub const AMR_OTP: &str = "otp";
+pub const AMR_MFA: &str = "mfa";
+// not registered by RFC 8176: a link sent by email, a login through another identity provider.
+pub const AMR_EMAIL: &str = "email";
+pub const AMR_FEDERATED: &str = "fed";
+
+// Token signing keys, see `service::signing_key`.
+pub const SIGNING_KEY_STATUS_PENDING: &str = "pending";
+pub const SIGNING_KEY_STATUS_ACTIVE: &str = "active";
+// superseded, still verifying the tokens it signed.
+pub const SIGNING_KEY_STATUS_PREVIOUS: &str = "previous";
+pub const SIGNING_KEY_STATUS_RETIRED: &str = "retired";
+
+// OAuth 2.0 error codes, see RFC 6749 section 4.1.2.1 and 5.2.
+pub const OAUTH_ERROR_INVALID_REQUEST: &str = "invalid_request";
+pub const OAUTH_ERROR_INVALID_CLIENT: &str = "invalid_client";
+pub const OAUTH_ERROR_INVALID_GRANT: &str = "invalid_grant";
+pub const OAUTH_ERROR_INVALID_SCOPE: &str = "invalid_scope";
+pub const OAUTH_ERROR_UNAUTHORIZED_CLIENT: &str = "unauthorized_client";
+pub const OAUTH_ERROR_UNSUPPORTED_GRANT_TYPE: &str = "unsupported_grant_type";
+pub const OAUTH_ERROR_UNSUPPORTED_RESPONSE_TYPE: &str = "unsupported_response_type";
+pub const OAUTH_ERROR_ACCESS_DENIED: &str = "access_denied";
+// RFC 8628 section 3.5, while the device polls the token endpoint.
+pub const OAUTH_ERROR_AUTHORIZATION_PENDING: &str = "authorization_pending";
+pub const OAUTH_ERROR_SLOW_DOWN: &str = "slow_down";
+pub const OAUTH_ERROR_EXPIRED_TOKEN: &str = "expired_token";
+// RFC 8707 section 2, an audience the client can't get tokens for.
+pub const OAUTH_ERROR_INVALID_TARGET: &str = "invalid_target";
+
+// Organization roles, held through the memberships.
+pub const ORG_ROLE_OWNER: &str = "owner";
+pub const ORG_ROLE_ADMIN: &str = "admin";
+pub const ORG_ROLE_MEMBER: &str = "member";
+
+// User token purposes
+pub const TOKEN_PURPOSE_EMAIL_VERIFICATION: &str = "email_verification";
+pub const TOKEN_PURPOSE_MAGIC_LINK: &str = "magic_link";
+pub const TOKEN_PURPOSE_MFA_CHALLENGE: &str = "mfa_challenge";
+pub const TOKEN_PURPOSE_EMAIL_CHANGE: &str = "email_change";
+pub const TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL: &str = "email_change_cancel";
+pub const TOKEN_PURPOSE_ACCOUNT_RESTORE: &str = "account_restore";
+pub const TOKEN_PURPOSE_PASSWORD_RESET: &str = "password_reset";
+
+// Audit events
+pub const AUDIT_EVENT_LOGIN: &str = "login";
+pub const AUDIT_EVENT_LOGIN_FAILED: &str = "login_failed";
+pub const AUDIT_EVENT_LOGOUT: &str = "logout";
+pub const AUDIT_EVENT_MFA_ENABLED: &str = "mfa_enabled";
+pub const AUDIT_EVENT_EMAIL_CHANGED: &str = "email_changed";
+pub const AUDIT_EVENT_ACCOUNT_DELETED: &str = "account_deleted";
+pub const AUDIT_EVENT_ACCOUNT_RESTORED: &str = "account_restored";
+pub const AUDIT_EVENT_DATA_EXPORT_REQUESTED: &str = "data_export_requested";
+pub const AUDIT_EVENT_ACCOUNT_DISABLED: &str = "account_disabled";
+pub const AUDIT_EVENT_ACCOUNT_ENABLED: &str = "account_enabled";
+pub const AUDIT_EVENT_PASSWORD_RESET_REQUIRED: &str = "password_reset_required";
+pub const AUDIT_EVENT_PASSWORD_RESET: &str = "password_reset";
+pub const AUDIT_EVENT_MFA_RESET: &str = "mfa_reset";
+pub const AUDIT_EVENT_SESSIONS_REVOKED: &str = "sessions_revoked";
+pub const AUDIT_EVENT_SESSION_REVOKED: &str = "session_revoked";
+pub const AUDIT_EVENT_APP_METADATA_UPDATED: &str = "app_metadata_updated";
+pub const AUDIT_EVENT_ACCOUNT_SUSPENDED: &str = "account_suspended";
+pub const AUDIT_EVENT_ACCOUNT_UNSUSPENDED: &str = "account_unsuspended";
+pub const AUDIT_EVENT_ROLE_ASSIGNED: &str = "role_assigned";
+pub const AUDIT_EVENT_ROLE_UNASSIGNED: &str = "role_unassigned";
+pub const AUDIT_EVENT_MEMBERSHIP_UPDATED: &str = "membership_updated";
+pub const AUDIT_EVENT_MEMBERSHIP_REMOVED: &str = "membership_removed";
+pub const AUDIT_EVENT_API_KEY_CREATED: &str = "api_key_created";
+pub const AUDIT_EVENT_API_KEY_DELETED: &str = "api_key_deleted";
+pub const AUDIT_EVENT_CONSENT_GRANTED: &str = "consent_granted";
+pub const AUDIT_EVENT_CONSENT_REVOKED: &str = "consent_revoked";
+pub const AUDIT_EVENT_REFRESH_TOKEN_REUSED: &str = "refresh_token_reused";
+
+// Login methods, recorded with the login events.
+pub const LOGIN_METHOD_PASSWORD: &str = "password";
+pub const LOGIN_METHOD_MAGIC_LINK: &str = "magic_link";
+
+// Data export status
+pub const DATA_EXPORT_STATUS_PENDING: &str = "pending";
+pub const DATA_EXPORT_STATUS_READY: &str = "ready";
+pub const DATA_EXPORT_STATUS_FAILED: &str = "failed";
+
+// Multi factor authentication
+pub const MFA_TYPE_TOTP: &str = "TOTP";
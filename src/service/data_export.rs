@@ -0,0 +1,295 @@
+use std::env;
+
+use anyhow::Context;
+use serde_json::json;
+use sqlx::types::time::OffsetDateTime;
+use tracing::error;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::client::ClientMeta,
+        response::{
+            data_export::{DataExportArchive, DataExportDTO},
+            BaseResponse,
+        },
+    },
+    model::{data_export::DataExport, ModelManager},
+    pkg::mail::{self, Mail},
+    repository::{
+        api_key::ApiKeyRepository, audit_event::AuditEventRepository,
+        data_export::DataExportRepository, oauth_client::OAuthClientRepository,
+        organization::OrganizationRepository, refresh_token::RefreshTokenRepository,
+        session::SessionRepository, user::UserRepository,
+    },
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_DATA_EXPORT_REQUESTED, AUDIT_EVENT_LOGIN, AUDIT_EVENT_LOGIN_FAILED,
+        DATA_EXPORT_STATUS_READY,
+    },
+    error::Result,
+    ServiceError,
+};
+
+const DEFAULT_TTL_HOURS: i64 = 7 * 24;
+
+fn ttl_seconds() -> i64 {
+    env::var("DATA_EXPORT_TTL_HOURS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_HOURS)
+        * 60
+        * 60
+}
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("export not found"))
+}
+
+#[derive(Debug, Clone)]
+pub struct DataExportService {}
+
+impl DataExportService {
+    // The archive is generated in the background, the user gets an email once it can
+    // be downloaded. A pending export is returned instead of starting another one.
+    pub async fn request(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<DataExportDTO>> {
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+        let user_id = ctx.user_id() as i64;
+
+        if let Some(export) =
+            DataExportRepository::find_pending(Ctx::root_ctx(), mm, user_id).await?
+        {
+            return Ok(BaseResponse::new(202, export.into_dto(&base_url)));
+        }
+
+        let export = DataExportRepository::create(Ctx::root_ctx(), mm, user_id).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_DATA_EXPORT_REQUESTED,
+            client,
+            json!({ "export_id": export.id }),
+        )
+        .await;
+
+        let mm = mm.clone();
+        let export_id = export.id;
+        let download_url = format!("{}/me/export/{}/download", base_url, export_id);
+
+        tokio::spawn(async move {
+            if let Err(err) = Self::generate(&mm, export_id, user_id, &download_url).await {
+                error!("failed to generate data export {}: {:?}", export_id, err);
+
+                if let Err(err) = DataExportRepository::fail(Ctx::root_ctx(), &mm, export_id).await
+                {
+                    error!(
+                        "failed to mark data export {} as failed: {:?}",
+                        export_id, err
+                    );
+                }
+            }
+        });
+
+        Ok(BaseResponse::new(202, export.into_dto(&base_url)))
+    }
+
+    pub async fn get(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<DataExportDTO> {
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+
+        let export = DataExportRepository::get(Ctx::root_ctx(), mm, ctx.user_id() as i64, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(export.into_dto(&base_url))
+    }
+
+    pub async fn download(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<serde_json::Value> {
+        let export = DataExportRepository::get(Ctx::root_ctx(), mm, ctx.user_id() as i64, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        match export {
+            DataExport {
+                archive: Some(archive),
+                status,
+                ..
+            } if status == DATA_EXPORT_STATUS_READY => Ok(archive),
+            _ => Err(ServiceError::ObjectConflict(String::from(
+                "export is not ready yet",
+            ))),
+        }
+    }
+
+    // also used as is by the `export-user` admin command.
+    pub async fn build_archive(mm: &ModelManager, user_id: i64) -> Result<DataExportArchive> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, user_id).await?;
+        let events = AuditEventRepository::list_by_user(Ctx::root_ctx(), mm, user_id).await?;
+
+        let login_history = events
+            .iter()
+            .filter(|x| x.event == AUDIT_EVENT_LOGIN || x.event == AUDIT_EVENT_LOGIN_FAILED)
+            .map(|x| x.clone().into())
+            .collect();
+
+        let sessions = SessionRepository::list_active_by_user(Ctx::root_ctx(), mm, user_id).await?;
+        let api_keys = ApiKeyRepository::list_by_user(Ctx::root_ctx(), mm, user_id).await?;
+        let memberships =
+            OrganizationRepository::list_for_user(Ctx::root_ctx(), mm, user_id).await?;
+        let consents = OAuthClientRepository::list_consents(Ctx::root_ctx(), mm, user_id).await?;
+        let refresh_tokens =
+            RefreshTokenRepository::list_active_by_user(Ctx::root_ctx(), mm, user_id).await?;
+
+        Ok(DataExportArchive {
+            generated_at: OffsetDateTime::now_utc(),
+            profile: user.into(),
+            login_history,
+            audit_events: events.into_iter().map(Into::into).collect(),
+            sessions: sessions.into_iter().map(|x| x.into_dto(false)).collect(),
+            api_keys: api_keys.into_iter().map(Into::into).collect(),
+            organizations: memberships
+                .into_iter()
+                .map(|x| x.organization.into_dto(Some(x.role)))
+                .collect(),
+            oauth_consents: consents.into_iter().map(Into::into).collect(),
+            refresh_tokens: refresh_tokens.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    async fn generate(
+        mm: &ModelManager,
+        export_id: i64,
+        user_id: i64,
+        download_url: &str,
+    ) -> Result<()> {
+        let archive = Self::build_archive(mm, user_id).await?;
+        let archive = serde_json::to_value(&archive).context("failed to serialize archive")?;
+
+        DataExportRepository::complete(Ctx::root_ctx(), mm, export_id, &archive, ttl_seconds())
+            .await?;
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, user_id).await?;
+
+        mail::send_in_background(Mail::new(
+            &user.email,
+            "Your data export is ready",
+            format!(
+                "Hi {},\n\nthe export of your account data is ready, download it while logged in from:\n\n{}\n\nIt will be deleted in {} hours.",
+                user.name,
+                download_url,
+                ttl_seconds() / (60 * 60)
+            ),
+        ));
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(mm: &ModelManager) -> Result<u64> {
+        let deleted = DataExportRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::DataExportService;
+    use crate::{
+        ctx::Ctx,
+        http::request::{api_key::CreateApiKeyDTO, client::ClientMeta},
+        model::ModelManager,
+        repository::{api_key::ApiKeyRepository, data_export::DataExportRepository},
+        service::{
+            audit::AuditService,
+            constant::{AUDIT_EVENT_LOGIN, AUDIT_EVENT_LOGIN_FAILED, AUDIT_EVENT_MFA_ENABLED},
+            ServiceError,
+        },
+    };
+
+    // `users.id` has no default.
+    async fn insert_user(mm: &ModelManager, id: i64) {
+        sqlx::query("INSERT INTO users (id,name,email,password,secret,created_at) VALUES ($1, 'user', $2, 'password-hash', 'mfa-secret', current_timestamp)")
+            .bind(id)
+            .bind(format!("user{}@example.com", id))
+            .execute(&mm.db)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn archive_has_the_history_without_secrets(db: PgPool) {
+        let mm = ModelManager { db };
+        insert_user(&mm, 1).await;
+
+        for event in [
+            AUDIT_EVENT_LOGIN,
+            AUDIT_EVENT_LOGIN_FAILED,
+            AUDIT_EVENT_MFA_ENABLED,
+        ] {
+            AuditService::record(&mm, 1, event, &ClientMeta::default(), json!({})).await;
+        }
+
+        ApiKeyRepository::create(
+            Ctx::root_ctx(),
+            &mm,
+            1,
+            "ak_test",
+            "api-key-hash",
+            &CreateApiKeyDTO {
+                name: String::from("script"),
+                scopes: Vec::new(),
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let archive = DataExportService::build_archive(&mm, 1).await.unwrap();
+        assert_eq!(archive.login_history.len(), 2);
+        assert_eq!(archive.audit_events.len(), 3);
+        assert_eq!(archive.api_keys.len(), 1);
+
+        let archive = serde_json::to_string(&archive).unwrap();
+        for secret in ["password-hash", "mfa-secret", "api-key-hash"] {
+            assert!(!archive.contains(secret), "{secret} is exported");
+        }
+    }
+
+    #[sqlx::test]
+    async fn download_once_ready_and_only_by_the_user(db: PgPool) {
+        let mm = ModelManager { db };
+        insert_user(&mm, 1).await;
+        insert_user(&mm, 2).await;
+        let owner = Ctx::new(1).unwrap();
+
+        let export = DataExportRepository::create(Ctx::root_ctx(), &mm, 1)
+            .await
+            .unwrap();
+        let result = DataExportService::download(&mm, &owner, export.id).await;
+        assert!(matches!(result, Err(ServiceError::ObjectConflict(_))));
+
+        let archive = json!({ "profile": {} });
+        DataExportRepository::complete(Ctx::root_ctx(), &mm, export.id, &archive, 60)
+            .await
+            .unwrap();
+        assert_eq!(
+            DataExportService::download(&mm, &owner, export.id)
+                .await
+                .unwrap(),
+            archive
+        );
+
+        let result = DataExportService::download(&mm, &Ctx::new(2).unwrap(), export.id).await;
+        assert!(matches!(result, Err(ServiceError::NotFound(_))));
+    }
+}
@@ -0,0 +1,396 @@
+use std::env;
+
+use anyhow::Context;
+use bcrypt::verify;
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{client::ClientMeta, user::ChangeEmailDTO},
+        response::{BaseResponse, MessageResponse},
+    },
+    model::ModelManager,
+    pkg::{
+        mail::{self, Mail},
+        util::{digest::sha256_hex, rand::generate_random_string},
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_EMAIL_CHANGED, TOKEN_PURPOSE_EMAIL_CHANGE, TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL,
+    },
+    error::Result,
+    session::SessionService,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 32;
+const DEFAULT_TTL_MINUTES: i64 = 24 * 60;
+const REQUEST_MESSAGE: &str = "a confirmation link has been sent to the new address";
+
+fn ttl_seconds() -> i64 {
+    env::var("EMAIL_CHANGE_TTL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_MINUTES)
+        * 60
+}
+
+// with EMAIL_CHANGE_REVOKE_SESSIONS=true the user is signed out everywhere once the change applies.
+fn revoke_sessions() -> bool {
+    env::var("EMAIL_CHANGE_REVOKE_SESSIONS").is_ok_and(|x| x == "true")
+}
+
+fn invalid_link() -> ServiceError {
+    ServiceError::BadRequest(String::from("link is invalid or has expired"))
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailChangeService {}
+
+impl EmailChangeService {
+    // The change only applies once the link sent to the new address is followed,
+    // the current address gets a notice with a link to cancel it.
+    pub async fn request(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &ChangeEmailDTO,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        // a stolen access token alone shouldn't be enough to take the account over.
+        if !user.password.is_empty() {
+            let password = req.password.as_deref().unwrap_or_default();
+
+            if !verify(password.as_bytes(), &user.password)? {
+                return Err(ServiceError::InvalidLoginAttmpt);
+            }
+        }
+
+        let new_email = req.new_email.trim().to_string();
+
+        if !new_email.contains('@') {
+            return Err(ServiceError::BadRequest(String::from(
+                "invalid email address",
+            )));
+        }
+
+        if new_email == user.email {
+            return Err(ServiceError::BadRequest(String::from(
+                "new email address is the same as the current one",
+            )));
+        }
+
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+
+        for purpose in [
+            TOKEN_PURPOSE_EMAIL_CHANGE,
+            TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL,
+        ] {
+            UserTokenRepository::revoke_for_user(Ctx::root_ctx(), mm, user.id, purpose).await?;
+        }
+
+        // a taken address gets a notice instead of the link, and the response stays the same.
+        if UserRepository::get_by_email(
+            Ctx::root_ctx().with_organization(user.organization_id),
+            mm,
+            &new_email,
+        )
+        .await?
+        .is_some()
+        {
+            mail::send_in_background(Mail::new(
+                &new_email,
+                "Email change attempt",
+                String::from(
+                    "Hi,\n\nsomeone tried to move another account to this email address, but it is already registered.\nNo change was made, you can ignore this email.",
+                ),
+            ));
+
+            return Ok(BaseResponse::new(
+                202,
+                MessageResponse::new(REQUEST_MESSAGE),
+            ));
+        }
+
+        let confirm_token = generate_random_string(TOKEN_LENGTH);
+        let cancel_token = generate_random_string(TOKEN_LENGTH);
+
+        UserTokenRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_EMAIL_CHANGE,
+            &sha256_hex(&confirm_token),
+            ttl_seconds(),
+            Some(json!({ "new_email": new_email })),
+        )
+        .await?;
+
+        UserTokenRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL,
+            &sha256_hex(&cancel_token),
+            ttl_seconds(),
+            None,
+        )
+        .await?;
+
+        mail::send_in_background(Mail::new(
+            &new_email,
+            "Confirm your new email address",
+            format!(
+                "Hi {},\n\nopen the link below to use this address for your account:\n\n{}/me/email/confirm?token={}\n\nThe link expires in {} minutes.",
+                user.name,
+                base_url,
+                confirm_token,
+                ttl_seconds() / 60
+            ),
+        ));
+
+        mail::send_in_background(Mail::new(
+            &user.email,
+            "Email change requested",
+            format!(
+                "Hi {},\n\na change of your account email address to {} was requested.\nIf it wasn't you, cancel it with the link below and change your password:\n\n{}/me/email/cancel?token={}",
+                user.name, new_email, base_url, cancel_token
+            ),
+        ));
+
+        Ok(BaseResponse::new(
+            202,
+            MessageResponse::new(REQUEST_MESSAGE),
+        ))
+    }
+
+    pub async fn confirm(
+        mm: &ModelManager,
+        token: &str,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let record = UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_EMAIL_CHANGE,
+            &sha256_hex(token),
+        )
+        .await?
+        .ok_or_else(invalid_link)?;
+
+        let new_email = record
+            .payload
+            .as_ref()
+            .and_then(|x| x.get("new_email"))
+            .and_then(|x| x.as_str())
+            .ok_or_else(invalid_link)?;
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, record.user_id).await?;
+
+        // the address could have been registered since the change was requested.
+        if UserRepository::get_by_email(
+            Ctx::root_ctx().with_organization(user.organization_id),
+            mm,
+            new_email,
+        )
+        .await?
+        .is_some()
+        {
+            return Err(ServiceError::ObjectConflict(String::from(
+                "email address is already in use",
+            )));
+        }
+
+        UserRepository::update_email(Ctx::root_ctx(), mm, user.id, new_email).await?;
+
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_EMAIL_CHANGED,
+            client,
+            json!({ "from": user.email, "to": new_email }),
+        )
+        .await;
+
+        UserTokenRepository::revoke_for_user(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL,
+        )
+        .await?;
+
+        if revoke_sessions() {
+            SessionService::revoke_all(mm, user.id).await?;
+        }
+
+        mail::send_in_background(Mail::new(
+            &user.email,
+            "Your email address was changed",
+            format!(
+                "Hi {},\n\nyour account email address is now {}.\nIf it wasn't you, contact support right away.",
+                user.name, new_email
+            ),
+        ));
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("email address updated"),
+        ))
+    }
+
+    pub async fn cancel(mm: &ModelManager, token: &str) -> Result<BaseResponse<MessageResponse>> {
+        let record = UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL,
+            &sha256_hex(token),
+        )
+        .await?
+        .ok_or_else(invalid_link)?;
+
+        UserTokenRepository::revoke_for_user(
+            Ctx::root_ctx(),
+            mm,
+            record.user_id,
+            TOKEN_PURPOSE_EMAIL_CHANGE,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("email change cancelled"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bcrypt::hash;
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use super::EmailChangeService;
+    use crate::{
+        ctx::Ctx,
+        http::request::{client::ClientMeta, user::ChangeEmailDTO},
+        model::ModelManager,
+        pkg::util::digest::sha256_hex,
+        repository::{user::UserRepository, user_token::UserTokenRepository},
+        service::{
+            constant::{TOKEN_PURPOSE_EMAIL_CHANGE, TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL},
+            ServiceError,
+        },
+    };
+
+    const CONFIRM_TOKEN: &str = "confirm-token";
+    const CANCEL_TOKEN: &str = "cancel-token";
+
+    // `users.id` has no default.
+    async fn insert_user(mm: &ModelManager, id: i64, email: &str, password: &str) {
+        sqlx::query("INSERT INTO users (id,name,email,password,created_at) VALUES ($1, 'user', $2, $3, current_timestamp)")
+            .bind(id)
+            .bind(email)
+            .bind(password)
+            .execute(&mm.db)
+            .await
+            .unwrap();
+    }
+
+    // user 1 asked to move from old@ to new@, with the links of both addresses.
+    async fn pending_change(mm: &ModelManager) {
+        insert_user(mm, 1, "old@example.com", "").await;
+
+        for (purpose, token, payload) in [
+            (
+                TOKEN_PURPOSE_EMAIL_CHANGE,
+                CONFIRM_TOKEN,
+                Some(json!({ "new_email": "new@example.com" })),
+            ),
+            (TOKEN_PURPOSE_EMAIL_CHANGE_CANCEL, CANCEL_TOKEN, None),
+        ] {
+            UserTokenRepository::create(
+                Ctx::root_ctx(),
+                mm,
+                1,
+                purpose,
+                &sha256_hex(token),
+                60,
+                payload,
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    async fn email(mm: &ModelManager) -> String {
+        UserRepository::get_by_id(Ctx::root_ctx(), mm, 1)
+            .await
+            .unwrap()
+            .email
+    }
+
+    #[sqlx::test]
+    async fn request_needs_the_password(db: PgPool) {
+        let mm = ModelManager { db };
+        insert_user(&mm, 1, "old@example.com", &hash("password", 4).unwrap()).await;
+
+        let result = EmailChangeService::request(
+            &mm,
+            &Ctx::new(1).unwrap(),
+            &ChangeEmailDTO {
+                new_email: String::from("new@example.com"),
+                password: Some(String::from("wrong")),
+            },
+        )
+        .await;
+
+        assert!(matches!(result, Err(ServiceError::InvalidLoginAttmpt)));
+    }
+
+    #[sqlx::test]
+    async fn confirm_applies_the_change_once(db: PgPool) {
+        let mm = ModelManager { db };
+        pending_change(&mm).await;
+
+        EmailChangeService::confirm(&mm, CONFIRM_TOKEN, &ClientMeta::default())
+            .await
+            .unwrap();
+        assert_eq!(email(&mm).await, "new@example.com");
+
+        // it's too late to cancel, and the link is spent.
+        assert!(EmailChangeService::cancel(&mm, CANCEL_TOKEN).await.is_err());
+        let result = EmailChangeService::confirm(&mm, CONFIRM_TOKEN, &ClientMeta::default()).await;
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+    }
+
+    #[sqlx::test]
+    async fn confirm_checks_the_address_again(db: PgPool) {
+        let mm = ModelManager { db };
+        pending_change(&mm).await;
+        // registered since the change was requested.
+        insert_user(&mm, 2, "new@example.com", "").await;
+
+        let result = EmailChangeService::confirm(&mm, CONFIRM_TOKEN, &ClientMeta::default()).await;
+
+        assert!(matches!(result, Err(ServiceError::ObjectConflict(_))));
+        assert_eq!(email(&mm).await, "old@example.com");
+    }
+
+    #[sqlx::test]
+    async fn cancel_drops_the_change(db: PgPool) {
+        let mm = ModelManager { db };
+        pending_change(&mm).await;
+
+        EmailChangeService::cancel(&mm, CANCEL_TOKEN).await.unwrap();
+
+        let result = EmailChangeService::confirm(&mm, CONFIRM_TOKEN, &ClientMeta::default()).await;
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+        assert_eq!(email(&mm).await, "old@example.com");
+    }
+}
@@ -0,0 +1,177 @@
+use std::env;
+
+use anyhow::Context;
+use tracing::error;
+
+use crate::{
+    ctx::Ctx,
+    http::response::{BaseResponse, MessageResponse},
+    model::{user::User, ModelManager},
+    pkg::{
+        mail::{self, Mail},
+        util::{digest::sha256_hex, rand::generate_random_string},
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+};
+
+use super::{
+    constant::{SCOPE_ACCOUNT, SCOPE_UNVERIFIED, TOKEN_PURPOSE_EMAIL_VERIFICATION},
+    error::Result,
+    organization::OrganizationService,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 32;
+const DEFAULT_TTL_MINUTES: i64 = 24 * 60;
+const RESEND_MESSAGE: &str =
+    "if the address belongs to an unverified account, a new verification link has been sent";
+
+// What happens to users whose email isn't verified yet, set with EMAIL_VERIFICATION_POLICY.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerificationPolicy {
+    // unverified users get the same tokens as everyone else.
+    Off,
+    // login is refused until the email is verified.
+    BlockLogin,
+    // login works but the token only carries the `unverified` scope.
+    RestrictScopes,
+}
+
+impl VerificationPolicy {
+    pub fn from_env() -> Self {
+        match env::var("EMAIL_VERIFICATION_POLICY").as_deref() {
+            Ok("block_login") => Self::BlockLogin,
+            Ok("restrict_scopes") => Self::RestrictScopes,
+            _ => Self::Off,
+        }
+    }
+}
+
+fn ttl_seconds() -> i64 {
+    env::var("EMAIL_VERIFICATION_TTL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_MINUTES)
+        * 60
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailVerificationService {}
+
+impl EmailVerificationService {
+    // scope of the tokens handed out at login, fails when the policy refuses the login.
+    pub fn login_scope(user: &User) -> Result<&'static str> {
+        if user.email_verified_at.is_some() {
+            return Ok(SCOPE_ACCOUNT);
+        }
+
+        match VerificationPolicy::from_env() {
+            VerificationPolicy::Off => Ok(SCOPE_ACCOUNT),
+            VerificationPolicy::RestrictScopes => Ok(SCOPE_UNVERIFIED),
+            VerificationPolicy::BlockLogin => Err(ServiceError::ForbiddenWithMessage(
+                String::from("email address is not verified"),
+            )),
+        }
+    }
+
+    // previous links stop working once a new one is sent.
+    pub async fn send_verification(mm: &ModelManager, user: &User) -> Result<()> {
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+        let token = generate_random_string(TOKEN_LENGTH);
+
+        UserTokenRepository::revoke_for_user(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_EMAIL_VERIFICATION,
+        )
+        .await?;
+
+        UserTokenRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_EMAIL_VERIFICATION,
+            &sha256_hex(&token),
+            ttl_seconds(),
+            None,
+        )
+        .await?;
+
+        mail::send_in_background(Mail::new(
+            &user.email,
+            "Verify your email address",
+            format!(
+                "Hi {},\n\nplease confirm your email address by opening the link below:\n\n{}/verify-email?token={}\n\nThe link expires in {} minutes.",
+                user.name,
+                base_url,
+                token,
+                ttl_seconds() / 60
+            ),
+        ));
+
+        Ok(())
+    }
+
+    // sent when someone signs up with an address that already has an account,
+    // the owner learns about it without the caller learning anything.
+    pub fn send_already_registered(email: &str) {
+        mail::send_in_background(Mail::new(
+            email,
+            "Signup attempt on your account",
+            String::from(
+                "Hi,\n\nsomeone tried to create an account with this email address, but it is already registered.\nIf it was you, just login with your existing credentials. Otherwise you can ignore this email.",
+            ),
+        ));
+    }
+
+    // the lookup runs in the background so the response time doesn't depend on the account existing.
+    pub fn resend(
+        mm: &ModelManager,
+        email: String,
+        organization: Option<String>,
+    ) -> BaseResponse<MessageResponse> {
+        let mm = mm.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = Self::resend_to(&mm, &email, organization.as_deref()).await {
+                error!("failed to resend verification: {:?}", err);
+            }
+        });
+
+        BaseResponse::new(202, MessageResponse::new(RESEND_MESSAGE))
+    }
+
+    async fn resend_to(mm: &ModelManager, email: &str, organization: Option<&str>) -> Result<()> {
+        let tenant = OrganizationService::tenant_ctx(mm, organization).await?;
+        let user = UserRepository::get_by_email(tenant, mm, email).await?;
+
+        let Some(user) = user.filter(|x| x.email_verified_at.is_none()) else {
+            return Ok(());
+        };
+
+        Self::send_verification(mm, &user).await
+    }
+
+    pub async fn verify(mm: &ModelManager, token: &str) -> Result<BaseResponse<MessageResponse>> {
+        let Some(token) = UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_EMAIL_VERIFICATION,
+            &sha256_hex(token),
+        )
+        .await?
+        else {
+            return Err(ServiceError::BadRequest(String::from(
+                "verification link is invalid or has expired",
+            )));
+        };
+
+        UserRepository::set_email_verified(Ctx::root_ctx(), mm, token.user_id).await?;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("email address verified"),
+        ))
+    }
+}
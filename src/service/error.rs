@@ -1,9 +1,20 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
 
 use thiserror::Error;
+use tracing::error;
+
+use sqlx::types::time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 
 use crate::http::ApiError;
 
+use super::constant::OAUTH_ERROR_INVALID_CLIENT;
+
 pub type Result<T> = core::result::Result<T, ServiceError>;
 
 #[derive(Error, Debug)]
@@ -18,6 +29,11 @@ pub enum ServiceError {
     ForbiddenWithMessage(String),
     #[error("{0}")]
     NotFound(String),
+    #[error("account is suspended")]
+    AccountSuspended {
+        reason: Option<String>,
+        until: Option<OffsetDateTime>,
+    },
     #[error("{0}")]
     ApplicationStartup(String),
     #[error("{0}")]
@@ -28,6 +44,12 @@ pub enum ServiceError {
     InternalServerErrorWithContext(String),
     #[error("{0}")]
     ObjectConflict(String),
+    // error responses of the OAuth 2.0 endpoints, shaped as RFC 6749 asks.
+    #[error("{error}: {description}")]
+    OAuth {
+        error: &'static str,
+        description: String,
+    },
     #[error("unprocessable request has occurred")]
     UnprocessableEntity { errors: String },
     #[error(transparent)]
@@ -38,26 +60,70 @@ pub enum ServiceError {
     BcryptError(#[from] bcrypt::BcryptError),
 }
 
+impl ServiceError {
+    pub fn oauth(error: &'static str, description: &str) -> Self {
+        Self::OAuth {
+            error,
+            description: description.to_string(),
+        }
+    }
+}
+
 impl IntoResponse for ServiceError {
     fn into_response(self) -> axum::response::Response {
+        if let Self::OAuth { error, description } = self {
+            let status = match error {
+                OAUTH_ERROR_INVALID_CLIENT => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::BAD_REQUEST,
+            };
+
+            return (
+                status,
+                [(header::CACHE_CONTROL, "no-store")],
+                Json(json!({ "error": error, "error_description": description })),
+            )
+                .into_response();
+        }
+
         let (status, error_message) = match self {
             Self::InternalServerErrorWithContext(err) => (StatusCode::INTERNAL_SERVER_ERROR, err),
             Self::NotFound(err) => (StatusCode::NOT_FOUND, err),
             Self::ObjectConflict(err) => (StatusCode::CONFLICT, err),
+            Self::BadRequest(err) => (StatusCode::BAD_REQUEST, err),
             Self::InvalidLoginAttmpt => (
                 StatusCode::BAD_REQUEST,
                 Self::InvalidLoginAttmpt.to_string(),
             ),
             Self::Unauthorized => (StatusCode::UNAUTHORIZED, Self::Unauthorized.to_string()),
-            Self::AnyhowError(err) => match err.to_string().contains("unique constraint") {
-                true => (StatusCode::BAD_REQUEST, err.to_string()),
-                false => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
-            },
+            Self::Forbidden => (StatusCode::FORBIDDEN, Self::Forbidden.to_string()),
             Self::ForbiddenWithMessage(err) => (StatusCode::FORBIDDEN, err),
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("unexpected error {:?}", self),
-            ),
+            Self::AccountSuspended { reason, until } => {
+                (StatusCode::FORBIDDEN, suspension_message(reason, until))
+            }
+            // the raw database error is never sent back, it would tell
+            // the caller which row already exists.
+            Self::AnyhowError(err) => {
+                error!("unexpected error: {:?}", err);
+
+                match err.to_string().contains("unique constraint") {
+                    true => (
+                        StatusCode::CONFLICT,
+                        String::from("request conflicts with an existing resource"),
+                    ),
+                    false => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Self::InternalServerError.to_string(),
+                    ),
+                }
+            }
+            _ => {
+                error!("unexpected error: {:?}", self);
+
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Self::InternalServerError.to_string(),
+                )
+            }
         };
 
         // I'm not a fan of the error specification, so for the sake of consistency,
@@ -67,3 +133,35 @@ impl IntoResponse for ServiceError {
         (status, body).into_response()
     }
 }
+
+// shared with `jwt_auth`, ie. "account is suspended until 2024-01-31T00:00:00Z: spam".
+pub fn suspension_message(reason: Option<String>, until: Option<OffsetDateTime>) -> String {
+    let mut message = String::from("account is suspended");
+
+    if let Some(until) = until.and_then(|x| x.format(&Rfc3339).ok()) {
+        message.push_str(&format!(" until {until}"));
+    }
+    if let Some(reason) = reason {
+        message.push_str(&format!(": {reason}"));
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+
+    use super::suspension_message;
+
+    #[test]
+    fn suspension_message_with_details() {
+        let until = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+
+        assert_eq!(suspension_message(None, None), "account is suspended");
+        assert_eq!(
+            suspension_message(Some(String::from("spam")), Some(until)),
+            "account is suspended until 2023-11-14T22:13:20Z: spam"
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::oauth::{BasicCredentials, IntrospectionRequest},
+        response::oauth::IntrospectionDTO,
+    },
+    model::{
+        oauth::OAuthClient,
+        refresh_token::RefreshToken,
+        user::{CustomTokenClaims, User},
+        ModelManager,
+    },
+    pkg::util::digest::sha256_hex,
+    repository::{
+        oauth_client::OAuthClientRepository, organization::OrganizationRepository,
+        refresh_token::RefreshTokenRepository, service_account::ServiceAccountRepository,
+        session::SessionRepository, user::UserRepository,
+    },
+};
+
+use super::{
+    constant::{
+        GRANT_TYPE_CLIENT_CREDENTIALS, OAUTH_ERROR_INVALID_CLIENT, PRINCIPAL_CLIENT,
+        PRINCIPAL_SERVICE_ACCOUNT,
+    },
+    error::Result,
+    oauth::OAuthService,
+    oidc::issuer,
+    revocation::RevocationService,
+    signing_key::SigningKeyService,
+    ServiceError,
+};
+
+fn inactive() -> IntrospectionDTO {
+    IntrospectionDTO::default()
+}
+
+// Deleted users aren't found at all, disabled and suspended ones lose their tokens and so
+// do the ones issued before the user signed out everywhere.
+fn user_usable(user: &User, issued_at: i64) -> bool {
+    user.disabled_at.is_none()
+        && !user.is_suspended()
+        && user
+            .tokens_valid_after
+            .is_none_or(|x| issued_at >= x.unix_timestamp())
+}
+
+// neither rotated, revoked nor expired.
+fn refresh_token_usable(refresh_token: &RefreshToken, now: OffsetDateTime) -> bool {
+    refresh_token.used_at.is_none()
+        && refresh_token.revoked_at.is_none()
+        && refresh_token.expires_at > now
+}
+
+// a client_credentials token of a client still allowed the grant, issued to that client.
+fn client_token_usable(oauth_client: &OAuthClient, client_id: Option<&str>) -> bool {
+    oauth_client.disabled_at.is_none()
+        && oauth_client.allows_grant(GRANT_TYPE_CLIENT_CREDENTIALS)
+        && client_id == Some(oauth_client.client_id.as_str())
+}
+
+// RFC 7662 token introspection, for the resource servers that would rather not validate
+// the tokens themselves. A token is active when `jwt_auth` or the token endpoint would
+// still accept it, the callers aren't told why one isn't.
+#[derive(Debug, Clone)]
+pub struct IntrospectionService {}
+
+impl IntrospectionService {
+    // callers authenticate as confidential clients, like at the token endpoint.
+    pub async fn introspect(
+        mm: &ModelManager,
+        req: &IntrospectionRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<IntrospectionDTO> {
+        let caller = OAuthService::authenticate_client(mm, &req.client, credentials).await?;
+        if caller.as_ref().is_none_or(|x| x.is_public()) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_INVALID_CLIENT,
+                "a confidential client has to authenticate",
+            ));
+        }
+
+        match req.token.contains('.') {
+            true => Self::access_token(mm, &req.token).await,
+            false => Self::refresh_token(mm, &req.token).await,
+        }
+    }
+
+    async fn access_token(mm: &ModelManager, token: &str) -> Result<IntrospectionDTO> {
+        let Some(claims) = SigningKeyService::verify::<CustomTokenClaims>(mm, token, None).await?
+        else {
+            return Ok(inactive());
+        };
+
+        if let Some(jti) = &claims.jti {
+            if RevocationService::is_revoked(mm, jti).await? {
+                return Ok(inactive());
+            }
+        }
+
+        let active = match claims.principal.as_deref() {
+            Some(PRINCIPAL_SERVICE_ACCOUNT) => {
+                ServiceAccountRepository::find_by_id(Ctx::root_ctx(), mm, claims.sub as i64)
+                    .await?
+                    .is_some_and(|x| x.disabled_at.is_none())
+            }
+            Some(PRINCIPAL_CLIENT) => {
+                OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, claims.sub as i64)
+                    .await?
+                    .is_some_and(|x| client_token_usable(&x, claims.client_id.as_deref()))
+            }
+            _ => {
+                // the tokens of a login also end with its session.
+                let session_active = match claims.sid {
+                    Some(session_id) => {
+                        SessionRepository::find_active(Ctx::root_ctx(), mm, session_id)
+                            .await?
+                            .is_some_and(|x| x.user_id == claims.sub as i64)
+                    }
+                    None => true,
+                };
+
+                session_active
+                    && Self::user_active(mm, claims.sub as i64, claims.org, claims.iat as i64)
+                        .await?
+            }
+        };
+
+        if !active {
+            return Ok(inactive());
+        }
+
+        Ok(IntrospectionDTO {
+            active: true,
+            scope: Some(claims.scope),
+            client_id: claims.client_id,
+            token_type: Some(String::from("Bearer")),
+            exp: Some(claims.exp as i64),
+            iat: Some(claims.iat as i64),
+            sub: Some(claims.sub.to_string()),
+            aud: claims.aud,
+            iss: Some(issuer()?),
+            principal: claims.principal,
+            org: claims.org,
+        })
+    }
+
+    async fn refresh_token(mm: &ModelManager, token: &str) -> Result<IntrospectionDTO> {
+        let Some(refresh_token) =
+            RefreshTokenRepository::find(Ctx::root_ctx(), mm, &sha256_hex(token)).await?
+        else {
+            return Ok(inactive());
+        };
+
+        if !refresh_token_usable(&refresh_token, OffsetDateTime::now_utc()) {
+            return Ok(inactive());
+        }
+
+        let client_id = match refresh_token.client_id {
+            Some(id) => {
+                match OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, id)
+                    .await?
+                    .filter(|x| x.disabled_at.is_none())
+                {
+                    Some(oauth_client) => Some(oauth_client.client_id),
+                    None => return Ok(inactive()),
+                }
+            }
+            None => None,
+        };
+
+        if !Self::user_active(
+            mm,
+            refresh_token.user_id,
+            refresh_token.organization_id,
+            refresh_token.created_at.unix_timestamp(),
+        )
+        .await?
+        {
+            return Ok(inactive());
+        }
+
+        Ok(IntrospectionDTO {
+            active: true,
+            scope: Some(refresh_token.scope),
+            client_id,
+            token_type: Some(String::from("refresh_token")),
+            exp: Some(refresh_token.expires_at.unix_timestamp()),
+            iat: Some(refresh_token.created_at.unix_timestamp()),
+            sub: Some(refresh_token.user_id.to_string()),
+            aud: Vec::new(),
+            iss: Some(issuer()?),
+            principal: None,
+            org: refresh_token.organization_id,
+        })
+    }
+
+    // The user also has to stay a member of the organization the token acts in.
+    async fn user_active(
+        mm: &ModelManager,
+        user_id: i64,
+        organization_id: Option<i64>,
+        issued_at: i64,
+    ) -> Result<bool> {
+        // soft-deleted users aren't found.
+        let Some(user) = UserRepository::find_by_id(Ctx::root_ctx(), mm, user_id).await? else {
+            return Ok(false);
+        };
+
+        if !user_usable(&user, issued_at) {
+            return Ok(false);
+        }
+
+        if let Some(organization_id) = organization_id {
+            let member = OrganizationRepository::find_member(
+                Ctx::root_ctx().with_organization(Some(organization_id)),
+                mm,
+                user.id,
+            )
+            .await?;
+
+            return Ok(member.is_some());
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+    use time::Duration;
+
+    use super::{client_token_usable, refresh_token_usable, user_usable};
+    use crate::{
+        model::{oauth::OAuthClient, refresh_token::RefreshToken, user::User},
+        service::constant::{
+            CLIENT_TYPE_CONFIDENTIAL, GRANT_TYPE_CLIENT_CREDENTIALS, GRANT_TYPE_REFRESH_TOKEN,
+        },
+    };
+
+    fn user() -> User {
+        User {
+            id: 1,
+            created_at: OffsetDateTime::now_utc(),
+            modified_at: None,
+            deleted_at: None,
+            purge_after: None,
+            name: String::from("user"),
+            email: String::from("user@example.com"),
+            auth_provider: None,
+            auth_provider_user_id: None,
+            secret: None,
+            password: String::new(),
+            email_verified_at: None,
+            tokens_valid_after: None,
+            user_metadata: serde_json::Value::Null,
+            app_metadata: serde_json::Value::Null,
+            disabled_at: None,
+            password_reset_required: false,
+            suspended_at: None,
+            suspended_until: None,
+            suspension_reason: None,
+            suspended_by: None,
+            organization_id: None,
+        }
+    }
+
+    fn refresh_token(expires_at: OffsetDateTime) -> RefreshToken {
+        RefreshToken {
+            id: 1,
+            token_hash: String::new(),
+            family_id: String::new(),
+            user_id: 1,
+            client_id: None,
+            scope: String::from("openid"),
+            organization_id: None,
+            auth_time: None,
+            amr: Vec::new(),
+            session_id: None,
+            created_at: OffsetDateTime::now_utc(),
+            expires_at,
+            used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn oauth_client(grant_types: &[&str]) -> OAuthClient {
+        OAuthClient {
+            id: 1,
+            client_id: String::from("client"),
+            client_secret_hash: None,
+            client_type: CLIENT_TYPE_CONFIDENTIAL.to_string(),
+            name: String::from("client"),
+            redirect_uris: Vec::new(),
+            allowed_scopes: Vec::new(),
+            grant_types: grant_types.iter().map(|x| x.to_string()).collect(),
+            first_party: false,
+            created_at: OffsetDateTime::now_utc(),
+            modified_at: None,
+            disabled_at: None,
+            client_scopes: Vec::new(),
+            audiences: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn user_tokens() {
+        let now = OffsetDateTime::now_utc();
+        let issued_at = now.unix_timestamp();
+        assert!(user_usable(&user(), issued_at));
+
+        let disabled = User {
+            disabled_at: Some(now),
+            ..user()
+        };
+        assert!(!user_usable(&disabled, issued_at));
+
+        let suspended = User {
+            suspended_at: Some(now),
+            ..user()
+        };
+        assert!(!user_usable(&suspended, issued_at));
+
+        // signed out everywhere after the token was issued, not before.
+        let signed_out = User {
+            tokens_valid_after: Some(now),
+            ..user()
+        };
+        assert!(!user_usable(&signed_out, issued_at - 60));
+        assert!(user_usable(&signed_out, issued_at));
+    }
+
+    #[test]
+    fn refresh_tokens() {
+        let now = OffsetDateTime::now_utc();
+        let expires_at = now + Duration::days(1);
+        assert!(refresh_token_usable(&refresh_token(expires_at), now));
+        assert!(!refresh_token_usable(&refresh_token(now), now));
+
+        let used = RefreshToken {
+            used_at: Some(now),
+            ..refresh_token(expires_at)
+        };
+        assert!(!refresh_token_usable(&used, now));
+
+        let revoked = RefreshToken {
+            revoked_at: Some(now),
+            ..refresh_token(expires_at)
+        };
+        assert!(!refresh_token_usable(&revoked, now));
+    }
+
+    #[test]
+    fn client_credentials_tokens() {
+        let client = oauth_client(&[GRANT_TYPE_CLIENT_CREDENTIALS]);
+        assert!(client_token_usable(&client, Some("client")));
+        assert!(!client_token_usable(&client, Some("other")));
+        assert!(!client_token_usable(&client, None));
+
+        // the grant was taken away from the client since.
+        let client = oauth_client(&[GRANT_TYPE_REFRESH_TOKEN]);
+        assert!(!client_token_usable(&client, Some("client")));
+
+        let disabled = OAuthClient {
+            disabled_at: Some(OffsetDateTime::now_utc()),
+            ..oauth_client(&[GRANT_TYPE_CLIENT_CREDENTIALS])
+        };
+        assert!(!client_token_usable(&disabled, Some("client")));
+    }
+}
@@ -0,0 +1,278 @@
+use std::env;
+
+use anyhow::Context;
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{
+            client::ClientMeta,
+            organization::{AcceptInvitationDTO, InviteMemberDTO},
+        },
+        response::{
+            organization::{InvitationDTO, OrganizationDTO},
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{organization::Organization, user::User, ModelManager},
+    pkg::{
+        mail::{self, Mail},
+        util::{digest::sha256_hex, rand::generate_random_string},
+    },
+    repository::{
+        invitation::InvitationRepository, organization::OrganizationRepository,
+        user::UserRepository,
+    },
+};
+
+use super::{
+    audit::AuditService,
+    constant::{AUDIT_EVENT_MEMBERSHIP_UPDATED, ORG_ROLE_OWNER},
+    error::Result,
+    organization::OrganizationService,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 32;
+const DEFAULT_TTL_HOURS: i64 = 72;
+
+fn ttl_seconds() -> i64 {
+    env::var("ORG_INVITATION_TTL_HOURS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_HOURS)
+        * 60
+        * 60
+}
+
+// owners are only invited by owners, admins can invite admins and members.
+fn can_invite(actor_role: &str, role: &str) -> bool {
+    role != ORG_ROLE_OWNER || actor_role == ORG_ROLE_OWNER
+}
+
+fn invited_email(email: &str) -> Result<&str> {
+    let email = email.trim();
+    if !email.contains('@') {
+        return Err(ServiceError::BadRequest(String::from(
+            "email address is invalid",
+        )));
+    }
+
+    Ok(email)
+}
+
+fn invalid_invitation() -> ServiceError {
+    ServiceError::BadRequest(String::from(
+        "invitation is invalid, has expired or was sent to another address",
+    ))
+}
+
+// Invitations to join an organization, sent by its owners and admins. The link is
+// accepted by a logged in user, at signup or through the google login.
+#[derive(Debug, Clone)]
+pub struct InvitationService {}
+
+impl InvitationService {
+    // a pending invitation to the same address is replaced.
+    pub async fn invite(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        req: &InviteMemberDTO,
+    ) -> Result<BaseResponse<InvitationDTO>> {
+        let (organization, tenant, actor) =
+            OrganizationService::require_manager(mm, ctx, id).await?;
+        OrganizationService::validate_role(&req.role)?;
+
+        if !can_invite(&actor.role, &req.role) {
+            return Err(ServiceError::ForbiddenWithMessage(String::from(
+                "only owners can invite owners",
+            )));
+        }
+
+        let email = invited_email(&req.email)?;
+
+        // members are either global accounts or accounts of the organization itself.
+        for scope in [Ctx::root_ctx(), tenant.clone()] {
+            let Some(user) = UserRepository::get_by_email(scope, mm, email).await? else {
+                continue;
+            };
+
+            if OrganizationRepository::find_member(tenant.clone(), mm, user.id)
+                .await?
+                .is_some()
+            {
+                return Err(ServiceError::ObjectConflict(String::from(
+                    "user is already a member of the organization",
+                )));
+            }
+        }
+
+        InvitationRepository::revoke_for_email(tenant.clone(), mm, email).await?;
+
+        let token = generate_random_string(TOKEN_LENGTH);
+        let invitation = InvitationRepository::create(
+            tenant,
+            mm,
+            email,
+            &req.role,
+            &sha256_hex(&token),
+            actor.user_id,
+            ttl_seconds(),
+        )
+        .await?;
+
+        Self::send(&organization, &invitation.email, &token)?;
+
+        Ok(BaseResponse::new(201, invitation.into()))
+    }
+
+    pub async fn list(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<Vec<InvitationDTO>> {
+        let (_, tenant, _) = OrganizationService::require_manager(mm, ctx, id).await?;
+        let invitations = InvitationRepository::list_pending(tenant, mm).await?;
+
+        Ok(invitations.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn revoke(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        invitation_id: i64,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let (_, tenant, _) = OrganizationService::require_manager(mm, ctx, id).await?;
+
+        if !InvitationRepository::revoke(tenant, mm, invitation_id).await? {
+            return Err(ServiceError::NotFound(String::from("invitation not found")));
+        }
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("invitation revoked"),
+        ))
+    }
+
+    // sends a new link, valid for the whole ttl again.
+    pub async fn resend(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        invitation_id: i64,
+    ) -> Result<InvitationDTO> {
+        let (organization, tenant, _) = OrganizationService::require_manager(mm, ctx, id).await?;
+        let token = generate_random_string(TOKEN_LENGTH);
+
+        let invitation = InvitationRepository::renew(
+            tenant,
+            mm,
+            invitation_id,
+            &sha256_hex(&token),
+            ttl_seconds(),
+        )
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(String::from("invitation not found")))?;
+
+        Self::send(&organization, &invitation.email, &token)?;
+
+        Ok(invitation.into())
+    }
+
+    pub async fn accept(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &AcceptInvitationDTO,
+        client: &ClientMeta,
+    ) -> Result<OrganizationDTO> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        Self::redeem(mm, &user, &req.token, client)
+            .await?
+            .ok_or_else(invalid_invitation)
+    }
+
+    // Joins the organization the invitation is for, `None` when it can't be used by the user.
+    // The link was sent to the user's address so it verifies it as well, used as is by
+    // the signup and the google login.
+    pub async fn redeem(
+        mm: &ModelManager,
+        user: &User,
+        token: &str,
+        client: &ClientMeta,
+    ) -> Result<Option<OrganizationDTO>> {
+        let Some(invitation) =
+            InvitationRepository::accept(Ctx::root_ctx(), mm, &sha256_hex(token), &user.email)
+                .await?
+        else {
+            return Ok(None);
+        };
+
+        let organization =
+            OrganizationRepository::find_by_id(Ctx::root_ctx(), mm, invitation.organization_id)
+                .await?
+                .ok_or_else(invalid_invitation)?;
+        let tenant = Ctx::root_ctx().with_organization(Some(organization.id));
+
+        // members keep the role they already have.
+        let role = match OrganizationRepository::find_member(tenant.clone(), mm, user.id).await? {
+            Some(member) => member.role,
+            None => {
+                OrganizationRepository::upsert_member(tenant, mm, user.id, &invitation.role)
+                    .await?;
+                invitation.role
+            }
+        };
+
+        UserRepository::set_email_verified(Ctx::root_ctx(), mm, user.id).await?;
+
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_MEMBERSHIP_UPDATED,
+            client,
+            json!({ "actor": invitation.invited_by, "organization": organization.id, "role": role, "invitation": invitation.id }),
+        )
+        .await;
+
+        Ok(Some(organization.into_dto(Some(role))))
+    }
+
+    fn send(organization: &Organization, email: &str, token: &str) -> Result<()> {
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+
+        mail::send_in_background(Mail::new(
+            email,
+            &format!("You're invited to join {}", organization.name),
+            format!(
+                "Hi,\n\nyou have been invited to join {} by following the link below, login or sign up with this email address to accept:\n\n{}/invitations/accept?token={}\n\nThe invitation expires in {} hours.",
+                organization.name,
+                base_url,
+                token,
+                ttl_seconds() / (60 * 60)
+            ),
+        ));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{can_invite, invited_email};
+    use crate::service::constant::{ORG_ROLE_ADMIN, ORG_ROLE_MEMBER, ORG_ROLE_OWNER};
+
+    #[test]
+    fn only_owners_invite_owners() {
+        assert!(can_invite(ORG_ROLE_OWNER, ORG_ROLE_OWNER));
+        assert!(!can_invite(ORG_ROLE_ADMIN, ORG_ROLE_OWNER));
+        assert!(can_invite(ORG_ROLE_ADMIN, ORG_ROLE_ADMIN));
+        assert!(can_invite(ORG_ROLE_ADMIN, ORG_ROLE_MEMBER));
+    }
+
+    #[test]
+    fn invited_email_trimmed() {
+        assert_eq!(invited_email(" jo@example.com ").unwrap(), "jo@example.com");
+        assert!(invited_email("example.com").is_err());
+        assert!(invited_email("  ").is_err());
+    }
+}
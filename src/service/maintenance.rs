@@ -0,0 +1,115 @@
+use std::{env, time::Duration};
+
+use tracing::{error, info};
+
+use crate::model::ModelManager;
+
+use super::{
+    account_deletion::AccountDeletionService, admin::AdminService, data_export::DataExportService,
+    oauth::OAuthService, refresh_token::RefreshTokenService, revocation::RevocationService,
+    service_account::ServiceAccountService, session::SessionService,
+    signing_key::SigningKeyService,
+};
+
+const DEFAULT_INTERVAL_MINUTES: u64 = 60;
+
+fn interval() -> Duration {
+    let minutes = env::var("MAINTENANCE_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<u64>().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+
+    Duration::from_secs(minutes * 60)
+}
+
+// Periodic cleanup running for the lifetime of the process, every MAINTENANCE_INTERVAL_MINUTES:
+// purge the accounts past their deletion grace period, drop expired data exports
+// lift the suspensions that ended, forget the expired service account assertions
+// drop the expired authorization codes, refresh tokens and sessions, encrypt the signing keys
+// stored in plain text, publish the next signing key when the active one is due for rotation
+// and retire the ones no token needs anymore.
+pub fn spawn(mm: ModelManager) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval());
+
+        loop {
+            interval.tick().await;
+
+            match AccountDeletionService::purge_expired(&mm).await {
+                Ok(0) => {}
+                Ok(purged) => info!("purged {} deleted accounts", purged),
+                Err(err) => error!("failed to purge deleted accounts: {:?}", err),
+            }
+
+            match DataExportService::delete_expired(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired data exports", deleted),
+                Err(err) => error!("failed to delete expired data exports: {:?}", err),
+            }
+
+            match AdminService::lift_expired_suspensions(&mm).await {
+                Ok(0) => {}
+                Ok(lifted) => info!("lifted {} expired suspensions", lifted),
+                Err(err) => error!("failed to lift expired suspensions: {:?}", err),
+            }
+
+            match ServiceAccountService::delete_expired_assertions(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired service account assertions", deleted),
+                Err(err) => error!(
+                    "failed to delete expired service account assertions: {:?}",
+                    err
+                ),
+            }
+
+            match OAuthService::delete_expired_codes(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired authorization codes", deleted),
+                Err(err) => error!("failed to delete expired authorization codes: {:?}", err),
+            }
+
+            match OAuthService::delete_expired_device_codes(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired device codes", deleted),
+                Err(err) => error!("failed to delete expired device codes: {:?}", err),
+            }
+
+            match RevocationService::delete_expired(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired revoked tokens", deleted),
+                Err(err) => error!("failed to delete expired revoked tokens: {:?}", err),
+            }
+
+            match RefreshTokenService::delete_expired(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired refresh tokens", deleted),
+                Err(err) => error!("failed to delete expired refresh tokens: {:?}", err),
+            }
+
+            match SessionService::delete_expired(&mm).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("deleted {} expired sessions", deleted),
+                Err(err) => error!("failed to delete expired sessions: {:?}", err),
+            }
+
+            match SigningKeyService::encrypt_stored(&mm).await {
+                Ok(0) => {}
+                Ok(encrypted) => info!("encrypted {} signing keys", encrypted),
+                Err(err) => error!("failed to encrypt signing keys: {:?}", err),
+            }
+
+            match SigningKeyService::schedule_rotation(&mm).await {
+                Ok(None) => {}
+                Ok(Some(kid)) => info!("published signing key {}", kid),
+                Err(err) => error!("failed to rotate the signing key: {:?}", err),
+            }
+
+            match SigningKeyService::retire_superseded(&mm).await {
+                Ok(0) => {}
+                Ok(retired) => info!("retired {} signing keys", retired),
+                Err(err) => error!("failed to retire signing keys: {:?}", err),
+            }
+        }
+    });
+}
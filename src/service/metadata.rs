@@ -0,0 +1,120 @@
+use std::{env, fs, sync::OnceLock};
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+use super::{error::Result, ServiceError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetadataKind {
+    // editable by the user through PATCH /me.
+    User,
+    // only editable by administrators.
+    App,
+}
+
+impl MetadataKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetadataKind::User => "user_metadata",
+            MetadataKind::App => "app_metadata",
+        }
+    }
+
+    fn schema_env(&self) -> &'static str {
+        match self {
+            MetadataKind::User => "USER_METADATA_SCHEMA",
+            MetadataKind::App => "APP_METADATA_SCHEMA",
+        }
+    }
+}
+
+type CachedValidator = core::result::Result<Option<Validator>, String>;
+
+// the schema file named by USER_METADATA_SCHEMA / APP_METADATA_SCHEMA, compiled once.
+fn validator(kind: MetadataKind) -> &'static CachedValidator {
+    static USER: OnceLock<CachedValidator> = OnceLock::new();
+    static APP: OnceLock<CachedValidator> = OnceLock::new();
+
+    let cell = match kind {
+        MetadataKind::User => &USER,
+        MetadataKind::App => &APP,
+    };
+
+    cell.get_or_init(|| compile(kind, env::var(kind.schema_env()).ok()))
+}
+
+// an empty value, as shipped in .env.example, means no schema.
+fn compile(kind: MetadataKind, path: Option<String>) -> CachedValidator {
+    let Some(path) = path.filter(|x| !x.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {} schema {path}: {e}", kind.name()))?;
+    let schema: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("invalid {} schema {path}: {e}", kind.name()))?;
+
+    jsonschema::validator_for(&schema)
+        .map(Some)
+        .map_err(|e| format!("invalid {} schema {path}: {e}", kind.name()))
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataService {}
+
+impl MetadataService {
+    // metadata must be an object, and match the configured schema if there is one.
+    pub fn validate(kind: MetadataKind, value: &Value) -> Result<()> {
+        if !value.is_object() {
+            return Err(ServiceError::BadRequest(format!(
+                "{} must be a json object",
+                kind.name()
+            )));
+        }
+
+        let validator = match validator(kind) {
+            Ok(Some(x)) => x,
+            Ok(None) => return Ok(()),
+            Err(err) => return Err(ServiceError::ApplicationStartup(err.clone())),
+        };
+
+        let errors: Vec<String> = validator
+            .iter_errors(value)
+            .map(|x| format!("{}{}: {}", kind.name(), x.instance_path, x))
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(ServiceError::BadRequest(errors.join(", ")));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compile, MetadataKind};
+
+    #[test]
+    fn compile_without_schema() {
+        assert!(matches!(compile(MetadataKind::User, None), Ok(None)));
+        assert!(matches!(
+            compile(MetadataKind::User, Some(String::new())),
+            Ok(None)
+        ));
+        assert!(matches!(
+            compile(MetadataKind::App, Some(String::from("  "))),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn compile_missing_schema_file() {
+        assert!(compile(
+            MetadataKind::User,
+            Some(String::from("/nonexistent/schema.json"))
+        )
+        .is_err());
+    }
+}
@@ -1,7 +1,31 @@
 mod error;
 
+pub mod account_deletion;
+pub mod admin;
+pub mod api_key;
+pub mod audit;
 pub mod auth;
+pub mod client_assertion;
 pub mod constant;
+pub mod data_export;
+pub mod email_change;
+pub mod email_verification;
+pub mod introspection;
+pub mod invitation;
+pub mod maintenance;
+pub mod metadata;
+pub mod oauth;
+pub mod oauth_client;
+pub mod oidc;
+pub mod organization;
+pub mod password_reset;
+pub mod refresh_token;
+pub mod revocation;
+pub mod role;
+pub mod service_account;
+pub mod session;
+pub mod signing_key;
+pub mod token;
 pub mod user;
 
-pub use self::error::{Result, ServiceError};
+pub use self::error::{suspension_message, Result, ServiceError};
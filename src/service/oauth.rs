@@ -0,0 +1,1389 @@
+use std::env;
+
+use anyhow::Context;
+use oauth2::url::Url;
+use serde_json::json;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{
+            client::ClientMeta,
+            oauth::{
+                AuthorizeDTO, AuthorizeQuery, BasicCredentials, ClientAuthentication,
+                DeviceAuthorizationRequest, TokenRequest, VerifyDeviceDTO,
+            },
+        },
+        response::{
+            oauth::{
+                AuthorizeResponseDTO, ConsentDTO, ConsentRequestDTO, DeviceAuthorizationDTO,
+                DeviceRequestDTO, TokenResponseDTO,
+            },
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{
+        oauth::{DeviceCode, OAuthClient},
+        user::User,
+        ModelManager,
+    },
+    pkg::util::{
+        digest::{constant_time_eq, pkce_s256, sha256_hex},
+        rand::generate_random_string,
+        user_code::{format_user_code, generate_user_code, normalize_user_code},
+    },
+    repository::{
+        authorization_code::{AuthorizationCodeRepository, NewAuthorizationCode},
+        device_code::{DeviceAnswer, DeviceCodeRepository, NewDeviceCode},
+        oauth_client::OAuthClientRepository,
+        organization::OrganizationRepository,
+        session::SessionRepository,
+        user::UserRepository,
+    },
+};
+
+use super::{
+    audit::AuditService,
+    client_assertion,
+    constant::{
+        AUDIT_EVENT_CONSENT_GRANTED, AUDIT_EVENT_CONSENT_REVOKED, CLIENT_ASSERTION_TYPE_JWT_BEARER,
+        CODE_CHALLENGE_METHOD_S256, DEVICE_CODE_STATUS_APPROVED, DEVICE_CODE_STATUS_DENIED,
+        GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_CLIENT_CREDENTIALS, GRANT_TYPE_DEVICE_CODE,
+        GRANT_TYPE_REFRESH_TOKEN, OAUTH_ERROR_ACCESS_DENIED, OAUTH_ERROR_AUTHORIZATION_PENDING,
+        OAUTH_ERROR_EXPIRED_TOKEN, OAUTH_ERROR_INVALID_CLIENT, OAUTH_ERROR_INVALID_GRANT,
+        OAUTH_ERROR_INVALID_REQUEST, OAUTH_ERROR_INVALID_SCOPE, OAUTH_ERROR_INVALID_TARGET,
+        OAUTH_ERROR_SLOW_DOWN, OAUTH_ERROR_UNAUTHORIZED_CLIENT, OAUTH_ERROR_UNSUPPORTED_GRANT_TYPE,
+        OAUTH_ERROR_UNSUPPORTED_RESPONSE_TYPE, RESPONSE_TYPE_CODE, SCOPE_OPENID,
+    },
+    email_verification::EmailVerificationService,
+    error::Result,
+    oidc::OidcService,
+    refresh_token::{new_family_id, RefreshTokenService},
+    session::SessionService,
+    token::{access_token_ttl_seconds, Authentication, TokenService},
+    user::UserService,
+    ServiceError,
+};
+
+const CODE_LENGTH: usize = 32;
+// codes are exchanged right after the redirect, RFC 6749 recommends at most 10 minutes.
+const CODE_TTL_SECONDS: i64 = 60;
+// RFC 8628 leaves it to the server, long enough to pick up another device and log in.
+const DEVICE_CODE_TTL_SECONDS: i64 = 10 * 60;
+// between two polls of the token endpoint, raised by as much on `slow_down`.
+const DEVICE_POLL_INTERVAL_SECONDS: i32 = 5;
+const USER_CODE_ATTEMPTS: usize = 3;
+// RFC 7636 section 4.2.
+const CODE_CHALLENGE_LENGTH: std::ops::RangeInclusive<usize> = 43..=128;
+
+// page of the frontend logging the user in and asking for consent,
+// it gets the query string of the authorization request.
+fn login_url() -> anyhow::Result<String> {
+    match env::var("OAUTH_LOGIN_URL").ok().filter(|x| !x.is_empty()) {
+        Some(url) => Ok(url),
+        None => {
+            let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+            Ok(format!("{}/oauth/login", base_url))
+        }
+    }
+}
+
+// page where the users enter the code shown by the device,
+// `verification_uri_complete` gives it the code in the `user_code` parameter.
+fn device_verification_url() -> anyhow::Result<String> {
+    match env::var("OAUTH_DEVICE_URL").ok().filter(|x| !x.is_empty()) {
+        Some(url) => Ok(url),
+        None => {
+            let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+            Ok(format!("{}/oauth/device", base_url))
+        }
+    }
+}
+
+// the `aud` of the private_key_jwt assertions.
+fn token_url() -> anyhow::Result<String> {
+    let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+
+    Ok(format!("{}/token", base_url))
+}
+
+fn with_query(url: &str, params: &[(&str, &str)]) -> Result<String> {
+    let mut url = Url::parse(url).context("invalid redirect uri")?;
+    url.query_pairs_mut().extend_pairs(params);
+
+    Ok(url.to_string())
+}
+
+fn split_scope(scope: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = scope.split_whitespace().map(String::from).collect();
+    scopes.sort();
+    scopes.dedup();
+    scopes
+}
+
+fn has_scope(scope: &str, name: &str) -> bool {
+    scope.split_whitespace().any(|x| x == name)
+}
+
+fn invalid_grant(description: &str) -> ServiceError {
+    ServiceError::oauth(OAUTH_ERROR_INVALID_GRANT, description)
+}
+
+// client_credentials grant: the requested scope, within the one granted to the client,
+// all of it when none is requested.
+fn client_credentials_scope(requested: Option<&str>, granted: &[String]) -> Result<String> {
+    let Some(requested) = requested else {
+        return Ok(granted.join(" "));
+    };
+
+    let requested = split_scope(requested);
+    if !requested.iter().all(|x| granted.contains(x)) {
+        return Err(ServiceError::oauth(
+            OAUTH_ERROR_INVALID_SCOPE,
+            "scope exceeds the one granted to the client",
+        ));
+    }
+
+    Ok(requested.join(" "))
+}
+
+// RFC 8707 resource indicators go by `invalid_target` as well.
+fn client_credentials_audiences(
+    requested: Option<&str>,
+    registered: &[String],
+) -> Result<Vec<String>> {
+    let audiences = match requested {
+        Some(requested) => {
+            let requested = split_scope(requested);
+            if requested.is_empty() || !requested.iter().all(|x| registered.contains(x)) {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_TARGET,
+                    "audience isn't registered for the client",
+                ));
+            }
+            requested
+        }
+        None => registered.to_vec(),
+    };
+
+    if audiences.is_empty() {
+        return Err(ServiceError::oauth(
+            OAUTH_ERROR_INVALID_TARGET,
+            "the client has no audience registered",
+        ));
+    }
+
+    Ok(audiences)
+}
+
+// An error of the authorization request the client is told about through the redirect,
+// once the client and its redirect uri are known to be legit.
+struct AuthorizeError {
+    error: &'static str,
+    description: &'static str,
+}
+
+impl AuthorizeError {
+    fn new(error: &'static str, description: &'static str) -> Self {
+        Self { error, description }
+    }
+}
+
+// The authorization server of our own and third party applications: the authorization
+// code grant with PKCE, refresh tokens and the consent of the users. The user logs in
+// through the usual login endpoints, MFA included, before the code is issued.
+#[derive(Debug, Clone)]
+pub struct OAuthService {}
+
+impl OAuthService {
+    // Where to send the browser starting an authorization request: the login page, or back
+    // to the client when the request can't be served.
+    pub async fn authorize_redirect(
+        mm: &ModelManager,
+        req: &AuthorizeQuery,
+        query: &str,
+    ) -> Result<String> {
+        let (client, redirect_uri) = Self::client_and_redirect_uri(mm, req).await?;
+
+        if let Err(err) = Self::validate_request(&client, req) {
+            return Self::error_redirect(&redirect_uri, err, req.state.as_deref());
+        }
+
+        Ok(format!("{}?{}", login_url()?, query))
+    }
+
+    // Called by the login page once the user is authenticated, and again with the answer
+    // when the user has to consent.
+    pub async fn authorize(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &AuthorizeDTO,
+        client: &ClientMeta,
+    ) -> Result<AuthorizeResponseDTO> {
+        let request = &req.request;
+        let (oauth_client, redirect_uri) = Self::client_and_redirect_uri(mm, request).await?;
+        let state = request.state.as_deref();
+
+        let scopes = match Self::validate_request(&oauth_client, request) {
+            Ok(scopes) => scopes,
+            Err(err) => return Self::error_response(&redirect_uri, err, state),
+        };
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+        UserService::ensure_can_login(&user)?;
+
+        match req.consent {
+            Some(false) => {
+                return Self::error_response(
+                    &redirect_uri,
+                    AuthorizeError::new(OAUTH_ERROR_ACCESS_DENIED, "the user denied the request"),
+                    state,
+                );
+            }
+            Some(true) => {
+                OAuthClientRepository::grant_consent(
+                    Ctx::root_ctx(),
+                    mm,
+                    user.id,
+                    oauth_client.id,
+                    &scopes,
+                )
+                .await?;
+
+                AuditService::record(
+                    mm,
+                    user.id,
+                    AUDIT_EVENT_CONSENT_GRANTED,
+                    client,
+                    json!({ "client": oauth_client.client_id, "scopes": scopes }),
+                )
+                .await;
+            }
+            None if !oauth_client.first_party => {
+                let consented = OAuthClientRepository::consented_scopes(
+                    Ctx::root_ctx(),
+                    mm,
+                    user.id,
+                    oauth_client.id,
+                )
+                .await?;
+
+                if !scopes.iter().all(|x| consented.contains(x)) {
+                    return Ok(AuthorizeResponseDTO {
+                        redirect_to: None,
+                        consent: Some(ConsentRequestDTO {
+                            client_id: oauth_client.client_id,
+                            client_name: oauth_client.name,
+                            scopes,
+                        }),
+                    });
+                }
+            }
+            None => {}
+        }
+
+        let code = generate_random_string(CODE_LENGTH);
+        let authentication = Authentication::from_ctx(ctx);
+
+        AuthorizationCodeRepository::create(
+            Ctx::root_ctx().with_organization(ctx.organization_id()),
+            mm,
+            NewAuthorizationCode {
+                code_hash: &sha256_hex(&code),
+                client_id: oauth_client.id,
+                user_id: user.id,
+                redirect_uri: &redirect_uri,
+                redirect_uri_provided: request.redirect_uri.is_some(),
+                scope: &scopes.join(" "),
+                code_challenge: request.code_challenge.as_deref(),
+                code_challenge_method: request
+                    .code_challenge
+                    .as_ref()
+                    .map(|_| CODE_CHALLENGE_METHOD_S256),
+                family_id: &new_family_id(),
+                nonce: request.nonce.as_deref(),
+                auth_time: authentication.time,
+                amr: &authentication.methods,
+                session_id: authentication.session_id,
+                ttl_seconds: CODE_TTL_SECONDS,
+            },
+        )
+        .await?;
+
+        let mut params = vec![("code", code.as_str())];
+        if let Some(state) = state {
+            params.push(("state", state));
+        }
+
+        Ok(AuthorizeResponseDTO {
+            redirect_to: Some(with_query(&redirect_uri, &params)?),
+            consent: None,
+        })
+    }
+
+    pub async fn token(
+        mm: &ModelManager,
+        req: &TokenRequest,
+        credentials: &BasicCredentials,
+        client: &ClientMeta,
+    ) -> Result<TokenResponseDTO> {
+        match req.grant_type.as_str() {
+            GRANT_TYPE_AUTHORIZATION_CODE => {
+                Self::authorization_code_grant(mm, req, credentials).await
+            }
+            GRANT_TYPE_REFRESH_TOKEN => {
+                Self::refresh_token_grant(mm, req, credentials, client).await
+            }
+            GRANT_TYPE_CLIENT_CREDENTIALS => {
+                Self::client_credentials_grant(mm, req, credentials).await
+            }
+            GRANT_TYPE_DEVICE_CODE => Self::device_code_grant(mm, req, credentials).await,
+            _ => Err(ServiceError::oauth(
+                OAUTH_ERROR_UNSUPPORTED_GRANT_TYPE,
+                "grant_type must be authorization_code, refresh_token, client_credentials or urn:ietf:params:oauth:grant-type:device_code",
+            )),
+        }
+    }
+
+    // the refresh token of a login kept in a cookie, see `cookie_session`.
+    pub async fn refresh_login(
+        mm: &ModelManager,
+        refresh_token: &str,
+        client: &ClientMeta,
+    ) -> Result<TokenResponseDTO> {
+        let req = TokenRequest {
+            grant_type: GRANT_TYPE_REFRESH_TOKEN.to_string(),
+            code: None,
+            redirect_uri: None,
+            code_verifier: None,
+            refresh_token: Some(refresh_token.to_string()),
+            scope: None,
+            audience: None,
+            device_code: None,
+            client: ClientAuthentication::default(),
+        };
+
+        Self::refresh_token_grant(mm, &req, &BasicCredentials::default(), client).await
+    }
+
+    // RFC 8628 section 3.1: the device shows the user code and polls the token endpoint
+    // with the device code while the user answers from a browser on another device.
+    pub async fn device_authorization(
+        mm: &ModelManager,
+        req: &DeviceAuthorizationRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<DeviceAuthorizationDTO> {
+        let oauth_client = Self::authenticate_client(mm, &req.client, credentials)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::oauth(OAUTH_ERROR_INVALID_CLIENT, "client_id is required")
+            })?;
+
+        if !oauth_client.allows_grant(GRANT_TYPE_DEVICE_CODE) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the device_code grant",
+            ));
+        }
+
+        let scopes = match &req.scope {
+            Some(scope) => split_scope(scope),
+            None => oauth_client.allowed_scopes.clone(),
+        };
+
+        if scopes.is_empty()
+            || !scopes
+                .iter()
+                .all(|x| oauth_client.allowed_scopes.contains(x))
+        {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_INVALID_SCOPE,
+                "scope isn't allowed for the client",
+            ));
+        }
+
+        let device_code = generate_random_string(CODE_LENGTH);
+        let device_code_hash = sha256_hex(&device_code);
+        let scope = scopes.join(" ");
+
+        // the user codes are short, another request could hold the same one.
+        let mut created = None;
+        for _ in 0..USER_CODE_ATTEMPTS {
+            let user_code = generate_user_code();
+            created = DeviceCodeRepository::create(
+                Ctx::root_ctx(),
+                mm,
+                NewDeviceCode {
+                    device_code_hash: &device_code_hash,
+                    user_code: &user_code,
+                    client_id: oauth_client.id,
+                    scope: &scope,
+                    poll_interval: DEVICE_POLL_INTERVAL_SECONDS,
+                    ttl_seconds: DEVICE_CODE_TTL_SECONDS,
+                },
+            )
+            .await?;
+
+            if created.is_some() {
+                break;
+            }
+        }
+
+        let created = created.context("failed to generate a unique user code")?;
+        let user_code = format_user_code(&created.user_code);
+        let verification_uri = device_verification_url()?;
+
+        Ok(DeviceAuthorizationDTO {
+            device_code,
+            verification_uri_complete: with_query(
+                &verification_uri,
+                &[("user_code", user_code.as_str())],
+            )?,
+            verification_uri,
+            user_code,
+            expires_in: DEVICE_CODE_TTL_SECONDS as u64,
+            interval: DEVICE_POLL_INTERVAL_SECONDS as u64,
+        })
+    }
+
+    // what the verification page shows before the user answers.
+    pub async fn device_request(mm: &ModelManager, user_code: &str) -> Result<DeviceRequestDTO> {
+        let (device_code, oauth_client) = Self::pending_device_code(mm, user_code).await?;
+
+        Ok(DeviceRequestDTO {
+            user_code: format_user_code(&device_code.user_code),
+            client_id: oauth_client.client_id,
+            client_name: oauth_client.name,
+            scopes: split_scope(&device_code.scope),
+        })
+    }
+
+    // The answer of the logged in user. Approving counts as the consent to the scopes,
+    // the tokens act in the organization of the session, if any.
+    pub async fn verify_device(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &VerifyDeviceDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+        UserService::ensure_can_login(&user)?;
+
+        let (device_code, oauth_client) = Self::pending_device_code(mm, &req.user_code).await?;
+        let authentication = Authentication::from_ctx(ctx);
+
+        let status = match req.approve {
+            true => DEVICE_CODE_STATUS_APPROVED,
+            false => DEVICE_CODE_STATUS_DENIED,
+        };
+
+        if !DeviceCodeRepository::answer(
+            Ctx::root_ctx().with_organization(ctx.organization_id()),
+            mm,
+            device_code.id,
+            DeviceAnswer {
+                status,
+                user_id: user.id,
+                auth_time: authentication.time,
+                amr: &authentication.methods,
+                session_id: authentication.session_id,
+            },
+        )
+        .await?
+        {
+            return Err(ServiceError::NotFound(String::from(
+                "code is invalid or expired",
+            )));
+        }
+
+        if !req.approve {
+            return Ok(BaseResponse::new(
+                200,
+                MessageResponse::new("device request denied"),
+            ));
+        }
+
+        let scopes = split_scope(&device_code.scope);
+        OAuthClientRepository::grant_consent(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            oauth_client.id,
+            &scopes,
+        )
+        .await?;
+
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_CONSENT_GRANTED,
+            client,
+            json!({ "client": oauth_client.client_id, "scopes": scopes, "device": true }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("device approved"),
+        ))
+    }
+
+    pub async fn list_consents(mm: &ModelManager, ctx: &Ctx) -> Result<Vec<ConsentDTO>> {
+        let consents =
+            OAuthClientRepository::list_consents(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        Ok(consents.into_iter().map(Into::into).collect())
+    }
+
+    // the client loses its refresh tokens as well, the user is asked again next time.
+    pub async fn revoke_consent(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        client_id: &str,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user_id = ctx.user_id() as i64;
+        let not_found = || ServiceError::NotFound(String::from("consent not found"));
+
+        let oauth_client = OAuthClientRepository::find_by_client_id(Ctx::root_ctx(), mm, client_id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        if !OAuthClientRepository::revoke_consent(Ctx::root_ctx(), mm, user_id, oauth_client.id)
+            .await?
+        {
+            return Err(not_found());
+        }
+
+        RefreshTokenService::revoke_for_user(mm, user_id, Some(oauth_client.id)).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_CONSENT_REVOKED,
+            client,
+            json!({ "client": oauth_client.client_id }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("consent revoked"),
+        ))
+    }
+
+    pub async fn delete_expired_codes(mm: &ModelManager) -> Result<u64> {
+        let deleted = AuthorizationCodeRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+
+    pub async fn delete_expired_device_codes(mm: &ModelManager) -> Result<u64> {
+        let deleted = DeviceCodeRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+
+    async fn authorization_code_grant(
+        mm: &ModelManager,
+        req: &TokenRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<TokenResponseDTO> {
+        let oauth_client = Self::authenticate_client(mm, &req.client, credentials)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::oauth(OAUTH_ERROR_INVALID_CLIENT, "client_id is required")
+            })?;
+
+        if !oauth_client.allows_grant(GRANT_TYPE_AUTHORIZATION_CODE) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the authorization_code grant",
+            ));
+        }
+
+        let code = req
+            .code
+            .as_deref()
+            .ok_or_else(|| ServiceError::oauth(OAUTH_ERROR_INVALID_REQUEST, "code is required"))?;
+        let code_hash = sha256_hex(code);
+
+        let Some(code) =
+            AuthorizationCodeRepository::consume(Ctx::root_ctx(), mm, &code_hash).await?
+        else {
+            // a replayed code could have been intercepted, the tokens issued for it go away.
+            if let Some(used) =
+                AuthorizationCodeRepository::find(Ctx::root_ctx(), mm, &code_hash).await?
+            {
+                RefreshTokenService::revoke_family(mm, &used.family_id).await?;
+            }
+
+            return Err(invalid_grant("code is invalid, expired or already used"));
+        };
+
+        if code.client_id != oauth_client.id {
+            return Err(invalid_grant("code was issued to another client"));
+        }
+
+        // RFC 6749 section 4.1.3: required when the authorization request had it.
+        match req.redirect_uri.as_deref() {
+            Some(uri) if uri != code.redirect_uri => {
+                return Err(invalid_grant(
+                    "redirect_uri doesn't match the authorization request",
+                ))
+            }
+            None if code.redirect_uri_provided => {
+                return Err(invalid_grant(
+                    "redirect_uri is required, the authorization request had it",
+                ))
+            }
+            _ => {}
+        }
+
+        match (&code.code_challenge, &req.code_verifier) {
+            (Some(challenge), Some(verifier)) => {
+                if !constant_time_eq(&pkce_s256(verifier), challenge) {
+                    return Err(invalid_grant(
+                        "code_verifier doesn't match the code_challenge",
+                    ));
+                }
+            }
+            (Some(_), None) => {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "code_verifier is required",
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(invalid_grant(
+                    "the authorization request had no code_challenge",
+                ))
+            }
+            (None, None) => {}
+        }
+
+        let user = Self::active_user(mm, code.user_id, code.organization_id).await?;
+        let authentication = Authentication {
+            time: code.auth_time.unwrap_or(code.created_at),
+            methods: code.amr.clone(),
+            session_id: code.session_id,
+        };
+        Self::continue_session(mm, code.session_id).await?;
+
+        let access_token = TokenService::issue_client_access_token(
+            mm,
+            &user,
+            &code.scope,
+            code.organization_id,
+            &authentication,
+            &oauth_client.client_id,
+        )
+        .await?;
+
+        let refresh_token = match oauth_client.allows_grant(GRANT_TYPE_REFRESH_TOKEN) {
+            true => Some(
+                RefreshTokenService::issue(
+                    mm,
+                    user.id,
+                    Some(oauth_client.id),
+                    &code.scope,
+                    code.organization_id,
+                    &code.family_id,
+                    &authentication,
+                )
+                .await?,
+            ),
+            false => None,
+        };
+
+        let id_token = match has_scope(&code.scope, SCOPE_OPENID) {
+            true => Some(
+                OidcService::issue_id_token(
+                    mm,
+                    &user,
+                    &oauth_client.client_id,
+                    &code.scope,
+                    &authentication,
+                    code.nonce.as_deref(),
+                )
+                .await?,
+            ),
+            false => None,
+        };
+
+        Ok(TokenResponseDTO {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: access_token_ttl_seconds(),
+            refresh_token,
+            scope: code.scope,
+            id_token,
+        })
+    }
+
+    // Also exchanges the refresh tokens of the login endpoints, presented without a client.
+    // Their scope follows the state of the account, the ones of a client can only be narrowed.
+    async fn refresh_token_grant(
+        mm: &ModelManager,
+        req: &TokenRequest,
+        credentials: &BasicCredentials,
+        client: &ClientMeta,
+    ) -> Result<TokenResponseDTO> {
+        let oauth_client = Self::authenticate_client(mm, &req.client, credentials).await?;
+
+        if oauth_client
+            .as_ref()
+            .is_some_and(|x| !x.allows_grant(GRANT_TYPE_REFRESH_TOKEN))
+        {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the refresh_token grant",
+            ));
+        }
+
+        let token = req.refresh_token.as_deref().ok_or_else(|| {
+            ServiceError::oauth(OAUTH_ERROR_INVALID_REQUEST, "refresh_token is required")
+        })?;
+
+        let refresh_token =
+            RefreshTokenService::consume(mm, token, oauth_client.as_ref().map(|x| x.id), client)
+                .await?;
+
+        let user =
+            Self::active_user(mm, refresh_token.user_id, refresh_token.organization_id).await?;
+
+        // signing out everywhere, a password reset and so on revoke the tokens issued before.
+        if user
+            .tokens_valid_after
+            .is_some_and(|x| refresh_token.created_at < x)
+        {
+            return Err(invalid_grant("refresh token has been revoked"));
+        }
+
+        let authentication = Authentication {
+            time: refresh_token.auth_time.unwrap_or(refresh_token.created_at),
+            methods: refresh_token.amr.clone(),
+            session_id: refresh_token.session_id,
+        };
+
+        if let Some(session_id) = refresh_token.session_id {
+            SessionService::extend(mm, session_id).await?;
+        }
+
+        let (access_token, scope, id_token) = match &oauth_client {
+            Some(oauth_client) => {
+                let granted = split_scope(&refresh_token.scope);
+                let scope = match &req.scope {
+                    Some(requested) => {
+                        let requested = split_scope(requested);
+                        if !requested.iter().all(|x| granted.contains(x)) {
+                            return Err(ServiceError::oauth(
+                                OAUTH_ERROR_INVALID_SCOPE,
+                                "scope exceeds the one originally granted",
+                            ));
+                        }
+                        requested.join(" ")
+                    }
+                    None => refresh_token.scope.clone(),
+                };
+
+                let access_token = TokenService::issue_client_access_token(
+                    mm,
+                    &user,
+                    &scope,
+                    refresh_token.organization_id,
+                    &authentication,
+                    &oauth_client.client_id,
+                )
+                .await?;
+
+                let id_token = match has_scope(&scope, SCOPE_OPENID) {
+                    true => Some(
+                        OidcService::issue_id_token(
+                            mm,
+                            &user,
+                            &oauth_client.client_id,
+                            &scope,
+                            &authentication,
+                            None,
+                        )
+                        .await?,
+                    ),
+                    false => None,
+                };
+
+                (access_token, scope, id_token)
+            }
+            None => {
+                let scope = EmailVerificationService::login_scope(&user)
+                    .map_err(|_| invalid_grant("email address is not verified"))?
+                    .to_string();
+
+                let access_token = TokenService::issue_access_token(
+                    mm,
+                    &user,
+                    &scope,
+                    refresh_token.organization_id,
+                    &authentication,
+                )
+                .await?;
+
+                (access_token, scope, None)
+            }
+        };
+
+        // the successor keeps what was granted, even when this access token got less.
+        let successor_scope = match oauth_client {
+            Some(_) => refresh_token.scope.as_str(),
+            None => scope.as_str(),
+        };
+
+        let successor = RefreshTokenService::issue(
+            mm,
+            user.id,
+            refresh_token.client_id,
+            successor_scope,
+            refresh_token.organization_id,
+            &refresh_token.family_id,
+            &authentication,
+        )
+        .await?;
+
+        Ok(TokenResponseDTO {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: access_token_ttl_seconds(),
+            refresh_token: Some(successor),
+            scope,
+            id_token,
+        })
+    }
+
+    // Service to service tokens, the client acts on its own behalf. The token is restricted
+    // to some of the APIs registered for the client, all of them unless `audience` says which.
+    async fn client_credentials_grant(
+        mm: &ModelManager,
+        req: &TokenRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<TokenResponseDTO> {
+        let oauth_client = Self::authenticate_client(mm, &req.client, credentials)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_CLIENT,
+                    "client authentication is required",
+                )
+            })?;
+
+        if oauth_client.is_public() || !oauth_client.allows_grant(GRANT_TYPE_CLIENT_CREDENTIALS) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the client_credentials grant",
+            ));
+        }
+
+        let scope = client_credentials_scope(req.scope.as_deref(), &oauth_client.client_scopes)?;
+        let audiences =
+            client_credentials_audiences(req.audience.as_deref(), &oauth_client.audiences)?;
+
+        let access_token =
+            TokenService::issue_client_credentials_token(mm, &oauth_client, &scope, &audiences)
+                .await?;
+
+        // no refresh token, the client can ask for a new access token whenever.
+        Ok(TokenResponseDTO {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: access_token_ttl_seconds(),
+            refresh_token: None,
+            scope,
+            id_token: None,
+        })
+    }
+
+    // RFC 8628 section 3.4, polled by the device until the user answered. The device code
+    // is good for a single token response, like an authorization code.
+    async fn device_code_grant(
+        mm: &ModelManager,
+        req: &TokenRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<TokenResponseDTO> {
+        let oauth_client = Self::authenticate_client(mm, &req.client, credentials)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::oauth(OAUTH_ERROR_INVALID_CLIENT, "client_id is required")
+            })?;
+
+        if !oauth_client.allows_grant(GRANT_TYPE_DEVICE_CODE) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the device_code grant",
+            ));
+        }
+
+        let device_code = req.device_code.as_deref().ok_or_else(|| {
+            ServiceError::oauth(OAUTH_ERROR_INVALID_REQUEST, "device_code is required")
+        })?;
+
+        let poll = DeviceCodeRepository::poll(
+            Ctx::root_ctx(),
+            mm,
+            &sha256_hex(device_code),
+            DEVICE_POLL_INTERVAL_SECONDS,
+        )
+        .await?
+        .filter(|x| x.code.client_id == oauth_client.id)
+        .ok_or_else(|| invalid_grant("device_code is invalid"))?;
+
+        if poll.code.used_at.is_some() {
+            return Err(invalid_grant("device_code was already used"));
+        }
+
+        if poll.code.expires_at <= OffsetDateTime::now_utc() {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_EXPIRED_TOKEN,
+                "device_code has expired",
+            ));
+        }
+
+        match poll.code.status.as_str() {
+            DEVICE_CODE_STATUS_APPROVED => {}
+            DEVICE_CODE_STATUS_DENIED => {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_ACCESS_DENIED,
+                    "the user denied the request",
+                ))
+            }
+            _ if poll.too_fast => {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_SLOW_DOWN,
+                    &format!("wait {} seconds between the polls", poll.code.poll_interval),
+                ))
+            }
+            _ => {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_AUTHORIZATION_PENDING,
+                    "the user hasn't answered yet",
+                ))
+            }
+        }
+
+        let code = DeviceCodeRepository::consume(Ctx::root_ctx(), mm, poll.code.id)
+            .await?
+            .ok_or_else(|| invalid_grant("device_code was already used"))?;
+
+        let user_id = code
+            .user_id
+            .context("approved device code without a user")?;
+        let user = Self::active_user(mm, user_id, code.organization_id).await?;
+        let authentication = Authentication {
+            time: code.auth_time.unwrap_or(code.created_at),
+            methods: code.amr.clone(),
+            session_id: code.session_id,
+        };
+        Self::continue_session(mm, code.session_id).await?;
+
+        let access_token = TokenService::issue_client_access_token(
+            mm,
+            &user,
+            &code.scope,
+            code.organization_id,
+            &authentication,
+            &oauth_client.client_id,
+        )
+        .await?;
+
+        let refresh_token = match oauth_client.allows_grant(GRANT_TYPE_REFRESH_TOKEN) {
+            true => Some(
+                RefreshTokenService::issue(
+                    mm,
+                    user.id,
+                    Some(oauth_client.id),
+                    &code.scope,
+                    code.organization_id,
+                    &new_family_id(),
+                    &authentication,
+                )
+                .await?,
+            ),
+            false => None,
+        };
+
+        let id_token = match has_scope(&code.scope, SCOPE_OPENID) {
+            true => Some(
+                OidcService::issue_id_token(
+                    mm,
+                    &user,
+                    &oauth_client.client_id,
+                    &code.scope,
+                    &authentication,
+                    None,
+                )
+                .await?,
+            ),
+            false => None,
+        };
+
+        Ok(TokenResponseDTO {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: access_token_ttl_seconds(),
+            refresh_token,
+            scope: code.scope,
+            id_token,
+        })
+    }
+
+    // a request still waiting for the user, of a client that can still get tokens.
+    async fn pending_device_code(
+        mm: &ModelManager,
+        user_code: &str,
+    ) -> Result<(DeviceCode, OAuthClient)> {
+        let not_found = || ServiceError::NotFound(String::from("code is invalid or expired"));
+
+        let user_code = normalize_user_code(user_code).ok_or_else(not_found)?;
+        let device_code = DeviceCodeRepository::find_pending(Ctx::root_ctx(), mm, &user_code)
+            .await?
+            .ok_or_else(not_found)?;
+
+        let oauth_client =
+            OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, device_code.client_id)
+                .await?
+                .filter(|x| x.disabled_at.is_none())
+                .ok_or_else(not_found)?;
+
+        Ok((device_code, oauth_client))
+    }
+
+    // The client making the token request, `None` when it didn't identify itself.
+    // Confidential clients authenticate with their secret or an assertion signed by one of
+    // their keys, public ones only give their id.
+    pub async fn authenticate_client(
+        mm: &ModelManager,
+        req: &ClientAuthentication,
+        credentials: &BasicCredentials,
+    ) -> Result<Option<OAuthClient>> {
+        let invalid_client =
+            || ServiceError::oauth(OAUTH_ERROR_INVALID_CLIENT, "client authentication failed");
+
+        if let Some(assertion) = &req.client_assertion {
+            if credentials.0.is_some() || req.client_secret.is_some() {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "use a single client authentication method",
+                ));
+            }
+
+            return Self::authenticate_assertion(mm, req, assertion)
+                .await
+                .map(Some);
+        }
+
+        let (client_id, secret) = match (&credentials.0, &req.client_secret) {
+            (Some(_), Some(_)) => {
+                return Err(ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "use a single client authentication method",
+                ))
+            }
+            (Some((id, secret)), None) => (Some(id.as_str()), Some(secret.as_str())),
+            (None, secret) => (req.client_id.as_deref(), secret.as_deref()),
+        };
+
+        let Some(client_id) = client_id else {
+            return match secret {
+                Some(_) => Err(invalid_client()),
+                None => Ok(None),
+            };
+        };
+
+        let oauth_client = OAuthClientRepository::find_by_client_id(Ctx::root_ctx(), mm, client_id)
+            .await?
+            .filter(|x| x.disabled_at.is_none())
+            .ok_or_else(invalid_client)?;
+
+        match (&oauth_client.client_secret_hash, secret) {
+            (Some(hash), Some(secret)) if constant_time_eq(&sha256_hex(secret), hash) => {}
+            (None, None) if oauth_client.is_public() => {}
+            _ => return Err(invalid_client()),
+        }
+
+        Ok(Some(oauth_client))
+    }
+
+    // private_key_jwt (RFC 7523 section 2.2), the `kid` header tells which client signed it.
+    async fn authenticate_assertion(
+        mm: &ModelManager,
+        req: &ClientAuthentication,
+        assertion: &str,
+    ) -> Result<OAuthClient> {
+        let invalid_client =
+            || ServiceError::oauth(OAUTH_ERROR_INVALID_CLIENT, "client authentication failed");
+
+        if req.client_assertion_type.as_deref() != Some(CLIENT_ASSERTION_TYPE_JWT_BEARER) {
+            return Err(ServiceError::oauth(
+                OAUTH_ERROR_INVALID_REQUEST,
+                "client_assertion_type must be urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+            ));
+        }
+
+        let kid = client_assertion::kid(assertion).ok_or_else(invalid_client)?;
+        let key = OAuthClientRepository::find_key_by_kid(Ctx::root_ctx(), mm, &kid)
+            .await?
+            .ok_or_else(invalid_client)?;
+
+        let oauth_client =
+            OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, key.oauth_client_id)
+                .await?
+                .filter(|x| x.disabled_at.is_none() && !x.is_public())
+                .ok_or_else(invalid_client)?;
+
+        if req
+            .client_id
+            .as_deref()
+            .is_some_and(|x| x != oauth_client.client_id)
+        {
+            return Err(invalid_client());
+        }
+
+        if !client_assertion::verify(
+            mm,
+            assertion,
+            &oauth_client.client_id,
+            &key.algorithm,
+            &key.public_key,
+            &token_url()?,
+        )
+        .await?
+        {
+            return Err(invalid_client());
+        }
+
+        Ok(oauth_client)
+    }
+
+    // the tokens of a grant belong to the login that approved it, unless it has ended since.
+    async fn continue_session(mm: &ModelManager, session_id: Option<i64>) -> Result<()> {
+        let Some(session_id) = session_id else {
+            return Ok(());
+        };
+
+        if SessionRepository::find_active(Ctx::root_ctx(), mm, session_id)
+            .await?
+            .is_none()
+        {
+            return Err(invalid_grant(
+                "the login that approved the request has ended",
+            ));
+        }
+
+        SessionService::extend(mm, session_id).await
+    }
+
+    // the user a grant was issued to, if it can still get tokens.
+    async fn active_user(
+        mm: &ModelManager,
+        user_id: i64,
+        organization_id: Option<i64>,
+    ) -> Result<User> {
+        let user = UserRepository::find_by_id(Ctx::root_ctx(), mm, user_id)
+            .await?
+            .ok_or_else(|| invalid_grant("the user no longer exists"))?;
+
+        UserService::ensure_can_login(&user).map_err(|_| invalid_grant("the user can't log in"))?;
+
+        if let Some(organization_id) = organization_id {
+            if OrganizationRepository::find_member(
+                Ctx::root_ctx().with_organization(Some(organization_id)),
+                mm,
+                user.id,
+            )
+            .await?
+            .is_none()
+            {
+                return Err(invalid_grant("the user left the organization"));
+            }
+        }
+
+        Ok(user)
+    }
+
+    // Errors about the client or its redirect uri are never redirected, the user would be
+    // sent to a page the client doesn't own.
+    async fn client_and_redirect_uri(
+        mm: &ModelManager,
+        req: &AuthorizeQuery,
+    ) -> Result<(OAuthClient, String)> {
+        let client = OAuthClientRepository::find_by_client_id(Ctx::root_ctx(), mm, &req.client_id)
+            .await?
+            .filter(|x| x.disabled_at.is_none())
+            .ok_or_else(|| ServiceError::BadRequest(String::from("unknown client_id")))?;
+
+        let redirect_uri = match &req.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(uri) => uri.clone(),
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => {
+                return Err(ServiceError::BadRequest(String::from(
+                    "redirect_uri isn't registered for the client",
+                )))
+            }
+        };
+
+        Ok((client, redirect_uri))
+    }
+
+    // the scopes being asked for.
+    fn validate_request(
+        client: &OAuthClient,
+        req: &AuthorizeQuery,
+    ) -> core::result::Result<Vec<String>, AuthorizeError> {
+        if req.response_type != RESPONSE_TYPE_CODE {
+            return Err(AuthorizeError::new(
+                OAUTH_ERROR_UNSUPPORTED_RESPONSE_TYPE,
+                "response_type must be code",
+            ));
+        }
+
+        if !client.allows_grant(GRANT_TYPE_AUTHORIZATION_CODE) {
+            return Err(AuthorizeError::new(
+                OAUTH_ERROR_UNAUTHORIZED_CLIENT,
+                "the client can't use the authorization_code grant",
+            ));
+        }
+
+        match (&req.code_challenge, &req.code_challenge_method) {
+            // without a method the challenge would be a plain one, those aren't supported.
+            (Some(_), method) if method.as_deref() != Some(CODE_CHALLENGE_METHOD_S256) => {
+                return Err(AuthorizeError::new(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "code_challenge_method must be S256",
+                ))
+            }
+            (Some(challenge), _) if !CODE_CHALLENGE_LENGTH.contains(&challenge.len()) => {
+                return Err(AuthorizeError::new(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "code_challenge must be 43 to 128 characters long",
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(AuthorizeError::new(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "code_challenge is required with code_challenge_method",
+                ))
+            }
+            (None, None) if client.is_public() => {
+                return Err(AuthorizeError::new(
+                    OAUTH_ERROR_INVALID_REQUEST,
+                    "public clients must use PKCE",
+                ))
+            }
+            _ => {}
+        }
+
+        let scopes = match &req.scope {
+            Some(scope) => split_scope(scope),
+            None => client.allowed_scopes.clone(),
+        };
+
+        if scopes.is_empty() || !scopes.iter().all(|x| client.allowed_scopes.contains(x)) {
+            return Err(AuthorizeError::new(
+                OAUTH_ERROR_INVALID_SCOPE,
+                "scope isn't allowed for the client",
+            ));
+        }
+
+        Ok(scopes)
+    }
+
+    fn error_redirect(
+        redirect_uri: &str,
+        err: AuthorizeError,
+        state: Option<&str>,
+    ) -> Result<String> {
+        let mut params = vec![("error", err.error), ("error_description", err.description)];
+        if let Some(state) = state {
+            params.push(("state", state));
+        }
+
+        with_query(redirect_uri, &params)
+    }
+
+    fn error_response(
+        redirect_uri: &str,
+        err: AuthorizeError,
+        state: Option<&str>,
+    ) -> Result<AuthorizeResponseDTO> {
+        Ok(AuthorizeResponseDTO {
+            redirect_to: Some(Self::error_redirect(redirect_uri, err, state)?),
+            consent: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{client_credentials_audiences, client_credentials_scope, ServiceError};
+    use crate::service::constant::{OAUTH_ERROR_INVALID_SCOPE, OAUTH_ERROR_INVALID_TARGET};
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn oauth_error<T>(result: Result<T, ServiceError>) -> &'static str {
+        match result {
+            Err(ServiceError::OAuth { error, .. }) => error,
+            _ => panic!("expected an OAuth error"),
+        }
+    }
+
+    #[test]
+    fn client_credentials_scope_within_granted() {
+        let granted = strings(&["reports:read", "reports:write"]);
+
+        assert_eq!(
+            client_credentials_scope(None, &granted).unwrap(),
+            "reports:read reports:write"
+        );
+        assert_eq!(
+            client_credentials_scope(Some("reports:read reports:read"), &granted).unwrap(),
+            "reports:read"
+        );
+        assert_eq!(
+            oauth_error(client_credentials_scope(
+                Some("reports:read admin"),
+                &granted
+            )),
+            OAUTH_ERROR_INVALID_SCOPE
+        );
+    }
+
+    #[test]
+    fn client_credentials_audiences_registered() {
+        let registered = strings(&["https://a.example", "https://b.example"]);
+
+        assert_eq!(
+            client_credentials_audiences(None, &registered).unwrap(),
+            registered
+        );
+        assert_eq!(
+            client_credentials_audiences(Some("https://b.example"), &registered).unwrap(),
+            strings(&["https://b.example"])
+        );
+        assert_eq!(
+            oauth_error(client_credentials_audiences(
+                Some("https://c.example"),
+                &registered
+            )),
+            OAUTH_ERROR_INVALID_TARGET
+        );
+        assert_eq!(
+            oauth_error(client_credentials_audiences(Some(" "), &registered)),
+            OAUTH_ERROR_INVALID_TARGET
+        );
+        assert_eq!(
+            oauth_error(client_credentials_audiences(None, &[])),
+            OAUTH_ERROR_INVALID_TARGET
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use oauth2::url::Url;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::oauth::{AddOAuthClientKeyDTO, CreateOAuthClientDTO, UpdateOAuthClientDTO},
+        response::{
+            oauth::{CreatedOAuthClientDTO, OAuthClientDTO, OAuthClientKeyDTO},
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{oauth::OAuthClient, ModelManager},
+    pkg::util::{digest::sha256_hex, rand::generate_random_string},
+    repository::oauth_client::OAuthClientRepository,
+};
+
+use super::{
+    client_assertion,
+    constant::{
+        CLIENT_SECRET_PREFIX, CLIENT_TYPE_CONFIDENTIAL, CLIENT_TYPE_PUBLIC,
+        GRANT_TYPE_AUTHORIZATION_CODE, GRANT_TYPE_CLIENT_CREDENTIALS, GRANT_TYPE_DEVICE_CODE,
+        GRANT_TYPE_REFRESH_TOKEN, SCOPE_ACCOUNT, SCOPE_EMAIL, SCOPE_OPENID, SCOPE_PROFILE,
+    },
+    error::Result,
+    ServiceError,
+};
+
+const CLIENT_ID_LENGTH: usize = 24;
+const SECRET_LENGTH: usize = 48;
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_CLIENT_SCOPE_LENGTH: usize = 64;
+const MAX_AUDIENCE_LENGTH: usize = 255;
+const KID_LENGTH: usize = 16;
+
+// scopes a client can be allowed to ask the users for.
+pub const OAUTH_SCOPES: [&str; 4] = [SCOPE_ACCOUNT, SCOPE_OPENID, SCOPE_PROFILE, SCOPE_EMAIL];
+pub const GRANT_TYPES: [&str; 4] = [
+    GRANT_TYPE_AUTHORIZATION_CODE,
+    GRANT_TYPE_REFRESH_TOKEN,
+    GRANT_TYPE_CLIENT_CREDENTIALS,
+    GRANT_TYPE_DEVICE_CODE,
+];
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("client not found"))
+}
+
+fn generate_secret() -> String {
+    format!(
+        "{}{}",
+        CLIENT_SECRET_PREFIX,
+        generate_random_string(SECRET_LENGTH)
+    )
+}
+
+// The registry of the applications using the authorization server, kept by the administrators.
+#[derive(Debug, Clone)]
+pub struct OAuthClientService {}
+
+impl OAuthClientService {
+    // the secret of confidential clients is only part of this response.
+    pub async fn create(
+        mm: &ModelManager,
+        req: &CreateOAuthClientDTO,
+    ) -> Result<BaseResponse<CreatedOAuthClientDTO>> {
+        if req.client_type != CLIENT_TYPE_CONFIDENTIAL && req.client_type != CLIENT_TYPE_PUBLIC {
+            return Err(ServiceError::BadRequest(format!(
+                "client_type must be {CLIENT_TYPE_CONFIDENTIAL} or {CLIENT_TYPE_PUBLIC}"
+            )));
+        }
+
+        let grant_types = req.grant_types.clone().unwrap_or_else(|| {
+            vec![
+                GRANT_TYPE_AUTHORIZATION_CODE.to_string(),
+                GRANT_TYPE_REFRESH_TOKEN.to_string(),
+            ]
+        });
+
+        Self::validate_name(&req.name)?;
+        Self::validate_grants(
+            &req.client_type,
+            &grant_types,
+            &req.redirect_uris,
+            &req.allowed_scopes,
+            &req.client_scopes,
+            &req.audiences,
+        )?;
+
+        let client_secret = (req.client_type == CLIENT_TYPE_CONFIDENTIAL).then(generate_secret);
+
+        let client = OAuthClientRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            &generate_random_string(CLIENT_ID_LENGTH),
+            client_secret.as_deref().map(sha256_hex).as_deref(),
+            req,
+            &grant_types,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(
+            201,
+            CreatedOAuthClientDTO {
+                client: client.into(),
+                client_secret,
+            },
+        ))
+    }
+
+    pub async fn list(mm: &ModelManager) -> Result<Vec<OAuthClientDTO>> {
+        let clients = OAuthClientRepository::list(Ctx::root_ctx(), mm).await?;
+
+        Ok(clients.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn get(mm: &ModelManager, id: i64) -> Result<OAuthClientDTO> {
+        Ok(Self::find(mm, id).await?.into())
+    }
+
+    pub async fn update(
+        mm: &ModelManager,
+        id: i64,
+        req: &UpdateOAuthClientDTO,
+    ) -> Result<OAuthClientDTO> {
+        if let Some(name) = &req.name {
+            Self::validate_name(name)?;
+        }
+
+        // what the grants need depends on the other fields, checked as they end up.
+        let client = Self::find(mm, id).await?;
+        Self::validate_grants(
+            &client.client_type,
+            req.grant_types.as_ref().unwrap_or(&client.grant_types),
+            req.redirect_uris.as_ref().unwrap_or(&client.redirect_uris),
+            req.allowed_scopes
+                .as_ref()
+                .unwrap_or(&client.allowed_scopes),
+            req.client_scopes.as_ref().unwrap_or(&client.client_scopes),
+            req.audiences.as_ref().unwrap_or(&client.audiences),
+        )?;
+
+        let client = OAuthClientRepository::update(Ctx::root_ctx(), mm, id, req)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(client.into())
+    }
+
+    // the previous secret stops working right away.
+    pub async fn rotate_secret(
+        mm: &ModelManager,
+        id: i64,
+    ) -> Result<BaseResponse<CreatedOAuthClientDTO>> {
+        let client = Self::find(mm, id).await?;
+
+        if client.is_public() {
+            return Err(ServiceError::BadRequest(String::from(
+                "public clients have no secret",
+            )));
+        }
+
+        let client_secret = generate_secret();
+        let client =
+            OAuthClientRepository::set_secret(Ctx::root_ctx(), mm, id, &sha256_hex(&client_secret))
+                .await?
+                .ok_or_else(not_found)?;
+
+        Ok(BaseResponse::new(
+            200,
+            CreatedOAuthClientDTO {
+                client: client.into(),
+                client_secret: Some(client_secret),
+            },
+        ))
+    }
+
+    // codes, consents and refresh tokens of the client go with it.
+    pub async fn delete(mm: &ModelManager, id: i64) -> Result<BaseResponse<MessageResponse>> {
+        if !OAuthClientRepository::delete(Ctx::root_ctx(), mm, id).await? {
+            return Err(not_found());
+        }
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("client deleted"),
+        ))
+    }
+
+    pub async fn list_keys(mm: &ModelManager, id: i64) -> Result<Vec<OAuthClientKeyDTO>> {
+        Self::find(mm, id).await?;
+
+        let keys = OAuthClientRepository::list_keys(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(keys.into_iter().map(Into::into).collect())
+    }
+
+    // private_key_jwt, the assertions are signed with the private key kept by the client.
+    pub async fn add_key(
+        mm: &ModelManager,
+        id: i64,
+        req: &AddOAuthClientKeyDTO,
+    ) -> Result<BaseResponse<OAuthClientKeyDTO>> {
+        if Self::find(mm, id).await?.is_public() {
+            return Err(ServiceError::BadRequest(String::from(
+                "public clients don't authenticate",
+            )));
+        }
+
+        client_assertion::validate_key(&req.algorithm, &req.public_key)?;
+
+        let key = OAuthClientRepository::create_key(
+            Ctx::root_ctx(),
+            mm,
+            id,
+            &generate_random_string(KID_LENGTH),
+            &req.algorithm,
+            &req.public_key,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(201, key.into()))
+    }
+
+    pub async fn delete_key(
+        mm: &ModelManager,
+        id: i64,
+        key_id: i64,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        if !OAuthClientRepository::delete_key(Ctx::root_ctx(), mm, id, key_id).await? {
+            return Err(ServiceError::NotFound(String::from("key not found")));
+        }
+
+        Ok(BaseResponse::new(200, MessageResponse::new("key deleted")))
+    }
+
+    async fn find(mm: &ModelManager, id: i64) -> Result<OAuthClient> {
+        let client = OAuthClientRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(client)
+    }
+
+    fn validate_name(name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ServiceError::BadRequest(format!(
+                "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+            )));
+        }
+
+        Ok(())
+    }
+
+    // the authorization_code grant needs somewhere to send the users back to and scopes
+    // to ask them for, client_credentials a confidential client and the APIs it calls.
+    fn validate_grants(
+        client_type: &str,
+        grant_types: &[String],
+        redirect_uris: &[String],
+        allowed_scopes: &[String],
+        client_scopes: &[String],
+        audiences: &[String],
+    ) -> Result<()> {
+        Self::validate_grant_types(grant_types)?;
+        Self::validate_redirect_uris(redirect_uris)?;
+        Self::validate_scopes(allowed_scopes)?;
+        Self::validate_client_scopes(client_scopes)?;
+        Self::validate_audiences(audiences)?;
+
+        let allows = |grant_type: &str| grant_types.iter().any(|x| x == grant_type);
+
+        if allows(GRANT_TYPE_AUTHORIZATION_CODE) && redirect_uris.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "at least one redirect uri is required",
+            )));
+        }
+
+        // the device grant asks the users as well, on another device.
+        if (allows(GRANT_TYPE_AUTHORIZATION_CODE) || allows(GRANT_TYPE_DEVICE_CODE))
+            && allowed_scopes.is_empty()
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "at least one scope is required",
+            )));
+        }
+
+        if allows(GRANT_TYPE_CLIENT_CREDENTIALS) {
+            if client_type == CLIENT_TYPE_PUBLIC {
+                return Err(ServiceError::BadRequest(format!(
+                    "public clients can't use the {GRANT_TYPE_CLIENT_CREDENTIALS} grant"
+                )));
+            }
+            if audiences.is_empty() {
+                return Err(ServiceError::BadRequest(String::from(
+                    "at least one audience is required",
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // absolute, without a fragment (RFC 6749 section 3.1.2). Custom schemes are fine
+    // for native applications.
+    fn validate_redirect_uris(redirect_uris: &[String]) -> Result<()> {
+        if let Some(invalid) = redirect_uris
+            .iter()
+            .find(|x| Url::parse(x).map_or(true, |url| url.fragment().is_some()))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "redirect uri {invalid} must be an absolute uri without a fragment"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_scopes(scopes: &[String]) -> Result<()> {
+        if let Some(unknown) = scopes.iter().find(|x| !OAUTH_SCOPES.contains(&x.as_str())) {
+            return Err(ServiceError::BadRequest(format!("unknown scope {unknown}")));
+        }
+
+        Ok(())
+    }
+
+    // defined by the APIs the client calls, any scope-token of RFC 6749 section 3.3.
+    fn validate_client_scopes(client_scopes: &[String]) -> Result<()> {
+        if let Some(invalid) = client_scopes.iter().find(|x| {
+            x.is_empty()
+                || x.len() > MAX_CLIENT_SCOPE_LENGTH
+                || !x
+                    .bytes()
+                    .all(|c| c.is_ascii_graphic() && c != b'"' && c != b'\\')
+        }) {
+            return Err(ServiceError::BadRequest(format!(
+                "client scope {invalid} must be at most {MAX_CLIENT_SCOPE_LENGTH} printable characters without spaces, quotes or backslashes"
+            )));
+        }
+
+        // these are about a user, there's none behind the client's own tokens.
+        if let Some(reserved) = client_scopes
+            .iter()
+            .find(|x| OAUTH_SCOPES.contains(&x.as_str()))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "client scope {reserved} is reserved for the users"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_audiences(audiences: &[String]) -> Result<()> {
+        if let Some(invalid) = audiences.iter().find(|x| {
+            x.is_empty()
+                || x.chars().count() > MAX_AUDIENCE_LENGTH
+                || x.chars().any(char::is_whitespace)
+        }) {
+            return Err(ServiceError::BadRequest(format!(
+                "audience {invalid} must be at most {MAX_AUDIENCE_LENGTH} characters without spaces"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_grant_types(grant_types: &[String]) -> Result<()> {
+        if let Some(unknown) = grant_types
+            .iter()
+            .find(|x| !GRANT_TYPES.contains(&x.as_str()))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "unsupported grant type {unknown}"
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::env;
+
+use anyhow::Context;
+use jsonwebtoken::get_current_timestamp;
+
+use crate::{
+    ctx::Ctx,
+    http::response::oidc::{DiscoveryDTO, JwksDTO, UserInfoDTO},
+    model::{
+        user::{IdTokenClaims, User},
+        ModelManager,
+    },
+    repository::user::UserRepository,
+};
+
+use super::{
+    client_assertion,
+    constant::{CODE_CHALLENGE_METHOD_S256, RESPONSE_TYPE_CODE, SCOPE_EMAIL, SCOPE_PROFILE},
+    error::Result,
+    oauth_client::{GRANT_TYPES, OAUTH_SCOPES},
+    signing_key::SigningKeyService,
+    token::{access_token_ttl_seconds, Authentication},
+};
+
+// the issuer identifier, the endpoints are found under it.
+pub fn issuer() -> anyhow::Result<String> {
+    env::var("BASE_URL").context("Missing BASE_URL env var")
+}
+
+fn has_scope(scope: &str, name: &str) -> bool {
+    scope.split_whitespace().any(|x| x == name)
+}
+
+// OpenID Connect on top of the authorization server: ID tokens for the clients asking
+// for the `openid` scope, the userinfo endpoint and the metadata of the provider.
+#[derive(Debug, Clone)]
+pub struct OidcService {}
+
+impl OidcService {
+    pub fn discovery() -> Result<DiscoveryDTO> {
+        let issuer = issuer()?;
+
+        Ok(DiscoveryDTO {
+            authorization_endpoint: format!("{}/authorize", issuer),
+            token_endpoint: format!("{}/token", issuer),
+            device_authorization_endpoint: format!("{}/device/code", issuer),
+            introspection_endpoint: format!("{}/introspect", issuer),
+            revocation_endpoint: format!("{}/revoke", issuer),
+            userinfo_endpoint: format!("{}/userinfo", issuer),
+            jwks_uri: format!("{}/jwks.json", issuer),
+            issuer,
+            scopes_supported: OAUTH_SCOPES.to_vec(),
+            response_types_supported: vec![RESPONSE_TYPE_CODE],
+            grant_types_supported: GRANT_TYPES.to_vec(),
+            subject_types_supported: vec!["public"],
+            id_token_signing_alg_values_supported: vec![SigningKeyService::algorithm_name()],
+            token_endpoint_auth_methods_supported: vec![
+                "client_secret_basic",
+                "client_secret_post",
+                "private_key_jwt",
+                "none",
+            ],
+            token_endpoint_auth_signing_alg_values_supported: client_assertion::algorithm_names(),
+            code_challenge_methods_supported: vec![CODE_CHALLENGE_METHOD_S256],
+            claims_supported: vec![
+                "iss",
+                "sub",
+                "aud",
+                "azp",
+                "exp",
+                "iat",
+                "auth_time",
+                "nonce",
+                "amr",
+                "name",
+                "updated_at",
+                "email",
+                "email_verified",
+            ],
+        })
+    }
+
+    // the keys signing the ID and access tokens.
+    pub async fn jwks(mm: &ModelManager) -> Result<JwksDTO> {
+        SigningKeyService::jwks(mm).await
+    }
+
+    // `nonce` comes from the authorization request, a refreshed ID token goes without.
+    pub async fn issue_id_token(
+        mm: &ModelManager,
+        user: &User,
+        client_id: &str,
+        scope: &str,
+        authentication: &Authentication,
+        nonce: Option<&str>,
+    ) -> Result<String> {
+        let now = get_current_timestamp();
+
+        let claims = IdTokenClaims {
+            iss: issuer()?,
+            aud: client_id.to_string(),
+            azp: client_id.to_string(),
+            iat: now as usize,
+            exp: (now + access_token_ttl_seconds()) as usize,
+            auth_time: authentication.time.unix_timestamp() as usize,
+            nonce: nonce.map(String::from),
+            amr: authentication.methods.clone(),
+            user_info: Self::user_info(user, scope),
+        };
+
+        SigningKeyService::sign(mm, &claims).await
+    }
+
+    // the claims the access token's scopes give access to.
+    pub async fn userinfo(mm: &ModelManager, ctx: &Ctx) -> Result<UserInfoDTO> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        Ok(Self::user_info(&user, &ctx.scopes().join(" ")))
+    }
+
+    fn user_info(user: &User, scope: &str) -> UserInfoDTO {
+        let profile = has_scope(scope, SCOPE_PROFILE);
+        let email = has_scope(scope, SCOPE_EMAIL);
+
+        UserInfoDTO {
+            sub: user.id.to_string(),
+            name: profile.then(|| user.name.clone()),
+            updated_at: profile
+                .then(|| user.modified_at.unwrap_or(user.created_at).unix_timestamp()),
+            email: email.then(|| user.email.clone()),
+            email_verified: email.then(|| user.email_verified_at.is_some()),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use std::env;
+
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{
+            client::ClientMeta,
+            organization::{CreateOrganizationDTO, SetMemberDTO},
+        },
+        response::{
+            organization::{MemberDTO, OrganizationDTO},
+            user::UserDTO,
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{
+        organization::{Member, Organization},
+        ModelManager,
+    },
+    repository::{organization::OrganizationRepository, user::UserRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_MEMBERSHIP_REMOVED, AUDIT_EVENT_MEMBERSHIP_UPDATED, ORG_ROLE_ADMIN,
+        ORG_ROLE_MEMBER, ORG_ROLE_OWNER, SCOPE_ACCOUNT,
+    },
+    error::Result,
+    token::{Authentication, TokenService},
+    user::UserService,
+    ServiceError,
+};
+
+const MAX_NAME_LENGTH: usize = 255;
+const MIN_SLUG_LENGTH: usize = 3;
+const MAX_SLUG_LENGTH: usize = 64;
+
+// TENANT_SCOPED_EMAILS=true lets signup and login pick an organization, the accounts created
+// that way belong to it and the same email can be registered once per organization.
+pub fn tenant_scoped_emails() -> bool {
+    env::var("TENANT_SCOPED_EMAILS")
+        .map(|x| x == "true")
+        .unwrap_or(false)
+}
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("organization not found"))
+}
+
+fn can_manage(role: &str) -> bool {
+    role == ORG_ROLE_OWNER || role == ORG_ROLE_ADMIN
+}
+
+fn is_valid_slug(slug: &str) -> bool {
+    (MIN_SLUG_LENGTH..=MAX_SLUG_LENGTH).contains(&slug.len())
+        && slug
+            .chars()
+            .all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == '-')
+}
+
+#[derive(Debug, Clone)]
+pub struct OrganizationService {}
+
+impl OrganizationService {
+    // the context signup and login look the account up in, the organization is ignored
+    // unless emails are scoped to tenants.
+    pub async fn tenant_ctx(mm: &ModelManager, organization: Option<&str>) -> Result<Ctx> {
+        let Some(slug) = organization.filter(|_| tenant_scoped_emails()) else {
+            return Ok(Ctx::root_ctx());
+        };
+
+        let organization = OrganizationRepository::find_by_slug(Ctx::root_ctx(), mm, slug)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(Ctx::root_ctx().with_organization(Some(organization.id)))
+    }
+
+    // the creator becomes the owner.
+    pub async fn create(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &CreateOrganizationDTO,
+    ) -> Result<BaseResponse<OrganizationDTO>> {
+        let name = req.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ServiceError::BadRequest(format!(
+                "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+            )));
+        }
+
+        let slug = req.slug.trim();
+        if !is_valid_slug(slug) {
+            return Err(ServiceError::BadRequest(format!(
+                "slug must be {MIN_SLUG_LENGTH} to {MAX_SLUG_LENGTH} lowercase letters, digits or dashes"
+            )));
+        }
+
+        if OrganizationRepository::find_by_slug(Ctx::root_ctx(), mm, slug)
+            .await?
+            .is_some()
+        {
+            return Err(ServiceError::ObjectConflict(format!(
+                "organization {slug} already exists"
+            )));
+        }
+
+        let organization = OrganizationRepository::create(Ctx::root_ctx(), mm, name, slug).await?;
+
+        OrganizationRepository::upsert_member(
+            Ctx::root_ctx().with_organization(Some(organization.id)),
+            mm,
+            ctx.user_id() as i64,
+            ORG_ROLE_OWNER,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(
+            201,
+            organization.into_dto(Some(ORG_ROLE_OWNER.to_string())),
+        ))
+    }
+
+    pub async fn list(mm: &ModelManager, ctx: &Ctx) -> Result<Vec<OrganizationDTO>> {
+        let memberships =
+            OrganizationRepository::list_for_user(Ctx::root_ctx(), mm, ctx.user_id() as i64)
+                .await?;
+
+        Ok(memberships
+            .into_iter()
+            .map(|x| x.organization.into_dto(Some(x.role)))
+            .collect())
+    }
+
+    pub async fn get(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<OrganizationDTO> {
+        let (organization, _, member) = Self::membership(mm, ctx, id).await?;
+
+        Ok(organization.into_dto(Some(member.role)))
+    }
+
+    pub async fn members(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<Vec<MemberDTO>> {
+        let (_, tenant, _) = Self::membership(mm, ctx, id).await?;
+        let members = OrganizationRepository::list_members(tenant, mm).await?;
+
+        Ok(members.into_iter().map(Into::into).collect())
+    }
+
+    // adds a user or changes their role, for owners and admins. Only owners hand out
+    // or take away the owner role.
+    pub async fn set_member(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        user_id: i64,
+        req: &SetMemberDTO,
+        client: &ClientMeta,
+    ) -> Result<MemberDTO> {
+        let (_, tenant, actor) = Self::require_manager(mm, ctx, id).await?;
+        Self::validate_role(&req.role)?;
+
+        UserRepository::find_by_id(Ctx::root_ctx(), mm, user_id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(String::from("user not found")))?;
+
+        let current = OrganizationRepository::find_member(tenant.clone(), mm, user_id).await?;
+        let current_role = current.as_ref().map(|x| x.role.as_str());
+
+        if (req.role == ORG_ROLE_OWNER || current_role == Some(ORG_ROLE_OWNER))
+            && actor.role != ORG_ROLE_OWNER
+        {
+            return Err(ServiceError::ForbiddenWithMessage(String::from(
+                "only owners can change who owns the organization",
+            )));
+        }
+
+        if current_role == Some(ORG_ROLE_OWNER) && req.role != ORG_ROLE_OWNER {
+            Self::ensure_other_owner(mm, &tenant).await?;
+        }
+
+        OrganizationRepository::upsert_member(tenant.clone(), mm, user_id, &req.role).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_MEMBERSHIP_UPDATED,
+            client,
+            json!({ "actor": ctx.user_id(), "organization": id, "role": req.role }),
+        )
+        .await;
+
+        let member = OrganizationRepository::find_member(tenant, mm, user_id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(String::from("user not found")))?;
+
+        Ok(member.into())
+    }
+
+    // members can leave on their own, owners and admins can remove the others.
+    pub async fn remove_member(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        user_id: i64,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let (_, tenant, actor) = Self::membership(mm, ctx, id).await?;
+        let is_self = user_id == actor.user_id;
+
+        if !is_self && !can_manage(&actor.role) {
+            return Err(ServiceError::Forbidden);
+        }
+
+        let member = OrganizationRepository::find_member(tenant.clone(), mm, user_id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(String::from("member not found")))?;
+
+        if member.role == ORG_ROLE_OWNER {
+            if !is_self && actor.role != ORG_ROLE_OWNER {
+                return Err(ServiceError::ForbiddenWithMessage(String::from(
+                    "only owners can remove an owner",
+                )));
+            }
+
+            Self::ensure_other_owner(mm, &tenant).await?;
+        }
+
+        // accounts created for the tenant can't log in anywhere else.
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, user_id).await?;
+        if user.organization_id == Some(id) {
+            return Err(ServiceError::BadRequest(String::from(
+                "accounts of the organization can't be removed from it",
+            )));
+        }
+
+        OrganizationRepository::remove_member(tenant, mm, user_id).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_MEMBERSHIP_REMOVED,
+            client,
+            json!({ "actor": ctx.user_id(), "organization": id }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("member removed from the organization"),
+        ))
+    }
+
+    // a new access token acting in the organization.
+    pub async fn switch(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<UserDTO> {
+        Self::membership(mm, ctx, id).await?;
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+        UserService::ensure_can_login(&user)?;
+
+        // still the same login, the organization doesn't change how the user authenticated.
+        let token = TokenService::issue_access_token(
+            mm,
+            &user,
+            SCOPE_ACCOUNT,
+            Some(id),
+            &Authentication::from_ctx(ctx),
+        )
+        .await?;
+
+        Ok(user.into_dto(Some(token), None, None))
+    }
+
+    pub fn validate_role(role: &str) -> Result<()> {
+        if ![ORG_ROLE_OWNER, ORG_ROLE_ADMIN, ORG_ROLE_MEMBER].contains(&role) {
+            return Err(ServiceError::BadRequest(format!(
+                "role must be one of {ORG_ROLE_OWNER}, {ORG_ROLE_ADMIN} or {ORG_ROLE_MEMBER}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    // the current user has to be an owner or an admin of the organization.
+    pub async fn require_manager(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+    ) -> Result<(Organization, Ctx, Member)> {
+        let membership = Self::membership(mm, ctx, id).await?;
+
+        if !can_manage(&membership.2.role) {
+            return Err(ServiceError::Forbidden);
+        }
+
+        Ok(membership)
+    }
+
+    // organizations the user isn't a member of are reported as missing.
+    async fn membership(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+    ) -> Result<(Organization, Ctx, Member)> {
+        let organization = OrganizationRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        let tenant = Ctx::root_ctx().with_organization(Some(id));
+        let member = OrganizationRepository::find_member(tenant.clone(), mm, ctx.user_id() as i64)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok((organization, tenant, member))
+    }
+
+    async fn ensure_other_owner(mm: &ModelManager, tenant: &Ctx) -> Result<()> {
+        let owners =
+            OrganizationRepository::count_members_with_role(tenant.clone(), mm, ORG_ROLE_OWNER)
+                .await?;
+
+        if owners <= 1 {
+            return Err(ServiceError::BadRequest(String::from(
+                "the organization needs at least one owner",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{can_manage, is_valid_slug, OrganizationService};
+    use crate::service::constant::{ORG_ROLE_ADMIN, ORG_ROLE_MEMBER, ORG_ROLE_OWNER};
+
+    #[test]
+    fn managers() {
+        assert!(can_manage(ORG_ROLE_OWNER));
+        assert!(can_manage(ORG_ROLE_ADMIN));
+        assert!(!can_manage(ORG_ROLE_MEMBER));
+    }
+
+    #[test]
+    fn validate_role() {
+        for role in [ORG_ROLE_OWNER, ORG_ROLE_ADMIN, ORG_ROLE_MEMBER] {
+            assert!(OrganizationService::validate_role(role).is_ok());
+        }
+        assert!(OrganizationService::validate_role("superuser").is_err());
+    }
+
+    #[test]
+    fn slugs() {
+        assert!(is_valid_slug("acme-2"));
+        assert!(!is_valid_slug("ac"));
+        assert!(!is_valid_slug("Acme"));
+        assert!(!is_valid_slug("acme corp"));
+        assert!(!is_valid_slug(&"a".repeat(65)));
+    }
+}
@@ -0,0 +1,123 @@
+use std::env;
+
+use anyhow::Context;
+use bcrypt::{hash, DEFAULT_COST};
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{client::ClientMeta, user::ResetPasswordDTO},
+        response::{BaseResponse, MessageResponse},
+    },
+    model::{user::User, ModelManager},
+    pkg::{
+        mail::{self, Mail},
+        util::{digest::sha256_hex, rand::generate_random_string},
+    },
+    repository::{user::UserRepository, user_token::UserTokenRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{AUDIT_EVENT_PASSWORD_RESET, TOKEN_PURPOSE_PASSWORD_RESET},
+    error::Result,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 32;
+const DEFAULT_TTL_MINUTES: i64 = 60;
+
+fn ttl_seconds() -> i64 {
+    env::var("PASSWORD_RESET_TTL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_MINUTES)
+        * 60
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordResetService {}
+
+impl PasswordResetService {
+    // previous links stop working once a new one is sent.
+    pub async fn send_reset_link(mm: &ModelManager, user: &User) -> Result<()> {
+        let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+        let token = generate_random_string(TOKEN_LENGTH);
+
+        UserTokenRepository::revoke_for_user(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_PASSWORD_RESET,
+        )
+        .await?;
+
+        UserTokenRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user.id,
+            TOKEN_PURPOSE_PASSWORD_RESET,
+            &sha256_hex(&token),
+            ttl_seconds(),
+            None,
+        )
+        .await?;
+
+        mail::send_in_background(Mail::new(
+            &user.email,
+            "Reset your password",
+            format!(
+                "Hi {},\n\nchoose a new password for your account by following the link below:\n\n{}/password/reset?token={}\n\nThe link expires in {} minutes.",
+                user.name,
+                base_url,
+                token,
+                ttl_seconds() / 60
+            ),
+        ));
+
+        Ok(())
+    }
+
+    // sets the new password and signs the user out everywhere.
+    pub async fn reset(
+        mm: &ModelManager,
+        req: &ResetPasswordDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        if req.password.is_empty() {
+            return Err(ServiceError::BadRequest(String::from(
+                "password can't be empty",
+            )));
+        }
+
+        let record = UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_PASSWORD_RESET,
+            &sha256_hex(&req.token),
+        )
+        .await?
+        .ok_or_else(|| {
+            ServiceError::BadRequest(String::from("reset link is invalid or has expired"))
+        })?;
+
+        let hash_password = hash(req.password.as_bytes(), DEFAULT_COST)?;
+
+        UserRepository::reset_password(Ctx::root_ctx(), mm, record.user_id, &hash_password).await?;
+
+        AuditService::record(
+            mm,
+            record.user_id,
+            AUDIT_EVENT_PASSWORD_RESET,
+            client,
+            json!({}),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("password updated, you can login with the new one"),
+        ))
+    }
+}
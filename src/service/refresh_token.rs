@@ -0,0 +1,166 @@
+use std::env;
+
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::request::client::ClientMeta,
+    model::{refresh_token::RefreshToken, user::User, ModelManager},
+    pkg::util::{digest::sha256_hex, rand::generate_random_string},
+    repository::refresh_token::{NewRefreshToken, RefreshTokenRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{AUDIT_EVENT_REFRESH_TOKEN_REUSED, OAUTH_ERROR_INVALID_GRANT},
+    error::Result,
+    token::Authentication,
+    ServiceError,
+};
+
+const TOKEN_LENGTH: usize = 48;
+const FAMILY_ID_LENGTH: usize = 32;
+const DEFAULT_TTL_DAYS: i64 = 30;
+
+pub fn refresh_token_ttl_seconds() -> i64 {
+    env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TTL_DAYS)
+        * 24
+        * 60
+        * 60
+}
+
+fn invalid_grant() -> ServiceError {
+    ServiceError::oauth(
+        OAUTH_ERROR_INVALID_GRANT,
+        "refresh token is invalid, expired or revoked",
+    )
+}
+
+pub fn new_family_id() -> String {
+    generate_random_string(FAMILY_ID_LENGTH)
+}
+
+// Refresh tokens are opaque and single use: each exchange hands out a successor in the
+// same family. A token presented twice means it leaked, the whole family is revoked.
+#[derive(Debug, Clone)]
+pub struct RefreshTokenService {}
+
+impl RefreshTokenService {
+    // `client_id` is the OAuth client the token is for, `None` for the login endpoints.
+    pub async fn issue(
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: Option<i64>,
+        scope: &str,
+        organization_id: Option<i64>,
+        family_id: &str,
+        authentication: &Authentication,
+    ) -> Result<String> {
+        let token = generate_random_string(TOKEN_LENGTH);
+
+        RefreshTokenRepository::create(
+            Ctx::root_ctx().with_organization(organization_id),
+            mm,
+            NewRefreshToken {
+                token_hash: &sha256_hex(&token),
+                family_id,
+                user_id,
+                client_id,
+                scope,
+                auth_time: authentication.time,
+                amr: &authentication.methods,
+                session_id: authentication.session_id,
+                ttl_seconds: refresh_token_ttl_seconds(),
+            },
+        )
+        .await?;
+
+        Ok(token)
+    }
+
+    // starts a new family, for the tokens handed out along with a login.
+    pub async fn issue_for_login(
+        mm: &ModelManager,
+        user: &User,
+        scope: &str,
+        authentication: &Authentication,
+    ) -> Result<String> {
+        Self::issue(
+            mm,
+            user.id,
+            None,
+            scope,
+            user.organization_id,
+            &new_family_id(),
+            authentication,
+        )
+        .await
+    }
+
+    // The token being exchanged, if it was issued to `client_id`. Reusing a token
+    // revokes its family.
+    pub async fn consume(
+        mm: &ModelManager,
+        token: &str,
+        client_id: Option<i64>,
+        client: &ClientMeta,
+    ) -> Result<RefreshToken> {
+        let token_hash = sha256_hex(token);
+
+        let Some(found) = RefreshTokenRepository::find(Ctx::root_ctx(), mm, &token_hash).await?
+        else {
+            return Err(invalid_grant());
+        };
+
+        if found.client_id != client_id {
+            return Err(invalid_grant());
+        }
+
+        if let Some(consumed) =
+            RefreshTokenRepository::consume(Ctx::root_ctx(), mm, &token_hash).await?
+        {
+            return Ok(consumed);
+        }
+
+        if found.used_at.is_some() && found.revoked_at.is_none() {
+            RefreshTokenRepository::revoke_family(Ctx::root_ctx(), mm, &found.family_id).await?;
+
+            AuditService::record(
+                mm,
+                found.user_id,
+                AUDIT_EVENT_REFRESH_TOKEN_REUSED,
+                client,
+                json!({ "client": found.client_id }),
+            )
+            .await;
+        }
+
+        Err(invalid_grant())
+    }
+
+    pub async fn revoke_family(mm: &ModelManager, family_id: &str) -> Result<()> {
+        RefreshTokenRepository::revoke_family(Ctx::root_ctx(), mm, family_id).await?;
+
+        Ok(())
+    }
+
+    // every token of the user, or only the ones given to the client.
+    pub async fn revoke_for_user(
+        mm: &ModelManager,
+        user_id: i64,
+        client_id: Option<i64>,
+    ) -> Result<()> {
+        RefreshTokenRepository::revoke_for_user(Ctx::root_ctx(), mm, user_id, client_id).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(mm: &ModelManager) -> Result<u64> {
+        let deleted = RefreshTokenRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+}
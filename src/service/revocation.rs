@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::request::oauth::{BasicCredentials, RevocationRequest},
+    model::{refresh_token::RefreshToken, user::CustomTokenClaims, ModelManager},
+    pkg::util::digest::sha256_hex,
+    repository::{refresh_token::RefreshTokenRepository, revoked_token::RevokedTokenRepository},
+};
+
+use super::{
+    constant::OAUTH_ERROR_INVALID_CLIENT, error::Result, oauth::OAuthService,
+    refresh_token::RefreshTokenService, signing_key::SigningKeyService, ServiceError,
+};
+
+// the revoked tokens are loaded again this often, the ones revoked through another
+// instance are accepted here until then.
+const RELOAD_SECONDS: u64 = 5;
+
+// The `jti`s of the revoked tokens with their `exp`, kept in memory so `jwt_auth`
+// doesn't query them on every request. An entry stops counting once its token expired.
+struct Denylist {
+    loaded_at: Instant,
+    jtis: HashMap<String, i64>,
+}
+
+impl Denylist {
+    fn is_revoked(&self, jti: &str, now: i64) -> bool {
+        self.jtis.get(jti).is_some_and(|exp| *exp > now)
+    }
+}
+
+static DENYLIST: RwLock<Option<Denylist>> = RwLock::new(None);
+
+// `None` when the denylist has to be loaded first.
+fn cached_is_revoked(jti: &str, now: i64) -> Option<bool> {
+    let denylist = DENYLIST.read().ok()?;
+
+    denylist
+        .as_ref()
+        .filter(|x| x.loaded_at.elapsed() < Duration::from_secs(RELOAD_SECONDS))
+        .map(|x| x.is_revoked(jti, now))
+}
+
+// the `jti` to deny, when the access token was issued to the client.
+fn revocable_jti<'a>(claims: &'a CustomTokenClaims, client_id: &str) -> Option<&'a str> {
+    match claims.client_id.as_deref() == Some(client_id) {
+        true => claims.jti.as_deref(),
+        false => None,
+    }
+}
+
+// the family to revoke, when the refresh token was issued to the client.
+fn revocable_family(refresh_token: &RefreshToken, oauth_client_id: i64) -> Option<&str> {
+    match refresh_token.client_id == Some(oauth_client_id) {
+        true => Some(&refresh_token.family_id),
+        false => None,
+    }
+}
+
+// Access tokens are stateless, the revoked ones are denied by their `jti` until they
+// expire. Refresh tokens are revoked with their family.
+#[derive(Debug, Clone)]
+pub struct RevocationService {}
+
+impl RevocationService {
+    pub async fn revoke_access_token(mm: &ModelManager, jti: &str, expires_at: u64) -> Result<()> {
+        let expires_at = OffsetDateTime::from_unix_timestamp(expires_at as i64)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc());
+
+        RevokedTokenRepository::create(Ctx::root_ctx(), mm, jti, expires_at).await?;
+
+        // denied right away by this instance, the others catch up on their next reload.
+        if let Ok(mut denylist) = DENYLIST.write() {
+            if let Some(denylist) = denylist.as_mut() {
+                denylist
+                    .jtis
+                    .insert(jti.to_string(), expires_at.unix_timestamp());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn is_revoked(mm: &ModelManager, jti: &str) -> Result<bool> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if let Some(revoked) = cached_is_revoked(jti, now) {
+            return Ok(revoked);
+        }
+
+        let mut denylist = Denylist {
+            loaded_at: Instant::now(),
+            jtis: RevokedTokenRepository::list_active(Ctx::root_ctx(), mm)
+                .await?
+                .into_iter()
+                .map(|(jti, expires_at)| (jti, expires_at.unix_timestamp()))
+                .collect(),
+        };
+        let revoked = denylist.is_revoked(jti, now);
+
+        if let Ok(mut cached) = DENYLIST.write() {
+            // revoked here while loading, tokens are never unrevoked.
+            for (jti, exp) in cached.take().into_iter().flat_map(|x| x.jtis) {
+                if exp > now {
+                    denylist.jtis.entry(jti).or_insert(exp);
+                }
+            }
+
+            *cached = Some(denylist);
+        }
+
+        Ok(revoked)
+    }
+
+    // RFC 7009: a client revokes the tokens issued to it. Unknown, invalid and other
+    // clients' tokens are ignored, the client can't tell the difference.
+    pub async fn revoke(
+        mm: &ModelManager,
+        req: &RevocationRequest,
+        credentials: &BasicCredentials,
+    ) -> Result<()> {
+        let oauth_client = OAuthService::authenticate_client(mm, &req.client, credentials)
+            .await?
+            .ok_or_else(|| {
+                ServiceError::oauth(
+                    OAUTH_ERROR_INVALID_CLIENT,
+                    "client authentication is required",
+                )
+            })?;
+
+        // access tokens are JWTs, refresh tokens opaque.
+        if req.token.contains('.') {
+            let claims =
+                SigningKeyService::verify::<CustomTokenClaims>(mm, &req.token, None).await?;
+
+            if let Some(claims) = claims {
+                if let Some(jti) = revocable_jti(&claims, &oauth_client.client_id) {
+                    Self::revoke_access_token(mm, jti, claims.exp as u64).await?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        if let Some(refresh_token) =
+            RefreshTokenRepository::find(Ctx::root_ctx(), mm, &sha256_hex(&req.token)).await?
+        {
+            if let Some(family_id) = revocable_family(&refresh_token, oauth_client.id) {
+                RefreshTokenService::revoke_family(mm, family_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(mm: &ModelManager) -> Result<u64> {
+        let deleted = RevokedTokenRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, time::Instant};
+
+    use serde_json::json;
+    use sqlx::types::time::OffsetDateTime;
+
+    use super::{revocable_family, revocable_jti, Denylist};
+    use crate::model::{refresh_token::RefreshToken, user::CustomTokenClaims};
+
+    fn claims(client_id: Option<&str>) -> CustomTokenClaims {
+        serde_json::from_value(json!({
+            "sub": 1,
+            "iat": 0,
+            "exp": 0,
+            "jti": "jti",
+            "client_id": client_id,
+        }))
+        .unwrap()
+    }
+
+    fn refresh_token(client_id: Option<i64>) -> RefreshToken {
+        let now = OffsetDateTime::now_utc();
+
+        RefreshToken {
+            id: 1,
+            token_hash: String::new(),
+            family_id: String::from("family"),
+            user_id: 1,
+            client_id,
+            scope: String::from("openid"),
+            organization_id: None,
+            auth_time: None,
+            amr: Vec::new(),
+            session_id: None,
+            created_at: now,
+            expires_at: now,
+            used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn access_tokens_of_the_client() {
+        assert_eq!(
+            revocable_jti(&claims(Some("client")), "client"),
+            Some("jti")
+        );
+        assert_eq!(revocable_jti(&claims(Some("other")), "client"), None);
+        // issued by the login endpoints, not to a client.
+        assert_eq!(revocable_jti(&claims(None), "client"), None);
+    }
+
+    #[test]
+    fn refresh_tokens_of_the_client() {
+        assert_eq!(revocable_family(&refresh_token(Some(1)), 1), Some("family"));
+        assert_eq!(revocable_family(&refresh_token(Some(2)), 1), None);
+        assert_eq!(revocable_family(&refresh_token(None), 1), None);
+    }
+
+    #[test]
+    fn revoked_until_the_token_expires() {
+        let denylist = Denylist {
+            loaded_at: Instant::now(),
+            jtis: HashMap::from([(String::from("jti"), 100)]),
+        };
+
+        assert!(denylist.is_revoked("jti", 99));
+        assert!(!denylist.is_revoked("jti", 100));
+        assert!(!denylist.is_revoked("other", 99));
+    }
+}
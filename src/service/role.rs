@@ -0,0 +1,259 @@
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{
+            client::ClientMeta,
+            role::{CreateRoleDTO, UpdateRoleDTO},
+        },
+        response::{
+            role::{PermissionDTO, RoleDTO},
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{role::Role, ModelManager},
+    repository::{role::RoleRepository, user::UserRepository},
+};
+
+use super::{
+    audit::AuditService,
+    constant::{AUDIT_EVENT_ROLE_ASSIGNED, AUDIT_EVENT_ROLE_UNASSIGNED, ROLE_ADMIN},
+    error::Result,
+    ServiceError,
+};
+
+const MAX_NAME_LENGTH: usize = 64;
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("role not found"))
+}
+
+fn validate_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ServiceError::BadRequest(format!(
+            "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+        )));
+    }
+
+    Ok(name)
+}
+
+// the first of `permissions` that isn't one of `known`.
+fn find_unknown<'a>(permissions: &'a [String], known: &[String]) -> Option<&'a String> {
+    permissions.iter().find(|x| !known.contains(x))
+}
+
+// Roles group permissions and are assigned to users, the permissions end up in
+// the access tokens issued afterwards.
+#[derive(Debug, Clone)]
+pub struct RoleService {}
+
+impl RoleService {
+    pub async fn list_roles(mm: &ModelManager) -> Result<Vec<RoleDTO>> {
+        let roles = RoleRepository::list(Ctx::root_ctx(), mm).await?;
+
+        Self::into_dtos(mm, roles).await
+    }
+
+    pub async fn get_role(mm: &ModelManager, id: i64) -> Result<RoleDTO> {
+        let role = Self::find_role(mm, id).await?;
+        let permissions = RoleRepository::permissions_of_role(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(role.into_dto(permissions))
+    }
+
+    pub async fn create_role(mm: &ModelManager, req: &CreateRoleDTO) -> Result<RoleDTO> {
+        let name = validate_name(&req.name)?;
+
+        Self::validate_permissions(mm, &req.permissions).await?;
+
+        if RoleRepository::find_by_name(Ctx::root_ctx(), mm, name)
+            .await?
+            .is_some()
+        {
+            return Err(ServiceError::ObjectConflict(format!(
+                "role {name} already exists"
+            )));
+        }
+
+        let role = RoleRepository::create(Ctx::root_ctx(), mm, name, &req.description).await?;
+        RoleRepository::set_permissions(Ctx::root_ctx(), mm, role.id, &req.permissions).await?;
+
+        Self::get_role(mm, role.id).await
+    }
+
+    pub async fn update_role(mm: &ModelManager, id: i64, req: &UpdateRoleDTO) -> Result<RoleDTO> {
+        Self::find_role(mm, id).await?;
+
+        if let Some(description) = &req.description {
+            RoleRepository::update_description(Ctx::root_ctx(), mm, id, description).await?;
+        }
+
+        if let Some(permissions) = &req.permissions {
+            Self::validate_permissions(mm, permissions).await?;
+            RoleRepository::set_permissions(Ctx::root_ctx(), mm, id, permissions).await?;
+        }
+
+        Self::get_role(mm, id).await
+    }
+
+    // the admin role can't be deleted, it's the way back in for administrators.
+    pub async fn delete_role(mm: &ModelManager, id: i64) -> Result<BaseResponse<MessageResponse>> {
+        let role = Self::find_role(mm, id).await?;
+
+        if role.name == ROLE_ADMIN {
+            return Err(ServiceError::BadRequest(String::from(
+                "the admin role can't be deleted",
+            )));
+        }
+
+        RoleRepository::delete(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(BaseResponse::new(200, MessageResponse::new("role deleted")))
+    }
+
+    pub async fn list_permissions(mm: &ModelManager) -> Result<Vec<PermissionDTO>> {
+        let permissions = RoleRepository::list_permissions(Ctx::root_ctx(), mm).await?;
+
+        Ok(permissions.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn user_roles(mm: &ModelManager, user_id: i64) -> Result<Vec<RoleDTO>> {
+        Self::find_user(mm, user_id).await?;
+        let roles = RoleRepository::roles_of_user(Ctx::root_ctx(), mm, user_id).await?;
+
+        Self::into_dtos(mm, roles).await
+    }
+
+    pub async fn assign_role(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        user_id: i64,
+        role_id: i64,
+        client: &ClientMeta,
+    ) -> Result<Vec<RoleDTO>> {
+        Self::find_user(mm, user_id).await?;
+        let role = Self::find_role(mm, role_id).await?;
+
+        RoleRepository::assign(Ctx::root_ctx(), mm, user_id, role_id).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_ROLE_ASSIGNED,
+            client,
+            json!({ "actor": AuditService::actor(ctx), "role": role.name }),
+        )
+        .await;
+
+        Self::user_roles(mm, user_id).await
+    }
+
+    pub async fn unassign_role(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        user_id: i64,
+        role_id: i64,
+        client: &ClientMeta,
+    ) -> Result<Vec<RoleDTO>> {
+        Self::find_user(mm, user_id).await?;
+        let role = Self::find_role(mm, role_id).await?;
+
+        // keeps an administrator from locking everyone out by mistake.
+        if role.name == ROLE_ADMIN && user_id == ctx.user_id() as i64 {
+            return Err(ServiceError::BadRequest(String::from(
+                "you can't remove the admin role from yourself",
+            )));
+        }
+
+        RoleRepository::unassign(Ctx::root_ctx(), mm, user_id, role_id).await?;
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_ROLE_UNASSIGNED,
+            client,
+            json!({ "actor": AuditService::actor(ctx), "role": role.name }),
+        )
+        .await;
+
+        Self::user_roles(mm, user_id).await
+    }
+
+    async fn validate_permissions(mm: &ModelManager, permissions: &[String]) -> Result<()> {
+        let known: Vec<String> = RoleRepository::list_permissions(Ctx::root_ctx(), mm)
+            .await?
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+
+        if let Some(unknown) = find_unknown(permissions, &known) {
+            return Err(ServiceError::BadRequest(format!(
+                "unknown permission {unknown}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn into_dtos(mm: &ModelManager, roles: Vec<Role>) -> Result<Vec<RoleDTO>> {
+        let mut dtos = Vec::with_capacity(roles.len());
+
+        for role in roles {
+            let permissions =
+                RoleRepository::permissions_of_role(Ctx::root_ctx(), mm, role.id).await?;
+            dtos.push(role.into_dto(permissions));
+        }
+
+        Ok(dtos)
+    }
+
+    async fn find_role(mm: &ModelManager, id: i64) -> Result<Role> {
+        RoleRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)
+    }
+
+    async fn find_user(mm: &ModelManager, id: i64) -> Result<()> {
+        UserRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(String::from("user not found")))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_unknown, validate_name, MAX_NAME_LENGTH};
+
+    #[test]
+    fn validate_name_trims() {
+        assert_eq!(validate_name("  support ").unwrap(), "support");
+    }
+
+    #[test]
+    fn validate_name_rejects_empty_or_long() {
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"r".repeat(MAX_NAME_LENGTH + 1)).is_err());
+        assert!(validate_name(&"r".repeat(MAX_NAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn find_unknown_permission() {
+        let known = vec![String::from("users:read"), String::from("users:write")];
+
+        assert_eq!(find_unknown(&[String::from("users:read")], &known), None);
+        assert_eq!(find_unknown(&[], &known), None);
+        assert_eq!(
+            find_unknown(
+                &[String::from("users:read"), String::from("roles:read")],
+                &known
+            ),
+            Some(&String::from("roles:read"))
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use std::env;
+
+use anyhow::Context;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::service_account::{
+            AddServiceAccountKeyDTO, CreateServiceAccountDTO, CreateServiceAccountSecretDTO,
+            ServiceAccountQuery, ServiceAccountTokenRequest, UpdateServiceAccountDTO,
+        },
+        response::{
+            service_account::{
+                CreatedServiceAccountSecretDTO, ServiceAccountDTO, ServiceAccountKeyDTO,
+                ServiceAccountTokenDTO,
+            },
+            BaseResponse, MessageResponse,
+        },
+    },
+    model::{service_account::ServiceAccount, ModelManager},
+    pkg::util::{digest::sha256_hex, rand::generate_random_string},
+    repository::{role::RoleRepository, service_account::ServiceAccountRepository},
+};
+
+use super::{
+    client_assertion,
+    constant::{
+        CLIENT_ASSERTION_TYPE_JWT_BEARER, GRANT_TYPE_CLIENT_CREDENTIALS,
+        PERMISSION_SERVICE_ACCOUNTS_READ, PERMISSION_SERVICE_ACCOUNTS_WRITE,
+        SERVICE_ACCOUNT_CLIENT_ID_PREFIX, SERVICE_ACCOUNT_SECRET_PREFIX,
+    },
+    error::Result,
+    organization::OrganizationService,
+    token::{access_token_ttl_seconds, TokenService},
+    ServiceError,
+};
+
+const CLIENT_ID_LENGTH: usize = 24;
+const SECRET_LENGTH: usize = 48;
+// characters of the random part kept along with the prefix to recognize a secret.
+const VISIBLE_LENGTH: usize = 8;
+const KID_LENGTH: usize = 16;
+const MAX_NAME_LENGTH: usize = 255;
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("service account not found"))
+}
+
+// the `aud` expected in the assertions.
+fn token_url() -> anyhow::Result<String> {
+    let base_url = env::var("BASE_URL").context("Missing BASE_URL env var")?;
+
+    Ok(format!("{}/service-accounts/token", base_url))
+}
+
+// Non human identities, separate from the users. A service account belongs to an
+// organization, managed by its owners and admins, or to no one and is managed by the
+// administrators. It authenticates with a client secret or a signed assertion and gets
+// access tokens carrying its own permissions.
+#[derive(Debug, Clone)]
+pub struct ServiceAccountService {}
+
+impl ServiceAccountService {
+    pub async fn create(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &CreateServiceAccountDTO,
+    ) -> Result<BaseResponse<ServiceAccountDTO>> {
+        Self::ensure_manager(
+            mm,
+            ctx,
+            req.organization_id,
+            PERMISSION_SERVICE_ACCOUNTS_WRITE,
+        )
+        .await?;
+
+        let name = req.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ServiceError::BadRequest(format!(
+                "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+            )));
+        }
+
+        Self::validate_permissions(mm, ctx, &req.permissions).await?;
+
+        let client_id = format!(
+            "{}{}",
+            SERVICE_ACCOUNT_CLIENT_ID_PREFIX,
+            generate_random_string(CLIENT_ID_LENGTH)
+        );
+        let created_by = (!ctx.is_service_account()).then_some(ctx.user_id() as i64);
+
+        let service_account = ServiceAccountRepository::create(
+            Ctx::root_ctx().with_organization(req.organization_id),
+            mm,
+            &client_id,
+            name,
+            &req.description,
+            created_by,
+        )
+        .await?;
+
+        ServiceAccountRepository::set_permissions(
+            Ctx::root_ctx(),
+            mm,
+            service_account.id,
+            &req.permissions,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(
+            201,
+            Self::into_dto(mm, service_account).await?,
+        ))
+    }
+
+    pub async fn list(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        query: &ServiceAccountQuery,
+    ) -> Result<Vec<ServiceAccountDTO>> {
+        Self::ensure_manager(
+            mm,
+            ctx,
+            query.organization,
+            PERMISSION_SERVICE_ACCOUNTS_READ,
+        )
+        .await?;
+
+        let service_accounts = ServiceAccountRepository::list(
+            Ctx::root_ctx().with_organization(query.organization),
+            mm,
+        )
+        .await?;
+
+        let mut dtos = Vec::with_capacity(service_accounts.len());
+        for service_account in service_accounts {
+            dtos.push(Self::into_dto(mm, service_account).await?);
+        }
+
+        Ok(dtos)
+    }
+
+    pub async fn get(mm: &ModelManager, ctx: &Ctx, id: i64) -> Result<ServiceAccountDTO> {
+        let service_account = Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_READ).await?;
+
+        Self::into_dto(mm, service_account).await
+    }
+
+    // disabling stops the access tokens already issued as well.
+    pub async fn update(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        req: &UpdateServiceAccountDTO,
+    ) -> Result<ServiceAccountDTO> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        if let Some(permissions) = &req.permissions {
+            Self::validate_permissions(mm, ctx, permissions).await?;
+        }
+
+        if let Some(description) = &req.description {
+            ServiceAccountRepository::update_description(Ctx::root_ctx(), mm, id, description)
+                .await?;
+        }
+
+        if let Some(permissions) = &req.permissions {
+            ServiceAccountRepository::set_permissions(Ctx::root_ctx(), mm, id, permissions).await?;
+        }
+
+        if let Some(disabled) = req.disabled {
+            ServiceAccountRepository::set_disabled(Ctx::root_ctx(), mm, id, disabled).await?;
+        }
+
+        Self::get(mm, ctx, id).await
+    }
+
+    pub async fn delete(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        ServiceAccountRepository::delete(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("service account deleted"),
+        ))
+    }
+
+    // the secret itself is only part of this response.
+    pub async fn create_secret(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        req: &CreateServiceAccountSecretDTO,
+    ) -> Result<BaseResponse<CreatedServiceAccountSecretDTO>> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        if req
+            .expires_at
+            .is_some_and(|x| x <= OffsetDateTime::now_utc())
+        {
+            return Err(ServiceError::BadRequest(String::from(
+                "expires_at must be in the future",
+            )));
+        }
+
+        let client_secret = format!(
+            "{}{}",
+            SERVICE_ACCOUNT_SECRET_PREFIX,
+            generate_random_string(SECRET_LENGTH)
+        );
+        let prefix = &client_secret[..SERVICE_ACCOUNT_SECRET_PREFIX.len() + VISIBLE_LENGTH];
+
+        let secret = ServiceAccountRepository::create_secret(
+            Ctx::root_ctx(),
+            mm,
+            id,
+            prefix,
+            &sha256_hex(&client_secret),
+            req.expires_at,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(
+            201,
+            CreatedServiceAccountSecretDTO {
+                secret: secret.into(),
+                client_secret,
+            },
+        ))
+    }
+
+    pub async fn delete_secret(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        secret_id: i64,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        if !ServiceAccountRepository::delete_secret(Ctx::root_ctx(), mm, id, secret_id).await? {
+            return Err(ServiceError::NotFound(String::from("secret not found")));
+        }
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("secret deleted"),
+        ))
+    }
+
+    // the `kid` to put in the header of the assertions signed with the matching private key.
+    pub async fn add_key(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        req: &AddServiceAccountKeyDTO,
+    ) -> Result<BaseResponse<ServiceAccountKeyDTO>> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        client_assertion::validate_key(&req.algorithm, &req.public_key)?;
+
+        let key = ServiceAccountRepository::create_key(
+            Ctx::root_ctx(),
+            mm,
+            id,
+            &generate_random_string(KID_LENGTH),
+            &req.algorithm,
+            &req.public_key,
+        )
+        .await?;
+
+        Ok(BaseResponse::new(201, key.into()))
+    }
+
+    pub async fn delete_key(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        key_id: i64,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        Self::find(mm, ctx, id, PERMISSION_SERVICE_ACCOUNTS_WRITE).await?;
+
+        if !ServiceAccountRepository::delete_key(Ctx::root_ctx(), mm, id, key_id).await? {
+            return Err(ServiceError::NotFound(String::from("key not found")));
+        }
+
+        Ok(BaseResponse::new(200, MessageResponse::new("key deleted")))
+    }
+
+    // client_credentials grant, the service account authenticates with a secret
+    // or an assertion signed by one of its keys.
+    pub async fn issue_token(
+        mm: &ModelManager,
+        req: &ServiceAccountTokenRequest,
+    ) -> Result<ServiceAccountTokenDTO> {
+        if req.grant_type != GRANT_TYPE_CLIENT_CREDENTIALS {
+            return Err(ServiceError::BadRequest(format!(
+                "grant_type must be {GRANT_TYPE_CLIENT_CREDENTIALS}"
+            )));
+        }
+
+        let service_account = match (&req.client_assertion, &req.client_secret) {
+            (Some(assertion), _) => {
+                if req.client_assertion_type.as_deref() != Some(CLIENT_ASSERTION_TYPE_JWT_BEARER) {
+                    return Err(ServiceError::BadRequest(format!(
+                        "client_assertion_type must be {CLIENT_ASSERTION_TYPE_JWT_BEARER}"
+                    )));
+                }
+
+                Self::authenticate_assertion(mm, assertion, req.client_id.as_deref()).await?
+            }
+            (None, Some(secret)) => {
+                let client_id = req.client_id.as_deref().ok_or(ServiceError::Unauthorized)?;
+                Self::authenticate_secret(mm, client_id, secret).await?
+            }
+            (None, None) => return Err(ServiceError::Unauthorized),
+        };
+
+        if service_account.disabled_at.is_some() {
+            return Err(ServiceError::Unauthorized);
+        }
+
+        let access_token = TokenService::issue_service_account_token(mm, &service_account).await?;
+
+        Ok(ServiceAccountTokenDTO {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: access_token_ttl_seconds(),
+        })
+    }
+
+    pub async fn delete_expired_assertions(mm: &ModelManager) -> Result<u64> {
+        let deleted =
+            ServiceAccountRepository::delete_expired_assertions(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+
+    async fn authenticate_secret(
+        mm: &ModelManager,
+        client_id: &str,
+        secret: &str,
+    ) -> Result<ServiceAccount> {
+        let service_account =
+            ServiceAccountRepository::find_by_client_id(Ctx::root_ctx(), mm, client_id)
+                .await?
+                .ok_or(ServiceError::Unauthorized)?;
+
+        ServiceAccountRepository::use_secret(
+            Ctx::root_ctx(),
+            mm,
+            service_account.id,
+            &sha256_hex(secret),
+        )
+        .await?
+        .ok_or(ServiceError::Unauthorized)?;
+
+        Ok(service_account)
+    }
+
+    // private_key_jwt: `iss` and `sub` are the client id, `aud` the token endpoint and
+    // the `kid` header one of the service account's keys. Each assertion is used once.
+    async fn authenticate_assertion(
+        mm: &ModelManager,
+        assertion: &str,
+        client_id: Option<&str>,
+    ) -> Result<ServiceAccount> {
+        let kid = client_assertion::kid(assertion).ok_or(ServiceError::Unauthorized)?;
+
+        let key = ServiceAccountRepository::find_key(Ctx::root_ctx(), mm, &kid)
+            .await?
+            .ok_or(ServiceError::Unauthorized)?;
+
+        let service_account =
+            ServiceAccountRepository::find_by_id(Ctx::root_ctx(), mm, key.service_account_id)
+                .await?
+                .ok_or(ServiceError::Unauthorized)?;
+
+        if client_id.is_some_and(|x| x != service_account.client_id) {
+            return Err(ServiceError::Unauthorized);
+        }
+
+        if !client_assertion::verify(
+            mm,
+            assertion,
+            &service_account.client_id,
+            &key.algorithm,
+            &key.public_key,
+            &token_url()?,
+        )
+        .await?
+        {
+            return Err(ServiceError::Unauthorized);
+        }
+
+        Ok(service_account)
+    }
+
+    // Holders of the permission manage every service account, the owners and admins of
+    // an organization the ones it owns.
+    async fn ensure_manager(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        organization_id: Option<i64>,
+        permission: &str,
+    ) -> Result<()> {
+        if ctx.has_permission(permission) {
+            return Ok(());
+        }
+
+        match organization_id {
+            Some(id) if !ctx.is_service_account() => {
+                OrganizationService::require_manager(mm, ctx, id).await?;
+                Ok(())
+            }
+            _ => Err(ServiceError::ForbiddenWithMessage(format!(
+                "missing the {permission} permission"
+            ))),
+        }
+    }
+
+    async fn find(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        permission: &str,
+    ) -> Result<ServiceAccount> {
+        let service_account = ServiceAccountRepository::find_by_id(Ctx::root_ctx(), mm, id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Self::ensure_manager(mm, ctx, service_account.organization_id, permission).await?;
+
+        Ok(service_account)
+    }
+
+    // a service account can't be given more than its manager holds.
+    async fn validate_permissions(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        permissions: &[String],
+    ) -> Result<()> {
+        let known = RoleRepository::list_permissions(Ctx::root_ctx(), mm).await?;
+
+        if let Some(unknown) = permissions
+            .iter()
+            .find(|x| !known.iter().any(|known| &known.name == *x))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "unknown permission {unknown}"
+            )));
+        }
+
+        if let Some(missing) = permissions.iter().find(|x| !ctx.has_permission(x)) {
+            return Err(ServiceError::ForbiddenWithMessage(format!(
+                "can't grant the {missing} permission you don't hold"
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn into_dto(
+        mm: &ModelManager,
+        service_account: ServiceAccount,
+    ) -> Result<ServiceAccountDTO> {
+        let id = service_account.id;
+        let permissions = ServiceAccountRepository::permissions_of(Ctx::root_ctx(), mm, id).await?;
+        let secrets = ServiceAccountRepository::list_secrets(Ctx::root_ctx(), mm, id).await?;
+        let keys = ServiceAccountRepository::list_keys(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(service_account.into_dto(permissions, secrets, keys))
+    }
+}
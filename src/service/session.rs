@@ -0,0 +1,255 @@
+use serde_json::json;
+
+use crate::{
+    ctx::Ctx,
+    http::{
+        request::{client::ClientMeta, user::LogoutDTO},
+        response::{user::SessionDTO, BaseResponse, MessageResponse},
+    },
+    model::{session::Session, ModelManager},
+    pkg::util::digest::sha256_hex,
+    repository::{
+        refresh_token::RefreshTokenRepository, session::SessionRepository, user::UserRepository,
+    },
+};
+
+use super::{
+    audit::AuditService,
+    constant::{AUDIT_EVENT_LOGOUT, AUDIT_EVENT_SESSIONS_REVOKED, AUDIT_EVENT_SESSION_REVOKED},
+    error::Result,
+    refresh_token::{refresh_token_ttl_seconds, RefreshTokenService},
+    revocation::RevocationService,
+    token::Authentication,
+    ServiceError,
+};
+
+// `current` is the session of the request, if it has one.
+fn into_dtos(sessions: Vec<Session>, current: Option<i64>) -> Vec<SessionDTO> {
+    sessions
+        .into_iter()
+        .map(|x| {
+            let current = current == Some(x.id);
+            x.into_dto(current)
+        })
+        .collect()
+}
+
+// A session is one login of the user, on one device. The tokens issued along with it
+// carry its id as `sid` and stop working once it's revoked.
+#[derive(Debug, Clone)]
+pub struct SessionService {}
+
+impl SessionService {
+    // records the login, the returned authentication carries the session to the tokens.
+    pub async fn start(
+        mm: &ModelManager,
+        user_id: i64,
+        authentication: Authentication,
+        client: &ClientMeta,
+    ) -> Result<Authentication> {
+        let session = SessionRepository::create(
+            Ctx::root_ctx(),
+            mm,
+            user_id,
+            client.user_agent.as_deref(),
+            client.ip.as_deref(),
+            &authentication.methods,
+            refresh_token_ttl_seconds(),
+        )
+        .await?;
+
+        Ok(Authentication {
+            session_id: Some(session.id),
+            ..authentication
+        })
+    }
+
+    // one of its refresh tokens was exchanged, the session lasts as long as the successor.
+    pub async fn extend(mm: &ModelManager, session_id: i64) -> Result<()> {
+        SessionRepository::extend(Ctx::root_ctx(), mm, session_id, refresh_token_ttl_seconds())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list(mm: &ModelManager, ctx: &Ctx) -> Result<Vec<SessionDTO>> {
+        let sessions =
+            SessionRepository::list_active_by_user(Ctx::root_ctx(), mm, ctx.user_id() as i64)
+                .await?;
+
+        Ok(into_dtos(sessions, ctx.session_id()))
+    }
+
+    // signs the device out, its access tokens are denied by `jwt_auth` from now on.
+    pub async fn revoke(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        id: i64,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let user_id = ctx.user_id() as i64;
+
+        if !Self::revoke_session(mm, user_id, id).await? {
+            return Err(ServiceError::NotFound(String::from("session not found")));
+        }
+
+        AuditService::record(
+            mm,
+            user_id,
+            AUDIT_EVENT_SESSION_REVOKED,
+            client,
+            json!({ "session_id": id }),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("session revoked"),
+        ))
+    }
+
+    // every access token issued so far stops being accepted by `jwt_auth`,
+    // and the refresh tokens can't be exchanged anymore.
+    pub async fn revoke_all(mm: &ModelManager, user_id: i64) -> Result<()> {
+        UserRepository::revoke_tokens(Ctx::root_ctx(), mm, user_id).await?;
+        SessionRepository::revoke_for_user(Ctx::root_ctx(), mm, user_id).await?;
+        RefreshTokenService::revoke_for_user(mm, user_id, None).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(mm: &ModelManager) -> Result<u64> {
+        let deleted = SessionRepository::delete_expired(Ctx::root_ctx(), mm).await?;
+
+        Ok(deleted)
+    }
+
+    // The access token of the request along with its session, and the refresh tokens of
+    // the same login when one of them is given. Someone else's refresh token is left alone.
+    pub async fn logout(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        req: &LogoutDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        Self::revoke_current(mm, ctx).await?;
+
+        if let Some(session_id) = ctx.session_id() {
+            Self::revoke_session(mm, ctx.user_id() as i64, session_id).await?;
+        }
+
+        if let Some(token) = &req.refresh_token {
+            if let Some(refresh_token) =
+                RefreshTokenRepository::find(Ctx::root_ctx(), mm, &sha256_hex(token)).await?
+            {
+                if refresh_token.user_id == ctx.user_id() as i64 {
+                    RefreshTokenService::revoke_family(mm, &refresh_token.family_id).await?;
+                }
+            }
+        }
+
+        AuditService::record(
+            mm,
+            ctx.user_id() as i64,
+            AUDIT_EVENT_LOGOUT,
+            client,
+            json!({}),
+        )
+        .await;
+
+        Ok(BaseResponse::new(200, MessageResponse::new("logged out")))
+    }
+
+    pub async fn logout_all(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        // `tokens_valid_after` has a one second resolution, the current token could be
+        // issued within the same second.
+        Self::revoke_current(mm, ctx).await?;
+        Self::revoke_all(mm, ctx.user_id() as i64).await?;
+
+        AuditService::record(
+            mm,
+            ctx.user_id() as i64,
+            AUDIT_EVENT_SESSIONS_REVOKED,
+            client,
+            json!({}),
+        )
+        .await;
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("logged out of every session"),
+        ))
+    }
+
+    // the session and the refresh tokens handed out with it, `false` when the user has
+    // no such active session.
+    async fn revoke_session(mm: &ModelManager, user_id: i64, id: i64) -> Result<bool> {
+        if !SessionRepository::revoke(Ctx::root_ctx(), mm, user_id, id).await? {
+            return Ok(false);
+        }
+
+        RefreshTokenRepository::revoke_for_session(Ctx::root_ctx(), mm, id).await?;
+
+        Ok(true)
+    }
+
+    async fn revoke_current(mm: &ModelManager, ctx: &Ctx) -> Result<()> {
+        if let Some(jti) = ctx.token_id() {
+            RevocationService::revoke_access_token(mm, jti, ctx.expires_at()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+
+    use super::into_dtos;
+    use crate::{model::session::Session, service::constant::AMR_PASSWORD};
+
+    fn session(id: i64) -> Session {
+        let now = OffsetDateTime::now_utc();
+
+        Session {
+            id,
+            user_id: 1,
+            user_agent: None,
+            ip: None,
+            amr: vec![AMR_PASSWORD.to_string()],
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now,
+            revoked_at: None,
+        }
+    }
+
+    fn current(sessions: Vec<Session>, current: Option<i64>) -> Vec<(i64, bool)> {
+        into_dtos(sessions, current)
+            .into_iter()
+            .map(|x| (x.id, x.current))
+            .collect()
+    }
+
+    #[test]
+    fn marks_the_current_session() {
+        assert_eq!(
+            current(vec![session(2), session(1)], Some(1)),
+            vec![(2, false), (1, true)]
+        );
+    }
+
+    #[test]
+    fn no_current_session_without_sid() {
+        // an API key or a token issued before the sessions were tracked.
+        assert_eq!(
+            current(vec![session(2), session(1)], None),
+            vec![(2, false), (1, false)]
+        );
+    }
+}
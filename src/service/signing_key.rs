@@ -0,0 +1,704 @@
+use std::{
+    env,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::{
+    rand::SystemRandom,
+    rsa::PublicKeyComponents,
+    signature::{
+        EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING,
+    },
+};
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    http::response::{
+        oidc::{JwkDTO, JwksDTO},
+        signing_key::SigningKeyDTO,
+        BaseResponse, MessageResponse,
+    },
+    model::{signing_key::SigningKey, ModelManager},
+    pkg::{
+        aead,
+        jwk::{base64url, ec_thumbprint, okp_thumbprint, rsa_thumbprint},
+    },
+    repository::signing_key::SigningKeyRepository,
+};
+
+use super::{
+    constant::{
+        SIGNING_KEY_STATUS_ACTIVE, SIGNING_KEY_STATUS_PENDING, SIGNING_KEY_STATUS_PREVIOUS,
+        SIGNING_KEY_STATUS_RETIRED,
+    },
+    error::Result,
+    token::access_token_ttl_seconds,
+    ServiceError,
+};
+
+const ALGORITHMS: [Algorithm; 3] = [Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA];
+const RSA_KEY_BITS: usize = 2048;
+const DEFAULT_ROTATION_DAYS: i64 = 30;
+// the next key is published this long before it signs, more than the JWKS may be cached.
+const PUBLISH_AHEAD_SECONDS: i64 = 24 * 60 * 60;
+pub const JWKS_MAX_AGE_SECONDS: u64 = 60 * 60;
+// how long the keys of the database are kept in memory, other instances could have rotated.
+const CACHE_SECONDS: u64 = 60;
+// an unknown `kid` reloads the keys, at most this often.
+const RELOAD_SECONDS: u64 = 5;
+
+// SIGNING_KEY_ALGORITHM of the keys created from now on, RS256 by default.
+fn algorithm() -> Algorithm {
+    env::var("SIGNING_KEY_ALGORITHM")
+        .ok()
+        .and_then(|x| Algorithm::from_str(&x).ok())
+        .filter(|x| ALGORITHMS.contains(x))
+        .unwrap_or(Algorithm::RS256)
+}
+
+fn rotation_seconds() -> i64 {
+    env::var("SIGNING_KEY_ROTATION_DAYS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_ROTATION_DAYS)
+        * 24
+        * 60
+        * 60
+}
+
+// SIGNING_KEY_ENCRYPTION_KEY, base64 of the 32 bytes encrypting the private keys at rest.
+fn encryption_key() -> anyhow::Result<Vec<u8>> {
+    let key = env::var("SIGNING_KEY_ENCRYPTION_KEY")
+        .context("Missing SIGNING_KEY_ENCRYPTION_KEY env var")?;
+    let key = STANDARD
+        .decode(key.trim())
+        .context("SIGNING_KEY_ENCRYPTION_KEY must be base64")?;
+
+    if key.len() != aead::KEY_LEN {
+        return Err(anyhow!(
+            "SIGNING_KEY_ENCRYPTION_KEY must be {} bytes",
+            aead::KEY_LEN
+        ));
+    }
+
+    Ok(key)
+}
+
+// the ciphertext is bound to the `kid`, it can't be swapped with the one of another key.
+fn encrypt(kid: &str, der: &[u8]) -> anyhow::Result<Vec<u8>> {
+    aead::seal(&encryption_key()?, kid.as_bytes(), der)
+}
+
+fn decrypt(key: &SigningKey) -> anyhow::Result<Vec<u8>> {
+    match key.encrypted {
+        true => aead::open(&encryption_key()?, key.kid.as_bytes(), &key.private_key)
+            .with_context(|| format!("failed to decrypt signing key {}", key.kid)),
+        false => Ok(key.private_key.clone()),
+    }
+}
+
+fn algorithm_name(algorithm: Algorithm) -> String {
+    format!("{:?}", algorithm)
+}
+
+fn not_found() -> ServiceError {
+    ServiceError::NotFound(String::from("signing key not found"))
+}
+
+// the newest key that activated and isn't retired signs, the keys are newest first.
+fn active_id(keys: &[SigningKey], now: OffsetDateTime) -> Option<i64> {
+    keys.iter()
+        .find(|x| x.retired_at.is_none() && x.activates_at <= now)
+        .map(|x| x.id)
+}
+
+fn key_status(key: &SigningKey, active: Option<i64>, now: OffsetDateTime) -> &'static str {
+    match key.retired_at {
+        Some(_) => SIGNING_KEY_STATUS_RETIRED,
+        None if Some(key.id) == active => SIGNING_KEY_STATUS_ACTIVE,
+        None if key.activates_at > now => SIGNING_KEY_STATUS_PENDING,
+        None => SIGNING_KEY_STATUS_PREVIOUS,
+    }
+}
+
+// The next key is published a day before the newest one is `rotation` seconds old,
+// and right away when there is none.
+fn rotation_due(newest: Option<OffsetDateTime>, rotation: i64, now: OffsetDateTime) -> bool {
+    match newest {
+        // the newest is still pending.
+        Some(activates_at) if activates_at > now => false,
+        Some(activates_at) => {
+            activates_at + time::Duration::seconds(rotation)
+                <= now + time::Duration::seconds(PUBLISH_AHEAD_SECONDS)
+        }
+        None => true,
+    }
+}
+
+// A new private key, DER encoded the way jsonwebtoken takes it:
+// PKCS#1 for RSA, PKCS#8 for the others.
+fn generate(algorithm: Algorithm) -> anyhow::Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+
+    let der = match algorithm {
+        Algorithm::RS256 => rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, RSA_KEY_BITS)?
+            .to_pkcs1_der()?
+            .as_bytes()
+            .to_vec(),
+        Algorithm::ES256 => EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .map_err(|_| anyhow!("failed to generate an ES256 key"))?
+            .as_ref()
+            .to_vec(),
+        Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| anyhow!("failed to generate an EdDSA key"))?
+            .as_ref()
+            .to_vec(),
+        _ => return Err(anyhow!("unsupported signing algorithm {:?}", algorithm)),
+    };
+
+    Ok(der)
+}
+
+// The public half of the key, as published in the JWKS. The `kid` is its thumbprint.
+fn public_jwk(algorithm: Algorithm, der: &[u8]) -> anyhow::Result<JwkDTO> {
+    let invalid = |_| anyhow!("invalid {:?} private key", algorithm);
+    // completed with the members of the key type below.
+    let base = JwkDTO {
+        kty: "",
+        use_: "sig",
+        alg: algorithm_name(algorithm),
+        kid: String::new(),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+        y: None,
+    };
+
+    let jwk = match algorithm {
+        Algorithm::RS256 => {
+            let key_pair = RsaKeyPair::from_der(der).map_err(invalid)?;
+            let public = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+            let (n, e) = (base64url(&public.n), base64url(&public.e));
+
+            JwkDTO {
+                kty: "RSA",
+                kid: rsa_thumbprint(&n, &e),
+                n: Some(n),
+                e: Some(e),
+                ..base
+            }
+        }
+        Algorithm::ES256 => {
+            let key_pair = EcdsaKeyPair::from_pkcs8(
+                &ECDSA_P256_SHA256_FIXED_SIGNING,
+                der,
+                &SystemRandom::new(),
+            )
+            .map_err(invalid)?;
+            // uncompressed point: 0x04, then x and y.
+            let point = key_pair.public_key().as_ref();
+            let (x, y) = (base64url(&point[1..33]), base64url(&point[33..65]));
+
+            JwkDTO {
+                kty: "EC",
+                kid: ec_thumbprint("P-256", &x, &y),
+                crv: Some("P-256"),
+                x: Some(x),
+                y: Some(y),
+                ..base
+            }
+        }
+        Algorithm::EdDSA => {
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).map_err(invalid)?;
+            let x = base64url(key_pair.public_key().as_ref());
+
+            JwkDTO {
+                kty: "OKP",
+                kid: okp_thumbprint("Ed25519", &x),
+                crv: Some("Ed25519"),
+                x: Some(x),
+                ..base
+            }
+        }
+        _ => return Err(anyhow!("unsupported signing algorithm {:?}", algorithm)),
+    };
+
+    Ok(jwk)
+}
+
+struct LoadedKey {
+    algorithm: Algorithm,
+    activates_at: OffsetDateTime,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: JwkDTO,
+}
+
+impl LoadedKey {
+    fn load(key: &SigningKey) -> anyhow::Result<Self> {
+        let algorithm = Algorithm::from_str(&key.algorithm)
+            .with_context(|| format!("unknown algorithm of signing key {}", key.kid))?;
+        let der = decrypt(key)?;
+        let jwk = public_jwk(algorithm, &der)?;
+
+        let (encoding_key, decoding_key) = match algorithm {
+            Algorithm::RS256 => (
+                EncodingKey::from_rsa_der(&der),
+                DecodingKey::from_rsa_components(
+                    jwk.n.as_deref().unwrap_or_default(),
+                    jwk.e.as_deref().unwrap_or_default(),
+                )?,
+            ),
+            Algorithm::ES256 => (
+                EncodingKey::from_ec_der(&der),
+                DecodingKey::from_ec_components(
+                    jwk.x.as_deref().unwrap_or_default(),
+                    jwk.y.as_deref().unwrap_or_default(),
+                )?,
+            ),
+            _ => (
+                EncodingKey::from_ed_der(&der),
+                DecodingKey::from_ed_components(jwk.x.as_deref().unwrap_or_default())?,
+            ),
+        };
+
+        Ok(Self {
+            algorithm,
+            activates_at: key.activates_at,
+            encoding_key,
+            decoding_key,
+            jwk,
+        })
+    }
+}
+
+// the published keys, newest first.
+struct KeySet {
+    loaded_at: Instant,
+    keys: Vec<Arc<LoadedKey>>,
+}
+
+impl KeySet {
+    fn find(&self, kid: &str) -> Option<&Arc<LoadedKey>> {
+        self.keys.iter().find(|x| x.jwk.kid == kid)
+    }
+
+    // the newest key that activated, the retired ones aren't published.
+    fn active(&self, now: OffsetDateTime) -> Option<Arc<LoadedKey>> {
+        self.keys.iter().find(|x| x.activates_at <= now).cloned()
+    }
+}
+
+static KEY_SET: RwLock<Option<Arc<KeySet>>> = RwLock::new(None);
+
+fn cached_key_set() -> Option<Arc<KeySet>> {
+    KEY_SET.read().ok().and_then(|x| x.clone())
+}
+
+fn invalidate() {
+    if let Ok(mut key_set) = KEY_SET.write() {
+        *key_set = None;
+    }
+}
+
+// The keys signing the access and ID tokens, stored in the database and shared by every
+// instance. A new key is published a day before it starts signing and the previous one is
+// retired once the tokens it signed have expired, verifiers only ever need the JWKS.
+#[derive(Debug, Clone)]
+pub struct SigningKeyService {}
+
+impl SigningKeyService {
+    // signed by the active key, with its `kid` in the header.
+    pub async fn sign<T: Serialize>(mm: &ModelManager, claims: &T) -> Result<String> {
+        let key_set = Self::key_set(mm, false).await?;
+        let key = match Self::active_key(&key_set) {
+            Some(key) => key,
+            // the very first token, or every key was retired.
+            None => {
+                Self::create_first(mm).await?;
+                let key_set = Self::key_set(mm, true).await?;
+                Self::active_key(&key_set).context("no active signing key")?
+            }
+        };
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.jwk.kid.clone());
+
+        let token = encode(&header, claims, &key.encoding_key).context("error encoding token")?;
+
+        Ok(token)
+    }
+
+    // The claims of a token signed by one of the published keys, `None` when the token
+    // is malformed, expired, or its key unknown or retired. A token with an `aud` claim
+    // has to name `audience`, any audience goes when `None`.
+    pub async fn verify<T: DeserializeOwned>(
+        mm: &ModelManager,
+        token: &str,
+        audience: Option<&str>,
+    ) -> Result<Option<T>> {
+        let Some(kid) = decode_header(token).ok().and_then(|x| x.kid) else {
+            return Ok(None);
+        };
+
+        let mut key_set = Self::key_set(mm, false).await?;
+        if key_set.find(&kid).is_none()
+            && key_set.loaded_at.elapsed() > Duration::from_secs(RELOAD_SECONDS)
+        {
+            key_set = Self::key_set(mm, true).await?;
+        }
+
+        let Some(key) = key_set.find(&kid) else {
+            return Ok(None);
+        };
+
+        // the algorithm comes from the key, never from the header of the token.
+        let mut validation = Validation::new(key.algorithm);
+        match audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        Ok(decode::<T>(token, &key.decoding_key, &validation)
+            .ok()
+            .map(|x| x.claims))
+    }
+
+    pub async fn jwks(mm: &ModelManager) -> Result<JwksDTO> {
+        let key_set = Self::key_set(mm, false).await?;
+
+        Ok(JwksDTO {
+            keys: key_set.keys.iter().map(|x| x.jwk.clone()).collect(),
+        })
+    }
+
+    // of the keys created from now on, the published ones could still use another.
+    pub fn algorithm_name() -> String {
+        algorithm_name(algorithm())
+    }
+
+    pub async fn list(mm: &ModelManager) -> Result<Vec<SigningKeyDTO>> {
+        let keys = SigningKeyRepository::list(Ctx::root_ctx(), mm).await?;
+        let now = OffsetDateTime::now_utc();
+        let active = active_id(&keys, now);
+
+        Ok(keys
+            .into_iter()
+            .map(|x| SigningKeyDTO {
+                status: key_status(&x, active, now),
+                kid: x.kid,
+                algorithm: x.algorithm,
+                created_at: x.created_at,
+                activates_at: x.activates_at,
+                retired_at: x.retired_at,
+            })
+            .collect())
+    }
+
+    // A key signing right away, when the current one has to be replaced before its time.
+    // Verifiers caching the JWKS learn about it on their next refresh.
+    pub async fn rotate(mm: &ModelManager) -> Result<SigningKeyDTO> {
+        let kid = Self::create(mm, algorithm(), 0).await?;
+
+        Self::list(mm)
+            .await?
+            .into_iter()
+            .find(|x| x.kid == kid)
+            .ok_or_else(not_found)
+    }
+
+    // the tokens it signed are rejected from now on, a compromised key goes this way.
+    pub async fn retire(mm: &ModelManager, kid: &str) -> Result<BaseResponse<MessageResponse>> {
+        if !SigningKeyRepository::retire(Ctx::root_ctx(), mm, kid).await? {
+            return Err(not_found());
+        }
+
+        invalidate();
+
+        Ok(BaseResponse::new(
+            200,
+            MessageResponse::new("signing key retired"),
+        ))
+    }
+
+    // Publishes the next key once the active one is due for rotation,
+    // the kid of the new key if any.
+    pub async fn schedule_rotation(mm: &ModelManager) -> Result<Option<String>> {
+        let keys = SigningKeyRepository::list_published(Ctx::root_ctx(), mm).await?;
+        let newest = keys.first().map(|x| x.activates_at);
+
+        if !rotation_due(newest, rotation_seconds(), OffsetDateTime::now_utc()) {
+            return Ok(None);
+        }
+
+        // nothing signs yet on a fresh install, no need to wait.
+        if keys.is_empty() {
+            return Self::create_first(mm).await;
+        }
+
+        Ok(Some(
+            Self::create(mm, algorithm(), PUBLISH_AHEAD_SECONDS).await?,
+        ))
+    }
+
+    // the keys superseded for longer than an access token lives.
+    pub async fn retire_superseded(mm: &ModelManager) -> Result<u64> {
+        let retired = SigningKeyRepository::retire_superseded(
+            Ctx::root_ctx(),
+            mm,
+            access_token_ttl_seconds() as i64,
+        )
+        .await?;
+
+        if retired > 0 {
+            invalidate();
+        }
+
+        Ok(retired)
+    }
+
+    // the keys stored in plain text before SIGNING_KEY_ENCRYPTION_KEY, the number encrypted.
+    pub async fn encrypt_stored(mm: &ModelManager) -> Result<u64> {
+        let mut encrypted = 0;
+
+        for key in SigningKeyRepository::list_unencrypted(Ctx::root_ctx(), mm).await? {
+            let private_key = encrypt(&key.kid, &key.private_key)?;
+
+            if SigningKeyRepository::set_encrypted(Ctx::root_ctx(), mm, key.id, &private_key)
+                .await?
+            {
+                encrypted += 1;
+            }
+        }
+
+        if encrypted > 0 {
+            invalidate();
+        }
+
+        Ok(encrypted)
+    }
+
+    // the kid of the key created, `None` when another caller created one in the meantime.
+    async fn create_first(mm: &ModelManager) -> Result<Option<String>> {
+        let (kid, alg, private_key) = Self::generate(algorithm()).await?;
+
+        let created =
+            SigningKeyRepository::create_first(Ctx::root_ctx(), mm, &kid, &alg, &private_key)
+                .await?;
+
+        invalidate();
+
+        Ok(created.then_some(kid))
+    }
+
+    // a new key, its kid and algorithm name along with the encrypted private key.
+    async fn generate(algorithm: Algorithm) -> Result<(String, String, Vec<u8>)> {
+        // RSA key generation takes a while.
+        let der = tokio::task::spawn_blocking(move || generate(algorithm))
+            .await
+            .context("key generation panicked")??;
+        let jwk = public_jwk(algorithm, &der)?;
+        let private_key = encrypt(&jwk.kid, &der)?;
+
+        Ok((jwk.kid, jwk.alg, private_key))
+    }
+
+    async fn create(mm: &ModelManager, algorithm: Algorithm, activates_in: i64) -> Result<String> {
+        let (kid, alg, private_key) = Self::generate(algorithm).await?;
+
+        SigningKeyRepository::create(Ctx::root_ctx(), mm, &kid, &alg, &private_key, activates_in)
+            .await?;
+
+        invalidate();
+
+        Ok(kid)
+    }
+
+    fn active_key(key_set: &KeySet) -> Option<Arc<LoadedKey>> {
+        key_set.active(OffsetDateTime::now_utc())
+    }
+
+    async fn key_set(mm: &ModelManager, reload: bool) -> Result<Arc<KeySet>> {
+        if let Some(key_set) = cached_key_set() {
+            if !reload && key_set.loaded_at.elapsed() < Duration::from_secs(CACHE_SECONDS) {
+                return Ok(key_set);
+            }
+        }
+
+        let keys = SigningKeyRepository::list_published(Ctx::root_ctx(), mm)
+            .await?
+            .iter()
+            .map(|x| LoadedKey::load(x).map(Arc::new))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let key_set = Arc::new(KeySet {
+            loaded_at: Instant::now(),
+            keys,
+        });
+
+        if let Ok(mut cached) = KEY_SET.write() {
+            *cached = Some(key_set.clone());
+        }
+
+        Ok(key_set)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Instant};
+
+    use jsonwebtoken::{decode, encode, Algorithm, Header, Validation};
+    use serde_json::{json, Value};
+    use sqlx::types::time::OffsetDateTime;
+    use time::Duration;
+
+    use super::{
+        active_id, generate, key_status, public_jwk, rotation_due, KeySet, LoadedKey,
+        PUBLISH_AHEAD_SECONDS,
+    };
+    use crate::{
+        model::signing_key::SigningKey,
+        service::constant::{
+            SIGNING_KEY_STATUS_ACTIVE, SIGNING_KEY_STATUS_PENDING, SIGNING_KEY_STATUS_PREVIOUS,
+            SIGNING_KEY_STATUS_RETIRED,
+        },
+    };
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    // stored in plain text, no SIGNING_KEY_ENCRYPTION_KEY needed.
+    fn key(id: i64, algorithm: Algorithm, activates_at: OffsetDateTime) -> SigningKey {
+        let der = generate(algorithm).unwrap();
+
+        SigningKey {
+            id,
+            kid: public_jwk(algorithm, &der).unwrap().kid,
+            algorithm: format!("{:?}", algorithm),
+            private_key: der,
+            encrypted: false,
+            created_at: activates_at,
+            activates_at,
+            retired_at: None,
+        }
+    }
+
+    #[test]
+    fn kid_is_thumbprint_of_the_key() {
+        for algorithm in [Algorithm::ES256, Algorithm::EdDSA] {
+            let der = generate(algorithm).unwrap();
+            let jwk = public_jwk(algorithm, &der).unwrap();
+
+            assert_eq!(jwk.kid, public_jwk(algorithm, &der).unwrap().kid);
+            assert_ne!(
+                jwk.kid,
+                public_jwk(algorithm, &generate(algorithm).unwrap())
+                    .unwrap()
+                    .kid
+            );
+        }
+    }
+
+    #[test]
+    fn newest_activated_key_is_active() {
+        let now = OffsetDateTime::now_utc();
+        let mut keys = vec![
+            key(3, Algorithm::ES256, now + Duration::days(1)),
+            key(2, Algorithm::ES256, now - Duration::days(1)),
+            key(1, Algorithm::ES256, now - Duration::days(30)),
+        ];
+
+        let active = active_id(&keys, now);
+        assert_eq!(active, Some(2));
+        assert_eq!(
+            key_status(&keys[0], active, now),
+            SIGNING_KEY_STATUS_PENDING
+        );
+        assert_eq!(key_status(&keys[1], active, now), SIGNING_KEY_STATUS_ACTIVE);
+        assert_eq!(
+            key_status(&keys[2], active, now),
+            SIGNING_KEY_STATUS_PREVIOUS
+        );
+
+        // a retired key never signs, the previous one takes over.
+        keys[1].retired_at = Some(now);
+        let active = active_id(&keys, now);
+        assert_eq!(active, Some(1));
+        assert_eq!(
+            key_status(&keys[1], active, now),
+            SIGNING_KEY_STATUS_RETIRED
+        );
+    }
+
+    #[test]
+    fn rotation_due_ahead_of_time() {
+        let now = OffsetDateTime::now_utc();
+        let rotation = 30 * DAY;
+
+        assert!(rotation_due(None, rotation, now));
+        assert!(!rotation_due(Some(now + Duration::hours(1)), rotation, now));
+        assert!(!rotation_due(Some(now - Duration::days(1)), rotation, now));
+        assert!(rotation_due(
+            Some(now - Duration::seconds(rotation - PUBLISH_AHEAD_SECONDS)),
+            rotation,
+            now
+        ));
+    }
+
+    #[test]
+    fn finds_keys_by_kid() {
+        let now = OffsetDateTime::now_utc();
+        let pending = key(2, Algorithm::EdDSA, now + Duration::days(1));
+        let active = key(1, Algorithm::ES256, now - Duration::days(1));
+        let key_set = KeySet {
+            loaded_at: Instant::now(),
+            keys: [&pending, &active]
+                .iter()
+                .map(|x| Arc::new(LoadedKey::load(x).unwrap()))
+                .collect(),
+        };
+
+        let signing = key_set.active(now).unwrap();
+        assert_eq!(signing.jwk.kid, active.kid);
+        assert!(key_set.find(&pending.kid).is_some());
+        assert!(key_set.find("unknown").is_none());
+
+        // signed by the active key, verified by the one found by kid.
+        let mut header = Header::new(signing.algorithm);
+        header.kid = Some(signing.jwk.kid.clone());
+        let token = encode(
+            &header,
+            &json!({ "exp": 4102444800u64 }),
+            &signing.encoding_key,
+        )
+        .unwrap();
+
+        let found = key_set.find(&active.kid).unwrap();
+        assert!(decode::<Value>(
+            &token,
+            &found.decoding_key,
+            &Validation::new(found.algorithm)
+        )
+        .is_ok());
+
+        let other = key_set.find(&pending.kid).unwrap();
+        assert!(decode::<Value>(
+            &token,
+            &other.decoding_key,
+            &Validation::new(other.algorithm)
+        )
+        .is_err());
+    }
+}
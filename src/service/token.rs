@@ -0,0 +1,234 @@
+use std::env;
+
+use jsonwebtoken::get_current_timestamp;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::{
+    ctx::Ctx,
+    model::{
+        oauth::OAuthClient,
+        service_account::ServiceAccount,
+        user::{CustomTokenClaims, User},
+        ModelManager,
+    },
+    pkg::util::rand::generate_random_string,
+    repository::{role::RoleRepository, service_account::ServiceAccountRepository},
+};
+
+use super::{
+    constant::{
+        AMR_EMAIL, AMR_FEDERATED, AMR_MFA, AMR_OTP, AMR_PASSWORD, LOGIN_METHOD_MAGIC_LINK,
+        LOGIN_METHOD_PASSWORD, PRINCIPAL_CLIENT, PRINCIPAL_SERVICE_ACCOUNT, SCOPE_ACCOUNT,
+    },
+    error::Result,
+    metadata::MetadataKind,
+    signing_key::SigningKeyService,
+};
+
+const DEFAULT_ACCESS_TOKEN_TTL_MINUTES: u64 = 15;
+const TOKEN_ID_LENGTH: usize = 24;
+
+// JWT_METADATA_CLAIMS=user_metadata,app_metadata copies the metadata into the access token,
+// mind the token size when the metadata grows.
+fn metadata_claims() -> Vec<String> {
+    env::var("JWT_METADATA_CLAIMS")
+        .map(|x| x.split(',').map(|x| x.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+pub fn access_token_ttl_seconds() -> u64 {
+    env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .ok()
+        .and_then(|x| x.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_ACCESS_TOKEN_TTL_MINUTES)
+        * 60
+}
+
+fn new_token_id() -> String {
+    generate_random_string(TOKEN_ID_LENGTH)
+}
+
+// When and how the user logged in, carried along by the tokens issued afterwards
+// for the `auth_time`, `amr` and `sid` claims.
+#[derive(Debug, Clone)]
+pub struct Authentication {
+    pub time: OffsetDateTime,
+    pub methods: Vec<String>,
+    // the session started by the login, see `SessionService::start`.
+    pub session_id: Option<i64>,
+}
+
+impl Authentication {
+    // `method` as recorded in the audit log, `mfa` once the second factor was verified.
+    pub fn new(method: &str, mfa: bool) -> Self {
+        let mut methods = vec![match method {
+            LOGIN_METHOD_PASSWORD => AMR_PASSWORD,
+            LOGIN_METHOD_MAGIC_LINK => AMR_EMAIL,
+            // the oauth providers.
+            _ => AMR_FEDERATED,
+        }];
+
+        if mfa {
+            methods.extend([AMR_OTP, AMR_MFA]);
+        }
+
+        Self {
+            time: OffsetDateTime::now_utc(),
+            methods: methods.into_iter().map(String::from).collect(),
+            session_id: None,
+        }
+    }
+
+    // the one of the access token the request came with.
+    pub fn from_ctx(ctx: &Ctx) -> Self {
+        Self {
+            time: OffsetDateTime::from_unix_timestamp(ctx.auth_time() as i64)
+                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            methods: ctx.amr().to_vec(),
+            session_id: ctx.session_id(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenService {}
+
+impl TokenService {
+    // the permissions of the user's roles come with the `account` scope only.
+    // `organization_id` is the organization the token acts in, if any.
+    pub async fn issue_access_token(
+        mm: &ModelManager,
+        user: &User,
+        scope: &str,
+        organization_id: Option<i64>,
+        authentication: &Authentication,
+    ) -> Result<String> {
+        Self::issue_user_token(mm, user, scope, organization_id, authentication, None).await
+    }
+
+    // issued by the authorization server, `scope` is what the user consented to.
+    pub async fn issue_client_access_token(
+        mm: &ModelManager,
+        user: &User,
+        scope: &str,
+        organization_id: Option<i64>,
+        authentication: &Authentication,
+        client_id: &str,
+    ) -> Result<String> {
+        Self::issue_user_token(
+            mm,
+            user,
+            scope,
+            organization_id,
+            authentication,
+            Some(client_id),
+        )
+        .await
+    }
+
+    async fn issue_user_token(
+        mm: &ModelManager,
+        user: &User,
+        scope: &str,
+        organization_id: Option<i64>,
+        authentication: &Authentication,
+        client_id: Option<&str>,
+    ) -> Result<String> {
+        let now = get_current_timestamp();
+        let metadata_claims = metadata_claims();
+        let project = |kind: MetadataKind, value: &serde_json::Value| {
+            metadata_claims
+                .iter()
+                .any(|x| x == kind.name())
+                .then(|| value.clone())
+        };
+
+        let permissions = match scope.split_whitespace().any(|x| x == SCOPE_ACCOUNT) {
+            true => RoleRepository::permissions_of_user(Ctx::root_ctx(), mm, user.id).await?,
+            false => Vec::new(),
+        };
+
+        let claims = CustomTokenClaims {
+            sub: user.id as u64,
+            iat: now as usize,
+            exp: (now + access_token_ttl_seconds()) as usize,
+            jti: Some(new_token_id()),
+            scope: scope.to_string(),
+            user_metadata: project(MetadataKind::User, &user.user_metadata),
+            app_metadata: project(MetadataKind::App, &user.app_metadata),
+            permissions,
+            org: organization_id,
+            principal: None,
+            client_id: client_id.map(String::from),
+            auth_time: Some(authentication.time.unix_timestamp() as usize),
+            amr: authentication.methods.clone(),
+            aud: Vec::new(),
+            sid: authentication.session_id,
+        };
+
+        SigningKeyService::sign(mm, &claims).await
+    }
+
+    // carries the permissions of the service account, and its organization as `org`.
+    pub async fn issue_service_account_token(
+        mm: &ModelManager,
+        service_account: &ServiceAccount,
+    ) -> Result<String> {
+        let now = get_current_timestamp();
+
+        let claims = CustomTokenClaims {
+            sub: service_account.id as u64,
+            iat: now as usize,
+            exp: (now + access_token_ttl_seconds()) as usize,
+            jti: Some(new_token_id()),
+            scope: String::new(),
+            user_metadata: None,
+            app_metadata: None,
+            permissions: ServiceAccountRepository::permissions_of(
+                Ctx::root_ctx(),
+                mm,
+                service_account.id,
+            )
+            .await?,
+            org: service_account.organization_id,
+            principal: Some(PRINCIPAL_SERVICE_ACCOUNT.to_string()),
+            client_id: None,
+            auth_time: None,
+            amr: Vec::new(),
+            aud: Vec::new(),
+            sid: None,
+        };
+
+        SigningKeyService::sign(mm, &claims).await
+    }
+
+    // client_credentials grant, `sub` is the client itself and `scope` some of its own.
+    pub async fn issue_client_credentials_token(
+        mm: &ModelManager,
+        oauth_client: &OAuthClient,
+        scope: &str,
+        audiences: &[String],
+    ) -> Result<String> {
+        let now = get_current_timestamp();
+
+        let claims = CustomTokenClaims {
+            sub: oauth_client.id as u64,
+            iat: now as usize,
+            exp: (now + access_token_ttl_seconds()) as usize,
+            jti: Some(new_token_id()),
+            scope: scope.to_string(),
+            user_metadata: None,
+            app_metadata: None,
+            permissions: Vec::new(),
+            org: None,
+            principal: Some(PRINCIPAL_CLIENT.to_string()),
+            client_id: Some(oauth_client.client_id.clone()),
+            auth_time: None,
+            amr: Vec::new(),
+            aud: audiences.to_vec(),
+            sid: None,
+        };
+
+        SigningKeyService::sign(mm, &claims).await
+    }
+}
@@ -1,31 +1,89 @@
+use std::sync::OnceLock;
+
 use bcrypt::{hash, verify, DEFAULT_COST};
-use uuid::Uuid;
+use chrono::Utc;
+use serde_json::json;
+use tracing::info;
 
 use crate::{
     ctx::Ctx,
     http::{
-        request::user::CreateUserDTO,
+        request::{client::ClientMeta, user::CreateUserDTO},
         response::{
-            user::{MFAResponse, UserDTO},
-            BaseResponse,
+            user::{MFAResponse, ProfileDTO, UserDTO},
+            BaseResponse, MessageResponse,
         },
     },
-    model::ModelManager,
-    pkg::{hmac::HMAC, hotp::Hotp, util::rand::generate_random_string},
-    repository::user::UserRepository,
+    model::{
+        user::{User, UserPatch},
+        ModelManager,
+    },
+    pkg::{
+        hmac::HMAC,
+        hotp::Hotp,
+        util::{digest::sha256_hex, json::merge_patch, rand::generate_random_string},
+    },
+    repository::{
+        organization::OrganizationRepository, user::UserRepository, user_token::UserTokenRepository,
+    },
+};
+
+use super::{
+    audit::AuditService,
+    constant::{
+        AUDIT_EVENT_LOGIN, AUDIT_EVENT_LOGIN_FAILED, AUDIT_EVENT_MFA_ENABLED,
+        LOGIN_METHOD_PASSWORD, MFA_TYPE_TOTP, ORG_ROLE_MEMBER, TOKEN_PURPOSE_MFA_CHALLENGE,
+    },
+    email_verification::EmailVerificationService,
+    error::Result,
+    invitation::InvitationService,
+    metadata::{MetadataKind, MetadataService},
+    organization::OrganizationService,
+    refresh_token::RefreshTokenService,
+    session::SessionService,
+    token::{Authentication, TokenService},
+    ServiceError,
 };
 
-use super::{error::Result, ServiceError};
+const MFA_ISSUER: &str = "authservice";
+const MFA_CODE_LENGTH: u8 = 6;
+const MFA_TOKEN_LENGTH: usize = 32;
+const MFA_CHALLENGE_TTL_SECONDS: i64 = 5 * 60;
+const MFA_CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+// members of the profile document that PATCH /me can touch.
+const PROFILE_PATCH_FIELDS: [&str; 2] = ["name", "user_metadata"];
+const MAX_NAME_LENGTH: usize = 255;
+
+const SIGNUP_MESSAGE: &str =
+    "signup request received, check your inbox to verify your email address";
+
+// bcrypt hash checked against when there's no password to compare with,
+// so unknown and oauth-only accounts take as long to reject as a wrong password.
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    DUMMY_HASH.get_or_init(|| {
+        hash(b"dummy-password-for-timing", DEFAULT_COST).expect("failed to hash dummy password")
+    })
+}
 
 #[derive(Debug, Clone)]
 pub struct UserService {}
 
 impl UserService {
-    pub async fn create_user(mm: &ModelManager, req: &CreateUserDTO) -> Result<UserDTO> {
+    // the response is the same whether the email was free or already registered,
+    // so signup can't be used to find out who has an account.
+    pub async fn create_user(
+        mm: &ModelManager,
+        req: &CreateUserDTO,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MessageResponse>> {
+        let tenant = OrganizationService::tenant_ctx(mm, req.organization.as_deref()).await?;
         let hash_password = hash(req.password.as_bytes(), DEFAULT_COST)?;
 
-        let user = UserRepository::create(
-            Ctx::root_ctx(),
+        let user = UserRepository::create_if_absent(
+            tenant.clone(),
             mm,
             CreateUserDTO {
                 email: req.email.to_owned(),
@@ -34,65 +92,306 @@ impl UserService {
                 auth_provider: None,
                 auth_provider_user_id: None,
                 secret: None,
+                organization: None,
+                invitation: None,
             },
         )
         .await?;
 
-        let token = Uuid::new_v4();
+        match user {
+            Some(user) => {
+                if tenant.organization_id().is_some() {
+                    OrganizationRepository::upsert_member(tenant, mm, user.id, ORG_ROLE_MEMBER)
+                        .await?;
+                }
 
-        Ok(user.into_dto(
-            Some(token.to_string()),
-            Some("supposethisisrefreshtoken".to_string()),
-            None,
-        ))
+                // the invitation link already proves the address belongs to the user.
+                let invited = match &req.invitation {
+                    Some(token) => InvitationService::redeem(mm, &user, token, client)
+                        .await?
+                        .is_some(),
+                    None => false,
+                };
+
+                if !invited {
+                    EmailVerificationService::send_verification(mm, &user).await?
+                }
+            }
+            None => {
+                info!("signup attempted with an already registered email");
+                EmailVerificationService::send_already_registered(&req.email);
+            }
+        }
+
+        Ok(BaseResponse::new(202, MessageResponse::new(SIGNUP_MESSAGE)))
     }
 
-    pub async fn login(mm: &ModelManager, email: String, password: String) -> Result<UserDTO> {
-        let Some(user) = UserRepository::get_by_email(Ctx::root_ctx(), mm, &email).await? else {
-            return Err(ServiceError::NotFound(
-                "couldn't find corresponding user".to_string(),
-            ));
+    pub async fn login(
+        mm: &ModelManager,
+        email: String,
+        password: String,
+        organization: Option<&str>,
+        client: &ClientMeta,
+    ) -> Result<UserDTO> {
+        let tenant = OrganizationService::tenant_ctx(mm, organization).await?;
+        let user = UserRepository::get_by_email(tenant, mm, &email).await?;
+
+        // unknown emails, oauth-only accounts and wrong passwords all get the same
+        // error after the same amount of work.
+        let user = match user {
+            Some(user) if !user.password.is_empty() => {
+                if !verify(password.as_bytes(), &user.password)? {
+                    AuditService::record(
+                        mm,
+                        user.id,
+                        AUDIT_EVENT_LOGIN_FAILED,
+                        client,
+                        json!({ "method": LOGIN_METHOD_PASSWORD }),
+                    )
+                    .await;
+
+                    return Err(ServiceError::InvalidLoginAttmpt);
+                }
+
+                // only said once the password is known to be right.
+                if user.password_reset_required {
+                    return Err(ServiceError::ForbiddenWithMessage(String::from(
+                        "password reset required, follow the link sent by email",
+                    )));
+                }
+
+                user
+            }
+            _ => {
+                let _ = verify(password.as_bytes(), dummy_hash());
+                return Err(ServiceError::InvalidLoginAttmpt);
+            }
         };
 
-        if user.password.is_empty() {
-            return Err(ServiceError::ForbiddenWithMessage(String::from(
-                "wrong endpoint, should try oauth",
-            )));
+        Self::complete_login(mm, user, LOGIN_METHOD_PASSWORD, client).await
+    }
+
+    // last step shared by every login method: hand out the tokens,
+    // or a challenge to be answered at /login/mfa when the user has MFA enabled.
+    // `method` is how the user authenticated, it ends up in the login history.
+    pub async fn complete_login(
+        mm: &ModelManager,
+        user: User,
+        method: &str,
+        client: &ClientMeta,
+    ) -> Result<UserDTO> {
+        Self::ensure_can_login(&user)?;
+        let scope = EmailVerificationService::login_scope(&user)?;
+
+        if user.secret.is_some() {
+            let mfa_token = generate_random_string(MFA_TOKEN_LENGTH);
+
+            UserTokenRepository::create(
+                Ctx::root_ctx(),
+                mm,
+                user.id,
+                TOKEN_PURPOSE_MFA_CHALLENGE,
+                &sha256_hex(&mfa_token),
+                MFA_CHALLENGE_TTL_SECONDS,
+                Some(json!({ "method": method })),
+            )
+            .await?;
+
+            return Ok(user.into_mfa_dto(mfa_token));
         }
 
-        let is_match = verify(password.as_bytes(), &user.password)?;
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_LOGIN,
+            client,
+            json!({ "method": method }),
+        )
+        .await;
+
+        let authentication =
+            SessionService::start(mm, user.id, Authentication::new(method, false), client).await?;
+        let token = TokenService::issue_access_token(
+            mm,
+            &user,
+            scope,
+            user.organization_id,
+            &authentication,
+        )
+        .await?;
+        let refresh_token =
+            RefreshTokenService::issue_for_login(mm, &user, scope, &authentication).await?;
+
+        Ok(user.into_dto(Some(token), Some(refresh_token), None))
+    }
+
+    pub async fn verify_mfa(
+        mm: &ModelManager,
+        mfa_token: &str,
+        code: &str,
+        client: &ClientMeta,
+    ) -> Result<UserDTO> {
+        let token_hash = sha256_hex(mfa_token);
 
-        if !is_match {
+        // the challenge is only looked up here, a mistyped code shouldn't restart the login.
+        let Some(challenge) = UserTokenRepository::get_active(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_MFA_CHALLENGE,
+            &token_hash,
+        )
+        .await?
+        else {
             return Err(ServiceError::Unauthorized);
+        };
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, challenge.user_id).await?;
+
+        if let Err(err) = Self::verify_totp_code(mm, &user, code).await {
+            // the challenge is burnt after a few wrong codes, the login has to start over.
+            UserTokenRepository::record_failure(
+                Ctx::root_ctx(),
+                mm,
+                challenge.id,
+                MFA_CHALLENGE_MAX_ATTEMPTS,
+            )
+            .await?;
+
+            AuditService::record(
+                mm,
+                user.id,
+                AUDIT_EVENT_LOGIN_FAILED,
+                client,
+                json!({ "method": MFA_TYPE_TOTP }),
+            )
+            .await;
+
+            return Err(err);
         }
 
-        if user.secret.is_some() {
-            return Ok(user.into_dto(None, None, Some("TOTP".to_string())));
+        if UserTokenRepository::consume(
+            Ctx::root_ctx(),
+            mm,
+            TOKEN_PURPOSE_MFA_CHALLENGE,
+            &token_hash,
+        )
+        .await?
+        .is_none()
+        {
+            return Err(ServiceError::Unauthorized);
         }
 
-        // let's say this is the jwt token.
-        let token = Uuid::new_v4();
+        Self::ensure_can_login(&user)?;
+        let scope = EmailVerificationService::login_scope(&user)?;
 
-        Ok(user.into_dto(
-            Some(token.to_string()),
-            Some("supposethisisrefreshtoken".to_string()),
-            None,
-        ))
+        let method = challenge
+            .payload
+            .as_ref()
+            .and_then(|x| x.get("method"))
+            .and_then(|x| x.as_str())
+            .unwrap_or_default();
+
+        AuditService::record(
+            mm,
+            user.id,
+            AUDIT_EVENT_LOGIN,
+            client,
+            json!({ "method": method, "mfa": MFA_TYPE_TOTP }),
+        )
+        .await;
+
+        let authentication =
+            SessionService::start(mm, user.id, Authentication::new(method, true), client).await?;
+        let token = TokenService::issue_access_token(
+            mm,
+            &user,
+            scope,
+            user.organization_id,
+            &authentication,
+        )
+        .await?;
+        let refresh_token =
+            RefreshTokenService::issue_for_login(mm, &user, scope, &authentication).await?;
+
+        Ok(user.into_dto(Some(token), Some(refresh_token), None))
+    }
+
+    // checked by every login method before handing out tokens.
+    pub fn ensure_can_login(user: &User) -> Result<()> {
+        if user.disabled_at.is_some() {
+            return Err(ServiceError::ForbiddenWithMessage(String::from(
+                "account is disabled",
+            )));
+        }
+
+        if user.is_suspended() {
+            return Err(ServiceError::AccountSuspended {
+                reason: user.suspension_reason.clone(),
+                until: user.suspended_until,
+            });
+        }
+
+        Ok(())
+    }
+
+    // check a code from the user's authenticator app, for users with MFA enabled.
+    // A code is only good once: its time step is recorded, the codes of that step and the
+    // ones before are rejected afterwards.
+    pub async fn verify_totp_code(mm: &ModelManager, user: &User, code: &str) -> Result<()> {
+        let Some(secret) = user.secret.as_deref() else {
+            return Err(ServiceError::Unauthorized);
+        };
+
+        let code = code
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| ServiceError::InvalidLoginAttmpt)?;
+
+        let hotp = Hotp::new(
+            Some(HMAC::HMACSHA256),
+            MFA_ISSUER,
+            &user.email,
+            secret,
+            MFA_CODE_LENGTH,
+        );
+
+        let Some(step) = hotp.totp_step(code, Utc::now().timestamp() as u64, 1)? else {
+            return Err(ServiceError::InvalidLoginAttmpt);
+        };
+
+        if !UserRepository::claim_totp_step(Ctx::root_ctx(), mm, user.id, step as i64).await? {
+            return Err(ServiceError::InvalidLoginAttmpt);
+        }
+
+        Ok(())
     }
 
-    pub async fn set_mfa(mm: &ModelManager, ctx: &Ctx) -> Result<BaseResponse<MFAResponse>> {
-        let mut user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+    pub async fn set_mfa(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        client: &ClientMeta,
+    ) -> Result<BaseResponse<MFAResponse>> {
+        let secret = generate_random_string(20);
 
-        user.secret = Some(generate_random_string(20));
+        let user = UserRepository::update(
+            Ctx::root_ctx(),
+            mm,
+            &(ctx.user_id() as i64),
+            &UserPatch {
+                secret: Some(Some(secret.clone())),
+                ..Default::default()
+            },
+        )
+        .await?;
 
-        UserRepository::update(Ctx::root_ctx(), mm, &(ctx.user_id() as i64), &user).await?;
+        AuditService::record(mm, user.id, AUDIT_EVENT_MFA_ENABLED, client, json!({})).await;
 
         let hotp = Hotp::new(
             Some(HMAC::HMACSHA256),
-            "authservice",
+            MFA_ISSUER,
             &user.email,
-            user.secret.unwrap().as_ref(),
-            6,
+            &secret,
+            MFA_CODE_LENGTH,
         );
 
         Ok(BaseResponse::new(
@@ -102,4 +401,198 @@ impl UserService {
             },
         ))
     }
+
+    pub async fn get_profile(mm: &ModelManager, ctx: &Ctx) -> Result<ProfileDTO> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        Ok(user.into())
+    }
+
+    // `patch` is a JSON merge patch (RFC 7396) applied on `{ name, user_metadata }`,
+    // only the columns that end up different are written.
+    pub async fn update_profile(
+        mm: &ModelManager,
+        ctx: &Ctx,
+        patch: &serde_json::Value,
+    ) -> Result<ProfileDTO> {
+        let Some(fields) = patch.as_object() else {
+            return Err(ServiceError::BadRequest(String::from(
+                "merge patch must be a json object",
+            )));
+        };
+
+        if let Some(field) = fields
+            .keys()
+            .find(|x| !PROFILE_PATCH_FIELDS.contains(&x.as_str()))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "{field} can't be changed through this endpoint"
+            )));
+        }
+
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, ctx.user_id() as i64).await?;
+
+        let mut document = json!({ "name": user.name, "user_metadata": user.user_metadata });
+        merge_patch(&mut document, patch);
+
+        let name = match document.get("name").and_then(|x| x.as_str()).map(str::trim) {
+            Some(x) if !x.is_empty() && x.chars().count() <= MAX_NAME_LENGTH => x.to_string(),
+            _ => {
+                return Err(ServiceError::BadRequest(format!(
+                    "name must be a non empty string of at most {MAX_NAME_LENGTH} characters"
+                )))
+            }
+        };
+
+        let user_metadata = document
+            .get("user_metadata")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        MetadataService::validate(MetadataKind::User, &user_metadata)?;
+
+        let changes = UserPatch {
+            name: (name != user.name).then_some(name),
+            user_metadata: (user_metadata != user.user_metadata).then_some(user_metadata),
+            ..Default::default()
+        };
+
+        if changes.name.is_none() && changes.user_metadata.is_none() {
+            return Ok(user.into());
+        }
+
+        let user = UserRepository::update(Ctx::root_ctx(), mm, &user.id, &changes).await?;
+
+        Ok(user.into())
+    }
+
+    // administrators only, `patch` is a JSON merge patch applied on the current app_metadata.
+    pub async fn update_app_metadata(
+        mm: &ModelManager,
+        user_id: i64,
+        patch: &serde_json::Value,
+    ) -> Result<ProfileDTO> {
+        let user = UserRepository::get_by_id(Ctx::root_ctx(), mm, user_id).await?;
+
+        let mut app_metadata = user.app_metadata;
+        merge_patch(&mut app_metadata, patch);
+
+        MetadataService::validate(MetadataKind::App, &app_metadata)?;
+
+        let user = UserRepository::update(
+            Ctx::root_ctx(),
+            mm,
+            &user_id,
+            &UserPatch {
+                app_metadata: Some(app_metadata),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(user.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bcrypt::hash;
+    use sqlx::PgPool;
+
+    use super::{UserService, SIGNUP_MESSAGE};
+    use crate::{
+        http::{
+            request::{client::ClientMeta, user::CreateUserDTO},
+            response::{BaseResponse, MessageResponse},
+        },
+        model::ModelManager,
+        service::ServiceError,
+    };
+
+    // `users.id` has no default.
+    async fn insert_user(mm: &ModelManager, id: i64, email: &str, password: &str) {
+        sqlx::query("INSERT INTO users (id,name,email,password,created_at) VALUES ($1, 'user', $2, $3, current_timestamp)")
+            .bind(id)
+            .bind(email)
+            .bind(password)
+            .execute(&mm.db)
+            .await
+            .unwrap();
+    }
+
+    async fn login(mm: &ModelManager, email: &str, password: &str) -> ServiceError {
+        UserService::login(
+            mm,
+            email.to_string(),
+            password.to_string(),
+            None,
+            &ClientMeta::default(),
+        )
+        .await
+        .err()
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn login_errors_dont_tell_accounts_apart(db: PgPool) {
+        let mm = ModelManager { db };
+        insert_user(&mm, 1, "user@example.com", &hash("password", 4).unwrap()).await;
+        // signed up with google, no password.
+        insert_user(&mm, 2, "google@example.com", "").await;
+
+        assert!(matches!(
+            login(&mm, "user@example.com", "wrong").await,
+            ServiceError::InvalidLoginAttmpt
+        ));
+        assert!(matches!(
+            login(&mm, "unknown@example.com", "wrong").await,
+            ServiceError::InvalidLoginAttmpt
+        ));
+        assert!(matches!(
+            login(&mm, "google@example.com", "").await,
+            ServiceError::InvalidLoginAttmpt
+        ));
+    }
+
+    #[sqlx::test]
+    async fn signup_with_a_registered_email(db: PgPool) {
+        let mm = ModelManager { db };
+        insert_user(&mm, 1, "user@example.com", "").await;
+        // signups need an id default to get as far as the conflict.
+        sqlx::query("ALTER TABLE users ALTER COLUMN id ADD GENERATED BY DEFAULT AS IDENTITY (START WITH 100)")
+            .execute(&mm.db)
+            .await
+            .unwrap();
+
+        let response = UserService::create_user(
+            &mm,
+            &CreateUserDTO {
+                name: String::from("someone else"),
+                email: String::from("user@example.com"),
+                password: String::from("password"),
+                auth_provider: None,
+                auth_provider_user_id: None,
+                secret: None,
+                organization: None,
+                invitation: None,
+            },
+            &ClientMeta::default(),
+        )
+        .await
+        .unwrap();
+
+        // the response of any signup.
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            serde_json::to_value(BaseResponse::new(202, MessageResponse::new(SIGNUP_MESSAGE)))
+                .unwrap()
+        );
+
+        let (count,): (i64,) =
+            sqlx::query_as("SELECT count(*) FROM users WHERE email = 'user@example.com'")
+                .fetch_one(&mm.db)
+                .await
+                .unwrap();
+        assert_eq!(count, 1);
+    }
 }